@@ -7,12 +7,70 @@ pub enum ContractError {
     StreamDoesNotExist,
     EndDateAndDurationAreEmpty,
     StreamDurationShouldBeGreater,
-    StreamEndDateShouldBeLater,
+    StreamEndDateInPast,
     StreamAvailableBalanceIsZero,
     EmptyFunds,
     ExpectedWithdrawalAmountExceedsStreamAvailableBalance,
     RecipientCannotBePayer,
     WithdrawTransferFailed,
     WithdrawalAmountShouldBeGreaterThanZero,
-    Unexpected,
+    BatchTooLarge,
+    StreamImmutable,
+    InvalidStreamDuration,
+    ArithmeticOverflow,
+    TvlCapExceeded,
+    EndDateDurationConflict,
+    InvalidCommitment,
+    NoCommitmentDue,
+    CommitmentAmountMismatch,
+    WrapFailed,
+    StreamAlreadyCancelled,
+    OnlyPayerCanCancel,
+    StreamAlreadyFinished,
+    BatchLengthMismatch,
+    BatchAmountMismatch,
+    StreamStartDateShouldBeLater,
+    FeeTooHigh,
+    InvalidAccount,
+    StreamNotEmpty,
+    StreamNotFinished,
+    UnsupportedAsset,
+    TokenTransferFailed,
+    StreamCliffShouldBeEarlier,
+    TransferBelowExistential,
+    InsufficientContractBalance,
+    ContractPaused,
+    InvalidVestingInterval,
+    InvalidRecipient,
+    StreamNotReclaimable,
+    InvalidSplit,
+    InsufficientAllowance,
+    StreamLimitReached,
+    CannotShortenStream,
+    AmountTooSmall,
+    AmountTooLarge,
+    Reentrancy,
+    ReductionExceedsUnvestedBalance,
+    RecipientNotAllowed,
+    StreamDurationTooLong,
+    TemplateDoesNotExist,
+    StreamIdAlreadyExists,
+    StreamFullyWithdrawn,
+    MigrationFinalized,
+    WithdrawOnCooldown,
+    DuplicateRecipient,
+    StreamNotCancellable,
+    StreamIdSpaceExhausted,
+    StartDateOffsetConflict,
+    AmountBelowExistential,
+    InvalidClaim,
+    TooManyCommitments,
+    WithdrawCooldownTooLong,
+    DuplicateStreamId,
+    TooManyContributors,
+    ClaimAlreadyExists,
+    ClaimAlreadyCommitted,
+    ClaimNotCommitted,
+    ClaimRevealTooEarly,
+    StreamHasContributors,
 }
@@ -0,0 +1,216 @@
+use ink_env::{
+    call::{build_call, Call, ExecutionInput, Selector},
+    AccountId, DefaultEnvironment,
+};
+use ink_prelude::{string::String, vec::Vec};
+
+use crate::errors::ContractError;
+
+/// Selector of the `Wrapper::deposit_for` message.
+///
+/// The wrapper contract should expose a payable message `deposit_for(account: AccountId)` that credits `account` with wrapped tokens for the transferred value.
+pub const WRAPPER_DEPOSIT_FOR_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("Wrapper::deposit_for");
+
+/// Wraps native tokens crediting the wrapped tokens to an account.
+///
+/// Parameters:
+/// - `wrapper`: AccountId of the wrapper contract.
+/// - `account`: AccountId to be credited with the wrapped tokens.
+/// - `amount`: Amount of native tokens to be wrapped.
+///
+/// Errors:
+/// - WrapFailed
+pub fn wrapper_deposit_for(
+    wrapper: AccountId,
+    account: AccountId,
+    amount: u128,
+) -> Result<(), ContractError> {
+    build_call::<DefaultEnvironment>()
+        .call_type(
+            Call::new()
+                .callee(wrapper)
+                .gas_limit(0)
+                .transferred_value(amount),
+        )
+        .exec_input(
+            ExecutionInput::new(Selector::new(WRAPPER_DEPOSIT_FOR_SELECTOR)).push_arg(account),
+        )
+        .returns::<()>()
+        .fire()
+        .map_err(|_| ContractError::WrapFailed)
+}
+
+/// Selector of the `StreamSubscriber::notify` message.
+///
+/// Subscriber contracts should expose a message `notify(stream_id: u64, event_kind: StreamEventKind)`.
+pub const SUBSCRIBER_NOTIFY_SELECTOR: [u8; 4] =
+    ink_lang::selector_bytes!("StreamSubscriber::notify");
+
+/// Maximum gas that a subscriber can consume when notified, so it can't exhaust the caller's gas.
+pub const SUBSCRIBER_NOTIFY_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Kind of stream lifecycle change notified to subscribers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum StreamEventKind {
+    Withdrawal,
+    Cancellation,
+}
+
+/// Notifies a subscriber contract about a stream lifecycle change.
+///
+/// Parameters:
+/// - `subscriber`: AccountId of the subscriber contract.
+/// - `stream_id`: The stream ID.
+/// - `event_kind`: The kind of lifecycle change.
+///
+/// Behavior:
+/// - The notification is best-effort: failures of the subscriber are ignored.
+pub fn notify_subscriber(subscriber: AccountId, stream_id: u64, event_kind: StreamEventKind) {
+    let _ = build_call::<DefaultEnvironment>()
+        .call_type(
+            Call::new()
+                .callee(subscriber)
+                .gas_limit(SUBSCRIBER_NOTIFY_GAS_LIMIT),
+        )
+        .exec_input(
+            ExecutionInput::new(Selector::new(SUBSCRIBER_NOTIFY_SELECTOR))
+                .push_arg(stream_id)
+                .push_arg(event_kind),
+        )
+        .returns::<()>()
+        .fire();
+}
+
+/// Selector of the `StreamRecipient::on_stream_created` message.
+///
+/// Recipient contracts can expose a message `on_stream_created(stream_id: u64)` to be notified about new streams.
+pub const RECIPIENT_ON_STREAM_CREATED_SELECTOR: [u8; 4] =
+    ink_lang::selector_bytes!("StreamRecipient::on_stream_created");
+
+/// Notifies a recipient contract about a new stream.
+///
+/// Parameters:
+/// - `recipient`: AccountId of the recipient contract.
+/// - `stream_id`: The stream ID.
+///
+/// Behavior:
+/// - The notification is best-effort: failures of the recipient are ignored, so it can't block the stream creation.
+/// - The recipient can consume up to `SUBSCRIBER_NOTIFY_GAS_LIMIT` gas.
+///
+/// NOTES
+/// -----
+/// - The off-chain test environment doesn't support cross-contract calls, so this call is not covered by the unit tests. It has to be checked on a node against a recipient contract.
+pub fn notify_stream_created(recipient: AccountId, stream_id: u64) {
+    let _ = build_call::<DefaultEnvironment>()
+        .call_type(
+            Call::new()
+                .callee(recipient)
+                .gas_limit(SUBSCRIBER_NOTIFY_GAS_LIMIT),
+        )
+        .exec_input(
+            ExecutionInput::new(Selector::new(RECIPIENT_ON_STREAM_CREATED_SELECTOR))
+                .push_arg(stream_id),
+        )
+        .returns::<()>()
+        .fire();
+}
+
+/// Selector of the `PSP22::balance_of` message.
+pub const PSP22_BALANCE_OF_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("PSP22::balance_of");
+
+/// Selector of the `PSP22::transfer` message.
+pub const PSP22_TRANSFER_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("PSP22::transfer");
+
+/// Selector of the `PSP22::transfer_from` message.
+pub const PSP22_TRANSFER_FROM_SELECTOR: [u8; 4] = ink_lang::selector_bytes!("PSP22::transfer_from");
+
+/// Errors returned by PSP22 token contracts.
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PSP22Error {
+    Custom(String),
+    InsufficientBalance,
+    InsufficientAllowance,
+    ZeroRecipientAddress,
+    ZeroSenderAddress,
+    SafeTransferCheckFailed(String),
+}
+
+/// Returns the token balance of an account.
+///
+/// Parameters:
+/// - `token`: AccountId of the PSP22 token contract.
+/// - `owner`: AccountId to query the balance of.
+///
+/// Behavior:
+/// - If the token call fails, the balance is considered zero.
+pub fn psp22_balance_of(token: AccountId, owner: AccountId) -> u128 {
+    build_call::<DefaultEnvironment>()
+        .call_type(Call::new().callee(token).gas_limit(0))
+        .exec_input(ExecutionInput::new(Selector::new(PSP22_BALANCE_OF_SELECTOR)).push_arg(owner))
+        .returns::<u128>()
+        .fire()
+        .unwrap_or(0)
+}
+
+/// Transfers tokens from the caller contract to an account.
+///
+/// Parameters:
+/// - `token`: AccountId of the PSP22 token contract.
+/// - `to`: AccountId receiving the tokens.
+/// - `value`: Amount of tokens to be transferred.
+///
+/// Errors:
+/// - TokenTransferFailed
+pub fn psp22_transfer(token: AccountId, to: AccountId, value: u128) -> Result<(), ContractError> {
+    let result = build_call::<DefaultEnvironment>()
+        .call_type(Call::new().callee(token).gas_limit(0))
+        .exec_input(
+            ExecutionInput::new(Selector::new(PSP22_TRANSFER_SELECTOR))
+                .push_arg(to)
+                .push_arg(value)
+                .push_arg(Vec::<u8>::new()),
+        )
+        .returns::<Result<(), PSP22Error>>()
+        .fire();
+
+    match result {
+        Ok(Ok(())) => Ok(()),
+        _ => Err(ContractError::TokenTransferFailed),
+    }
+}
+
+/// Transfers tokens on behalf of an account, using the allowance given to the caller contract.
+///
+/// Parameters:
+/// - `token`: AccountId of the PSP22 token contract.
+/// - `from`: AccountId sending the tokens. Should have approved the caller contract.
+/// - `to`: AccountId receiving the tokens.
+/// - `value`: Amount of tokens to be transferred.
+///
+/// Errors:
+/// - TokenTransferFailed
+pub fn psp22_transfer_from(
+    token: AccountId,
+    from: AccountId,
+    to: AccountId,
+    value: u128,
+) -> Result<(), ContractError> {
+    let result = build_call::<DefaultEnvironment>()
+        .call_type(Call::new().callee(token).gas_limit(0))
+        .exec_input(
+            ExecutionInput::new(Selector::new(PSP22_TRANSFER_FROM_SELECTOR))
+                .push_arg(from)
+                .push_arg(to)
+                .push_arg(value)
+                .push_arg(Vec::<u8>::new()),
+        )
+        .returns::<Result<(), PSP22Error>>()
+        .fire();
+
+    match result {
+        Ok(Ok(())) => Ok(()),
+        _ => Err(ContractError::TokenTransferFailed),
+    }
+}
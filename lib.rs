@@ -7,6 +7,7 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 mod errors;
+pub mod interfaces;
 pub mod stream;
 pub mod utils;
 use ink_lang as ink;
@@ -14,569 +15,14349 @@ use ink_lang as ink;
 #[ink::contract]
 pub mod streams_contract {
     use crate::errors::ContractError;
-    use crate::stream::Stream;
+    use crate::interfaces::{
+        notify_stream_created, notify_subscriber, psp22_balance_of, psp22_transfer,
+        psp22_transfer_from, wrapper_deposit_for, StreamEventKind,
+    };
+    use crate::stream::{
+        ContractConfig, Rounding, Stream, StreamAudit, StreamDetails, StreamOptions, StreamStatus,
+        StreamTemplate, TimeUnit, BPS_DENOMINATOR, CONTRACT_VERSION, GRACE_PERIOD,
+        MAX_AUDIT_COMMITMENTS, MAX_BATCH_SIZE, MAX_COMMITMENTS, MAX_CONTRIBUTORS, MAX_FEE_BPS,
+        MAX_PAGE_SIZE, MAX_STREAMS_PER_PAYER, MAX_WITHDRAWAL_HISTORY, NATIVE_TOKEN_DECIMALS,
+        STREAM_MINIMUM_DURATION,
+    };
     use crate::utils::{
-        get_current_time_in_seconds, validate_and_generate_stream_end_date,
-        validate_recipient_withdrawal_amount, validate_stream_creation_parameters,
+        generate_stream_start_date_from_offset, get_current_time_in_seconds, get_minimum_balance,
+        hash_claim_commitment, hash_claim_preimage, is_contract_account, mul_div,
+        proportional_share, validate_and_generate_stream_cliff_date,
+        validate_and_generate_stream_end_date, validate_and_generate_stream_start_date,
+        validate_and_generate_vesting_kind, validate_recipient_withdrawal_amount,
+        validate_stream_creation_parameters, validate_stream_duration, validate_tvl_cap,
     };
     use ink_lang::utils::initialize_contract;
+    use ink_prelude::vec::Vec;
     use ink_storage::traits::SpreadAllocate;
     use ink_storage::Mapping;
 
+    /// Emitted when a new stream is created.
+    #[ink(event)]
+    pub struct StreamCreated {
+        #[ink(topic)]
+        stream_id: u64,
+        #[ink(topic)]
+        payer: AccountId,
+        #[ink(topic)]
+        recipient: AccountId,
+        amount: u128,
+        start_date: u64,
+        end_date: u64,
+        /// Part of `amount` only available once the stream is finished.
+        completion_bonus: u128,
+    }
+
+    /// Emitted when the recipient withdraws funds from a stream.
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        stream_id: u64,
+        #[ink(topic)]
+        recipient: AccountId,
+        /// Net amount received by the recipient.
+        amount: u128,
+        /// Protocol fee transferred to the contract owner.
+        fee: u128,
+    }
+
+    /// Emitted when the contract ownership is transferred.
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous: AccountId,
+        #[ink(topic)]
+        new: AccountId,
+    }
+
+    /// Emitted when the current balance of a stream reaches zero.
+    #[ink(event)]
+    pub struct StreamDrained {
+        #[ink(topic)]
+        stream_id: u64,
+    }
+
+    /// Emitted when a stream is cancelled by its payer.
+    #[ink(event)]
+    pub struct StreamCancelled {
+        #[ink(topic)]
+        stream_id: u64,
+        /// Vested balance not withdrawn yet, paid to the recipient.
+        recipient_paid: u128,
+        /// Share of the unvested balance refunded to the payer. The co-payers shares are reported by `ContributionRefunded`.
+        payer_refunded: u128,
+    }
+
+    /// Emitted when a stream is renounced by its recipient.
+    #[ink(event)]
+    pub struct StreamRenounced {
+        #[ink(topic)]
+        stream_id: u64,
+        /// Vested balance not withdrawn yet, paid to the recipient.
+        recipient_paid: u128,
+        /// Share of the unvested balance refunded to the payer. The co-payers shares are reported by `ContributionRefunded`.
+        payer_refunded: u128,
+    }
+
+    /// Emitted when a co-payer of a stream is refunded its share of a refund.
+    #[ink(event)]
+    pub struct ContributionRefunded {
+        #[ink(topic)]
+        stream_id: u64,
+        #[ink(topic)]
+        contributor: AccountId,
+        /// Funds transferred to the co-payer.
+        amount: u128,
+    }
+
+    /// Emitted when a withdrawal leaves the contract balance below the funds owed to streams.
+    #[ink(event)]
+    pub struct SolvencyWarning {
+        /// Balance held by the contract after the withdrawal.
+        held: u128,
+        /// Sum of the current balance of all the streams.
+        outstanding: u128,
+    }
+
+    /// Emitted when the payer adds funds to a stream.
+    #[ink(event)]
+    pub struct StreamToppedUp {
+        #[ink(topic)]
+        stream_id: u64,
+        /// Funds added to the stream.
+        added: u128,
+        /// Stream current balance after the top-up.
+        new_balance: u128,
+    }
+
     #[ink(storage)]
     #[derive(SpreadAllocate)]
     pub struct StreamsContract {
         pub owner: AccountId,
         next_stream_id: u64,
         streams: Mapping<u64, Stream>,
+        total_locked: u128,
+        tvl_cap: Option<u128>,
+        commitments: Mapping<u64, Vec<(u64, u128)>>,
+        default_duration: Option<u64>,
+        subscribers: Mapping<u64, AccountId>,
+        min_duration: u64,
+        streams_by_recipient: Mapping<AccountId, Vec<u64>>,
+        streams_by_payer: Mapping<AccountId, Vec<u64>>,
+        fee_bps: u16,
+        token: Option<AccountId>,
+        paused: bool,
+        grace_period: u64,
+        allowances: Mapping<(AccountId, AccountId), u128>,
+        max_streams_per_payer: u32,
+        min_stream_amount: u128,
+        max_stream_amount: u128,
+        /// Set while a withdrawal is in progress. A mapping, unlike plain fields, is written to storage immediately, so re-entrant calls can see it.
+        reentrancy_lock: Mapping<(), bool>,
+        allowlist_enabled: bool,
+        allowed_recipients: Mapping<AccountId, bool>,
+        total_streamed_volume: u128,
+        total_withdrawn_volume: u128,
+        max_duration: u64,
+        templates: Mapping<u64, Vec<StreamTemplate>>,
+        migration_finalized: bool,
+        withdraw_agents: Mapping<u64, AccountId>,
+        existential_deposit: u128,
+        claimable_streams: Mapping<[u8; 32], (AccountId, u128, u64)>,
+        withdrawal_history: Mapping<u64, Vec<(u64, u128)>>,
+        fee_exempt: Mapping<AccountId, bool>,
+        total_escrowed: u128,
+        stream_count: u64,
+        contributions: Mapping<(u64, AccountId), u128>,
+        contributors: Mapping<u64, Vec<AccountId>>,
+        claim_commitments: Mapping<[u8; 32], BlockNumber>,
     }
 
     impl StreamsContract {
         #[ink(constructor)]
         pub fn new() -> Self {
+            Self::new_with_min_duration(STREAM_MINIMUM_DURATION)
+        }
+
+        /// Instantiates the contract with a custom stream minimum duration.
+        ///
+        /// Parameters:
+        /// - `min_duration`: The minimum duration that a stream can have, measured in seconds.
+        #[ink(constructor)]
+        pub fn new_with_min_duration(min_duration: u64) -> Self {
             initialize_contract(|contract: &mut Self| {
                 contract.owner = Self::env().caller();
                 contract.next_stream_id = 1;
                 contract.streams = <Mapping<u64, Stream>>::default();
+                contract.total_locked = 0;
+                contract.tvl_cap = None;
+                contract.commitments = <Mapping<u64, Vec<(u64, u128)>>>::default();
+                contract.default_duration = None;
+                contract.subscribers = <Mapping<u64, AccountId>>::default();
+                contract.min_duration = min_duration;
+                contract.streams_by_recipient = <Mapping<AccountId, Vec<u64>>>::default();
+                contract.streams_by_payer = <Mapping<AccountId, Vec<u64>>>::default();
+                contract.fee_bps = 0;
+                contract.token = None;
+                contract.paused = false;
+                contract.grace_period = GRACE_PERIOD;
+                contract.allowances = <Mapping<(AccountId, AccountId), u128>>::default();
+                contract.max_streams_per_payer = MAX_STREAMS_PER_PAYER;
+                contract.min_stream_amount = 0;
+                contract.max_stream_amount = 0;
+                contract.reentrancy_lock = <Mapping<(), bool>>::default();
+                contract.allowlist_enabled = false;
+                contract.allowed_recipients = <Mapping<AccountId, bool>>::default();
+                contract.total_streamed_volume = 0;
+                contract.total_withdrawn_volume = 0;
+                contract.max_duration = 0;
+                contract.templates = <Mapping<u64, Vec<StreamTemplate>>>::default();
+                contract.migration_finalized = false;
+                contract.withdraw_agents = <Mapping<u64, AccountId>>::default();
+                contract.existential_deposit = 0;
+                contract.claimable_streams = <Mapping<[u8; 32], (AccountId, u128, u64)>>::default();
+                contract.withdrawal_history = <Mapping<u64, Vec<(u64, u128)>>>::default();
+                contract.fee_exempt = <Mapping<AccountId, bool>>::default();
+                contract.total_escrowed = 0;
+                contract.stream_count = 0;
+                contract.contributions = <Mapping<(u64, AccountId), u128>>::default();
+                contract.contributors = <Mapping<u64, Vec<AccountId>>>::default();
+                contract.claim_commitments = <Mapping<[u8; 32], BlockNumber>>::default();
             })
         }
 
+        /// Instantiates the contract to stream a PSP22 token instead of the native token.
+        ///
+        /// Parameters:
+        /// - `token`: The PSP22 token contract address.
+        ///
+        /// NOTES
+        /// -----
+        /// - Streams are created with `create_psp22_stream`. Payouts are transferred in the PSP22 token.
+        /// - `top_up_stream`, `add_commitment` and `fulfill_commitment` are not supported, since they take the funds from the transferred value.
+        #[ink(constructor)]
+        pub fn new_psp22(token: AccountId) -> Self {
+            let mut contract = Self::new_with_min_duration(STREAM_MINIMUM_DURATION);
+            contract.token = Some(token);
+            contract
+        }
+
+        /// Instantiates the contract with a custom limit of active streams per payer.
+        ///
+        /// Parameters:
+        /// - `max_streams_per_payer`: The maximum number of active streams a payer can have.
+        #[ink(constructor)]
+        pub fn new_with_max_streams_per_payer(max_streams_per_payer: u32) -> Self {
+            let mut contract = Self::new_with_min_duration(STREAM_MINIMUM_DURATION);
+            contract.max_streams_per_payer = max_streams_per_payer;
+            contract
+        }
+
         /// Creates a token stream from the sender to the specified recipient setting the end date or the duration.
         ///
         /// Parameters:
         /// - `recipient`: The recipient wallet address of the stream.
         /// - `end_date`: The end date of the stream measured in seconds. If not specified, the stream will be created with the duration.
         /// - `duration`: The duration of the stream measured in seconds. If not specified, the stream will be created with the end date.
+        /// - `options`: The optional settings of the stream, see `StreamOptions`. `StreamOptions::default()` creates a mutable and cancellable linear stream starting immediately.
         /// - **Transaction funds:** The amount of funds to be transferred to the recipient through the stream.
         ///
         /// Validations:
+        /// - The contract should not be paused.
+        /// - The sender should not exceed the maximum number of active streams per payer.
+        /// - The recipient can't be the zero account or the streams contract.
         /// - The sender can't be the recipient.
         /// - The sender should send funds in the transaction.
+        /// - The funds should be within the stream amount bounds, if configured.
+        /// - The recipient should be allowed, if the recipient allowlist is enabled.
         /// - The date parameters should be valid.
-        ///   * `end_date` and `duration` cannot be both empty.
+        ///   * `options.start_date` should not be earlier than the current date.
+        ///   * `options.start_date` and `options.start_offset` cannot be both specified.
+        ///   * `end_date` and `duration` cannot be both empty, unless a default duration is configured.
+        ///   * If both `end_date` and `duration` are specified, they should produce the same end date.
         ///   * `end_date` should be later than the current date.
         ///   * The stream duration should be greater than the **minimum duration**.
+        ///   * The stream duration should not be greater than the **maximum duration**, if configured.
+        ///   * The cliff date should not be later than `end_date`.
+        ///   * The interval of a stepped stream should be greater than zero and divide the stream duration evenly.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
         ///
         /// Behavior:
         /// - A new stream with a unique ID will be stored in a mapping structure.
         /// - The next available ID will be increased by 1.
+        /// - The stream funds will be added to the total locked funds.
+        /// - If `options.notify` is `true` and the recipient is a contract, its `on_stream_created` message will be called. Failures are ignored.
         ///
         /// Returns:
         /// - The created stream ID.
         ///
         /// Errors:
+        /// - ContractPaused
+        /// - StreamLimitReached
+        /// - InvalidRecipient
         /// - RecipientCannotBePayer
         /// - EmptyFunds
+        /// - AmountTooSmall
+        /// - AmountTooLarge
+        /// - RecipientNotAllowed
+        /// - StartDateOffsetConflict
+        /// - StreamStartDateShouldBeLater
         /// - EndDateAndDurationAreEmpty
-        /// - StreamEndDateShouldBeLater
+        /// - EndDateDurationConflict
+        /// - StreamEndDateInPast
         /// - StreamDurationShouldBeGreater
+        /// - StreamDurationTooLong
+        /// - StreamCliffShouldBeEarlier
+        /// - InvalidVestingInterval
+        /// - ArithmeticOverflow
+        /// - TvlCapExceeded
+        /// - StreamIdSpaceExhausted
         ///
         /// NOTES
         /// -----
-        /// - The current stream **minimum duration** is 5 minutes.
-        /// - The stream starts immediately after it is created.
+        /// - The stream **minimum duration** is configured when the contract is instantiated. It is 5 minutes by default.
+        /// - Unless `options.start_date` or `options.start_offset` is specified, the stream starts immediately after it is created.
+        /// - `duration` is measured from the stream start date.
+        /// - Immutable streams reject every payer-side modification, withdrawals are not affected.
+        /// - Non-cancellable streams can still be modified in ways that don't take vested or unvested funds from the recipient, such as topped up, unless they are immutable.
+        /// - Once the cliff date is reached, the balance vested since the start date becomes available at once.
+        /// - Funds lower than the duration in seconds are allowed, they unlock one token every `duration / funds` seconds.
         #[ink(message, payable)]
         pub fn create_stream(
             &mut self,
             recipient: AccountId,
             end_date: Option<u64>,
             duration: Option<u64>,
+            options: StreamOptions,
         ) -> Result<u64, ContractError> {
-            let start_date = get_current_time_in_seconds(&self);
+            self.ensure_not_paused()?;
             let caller = self.env().caller();
-            let stream_funds = self.env().transferred_value();
+            self.ensure_stream_slots(caller, 1)?;
+            let stream_funds = self.transferred_funds()?;
 
-            validate_stream_creation_parameters(caller, recipient, stream_funds)?;
-            let end_date = validate_and_generate_stream_end_date(end_date, duration, start_date)?;
+            let new_stream = self.build_new_stream_with_options(
+                caller,
+                recipient,
+                end_date,
+                duration,
+                stream_funds,
+                &options,
+            )?;
+            validate_tvl_cap(self.total_locked, stream_funds, self.tvl_cap)?;
 
-            let new_stream = Stream::new(caller, recipient, stream_funds, start_date, end_date);
+            self.store_new_stream_with_options(&new_stream, &options)
+        }
 
-            let new_stream_id = self.next_stream_id.clone().into();
-            self.streams.insert(new_stream_id, &new_stream);
-            self.next_stream_id += 1;
+        /// Creates a token stream, returning the stored stream along with its ID.
+        ///
+        /// Parameters:
+        /// - Same as `create_stream`.
+        ///
+        /// Validations:
+        /// - Same as `create_stream`.
+        ///
+        /// Behavior:
+        /// - Same as `create_stream`.
+        ///
+        /// Returns:
+        /// - A tuple with the created stream ID and the stream as stored, including its computed dates.
+        ///
+        /// Errors:
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_stream_detailed(
+            &mut self,
+            recipient: AccountId,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            options: StreamOptions,
+        ) -> Result<(u64, Stream), ContractError> {
+            let stream_id = self.create_stream(recipient, end_date, duration, options)?;
 
-            Ok(new_stream_id)
+            Ok((stream_id, self.get_stream_by_id(stream_id)?))
         }
 
-        /// Withdraws tokens from a stream. The recipient can specify the expected amount of tokens or withdraw all the available balance.
+        /// Creates a token stream from the sender to the specified recipient setting the duration in a time unit.
         ///
         /// Parameters:
-        /// - `stream_id`: The stream ID.
-        /// - `withdrawal_amount`: The amount of tokens to be withdrawn. If not specified, all the available balance will be withdrawn.
+        /// - `recipient`: The recipient wallet address of the stream.
+        /// - `count`: The duration of the stream, measured in `unit`.
+        /// - `unit`: The unit of `count`.
+        /// - **Transaction funds:** The amount of funds to be transferred to the recipient through the stream.
         ///
         /// Validations:
-        /// - The stream should exist.
-        /// - The sender should be the recipient of the stream.
-        /// - The expected withdrawal amount should be greater or equal than the available balance.
+        /// - The duration in seconds should not overflow.
+        /// - Same as `create_stream`.
         ///
         /// Behavior:
-        /// - The stream available balance will be calculated based on the elapsed time.
-        /// - The current stream balance will be reduced by the withdrawal amount.
-        /// - The requested funds will be transfered to the sender.
+        /// - Same as `create_stream`, with a duration of `count` units starting immediately.
         ///
         /// Returns:
-        /// - The amount of tokens withdrawn.
+        /// - The created stream ID.
         ///
         /// Errors:
-        /// - Unauthorized
-        /// - WithdrawalAmountShouldBeGreaterThanZero
-        /// - StreamAvailableBalanceisZero
-        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
-        /// - WithdrawTransferFailed
-        #[ink(message)]
-        pub fn recipient_withdraw(
+        /// - ArithmeticOverflow
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_stream_units(
             &mut self,
-            stream_id: u64,
-            withdrawal_amount: Option<u128>,
-        ) -> Result<u128, ContractError> {
-            validate_recipient_withdrawal_amount(withdrawal_amount)?;
-            let mut stream = self.get_stream_by_id(stream_id)?;
-            stream.has_permission_to_withdraw(self.env().caller())?;
+            recipient: AccountId,
+            count: u64,
+            unit: TimeUnit,
+        ) -> Result<u64, ContractError> {
+            let duration = unit.to_seconds(count)?;
 
-            let available_balance =
-                stream.get_available_balance(get_current_time_in_seconds(&self))?;
+            self.create_stream(recipient, None, Some(duration), StreamOptions::default())
+        }
 
-            let amount_to_withdraw = withdrawal_amount.unwrap_or(available_balance);
+        /// Creates a token stream from the sender to the specified recipient holding back part of the funds until it finishes.
+        ///
+        /// Parameters:
+        /// - `recipient`: The recipient wallet address of the stream.
+        /// - `duration`: The duration of the stream, measured in seconds.
+        /// - `completion_bonus`: Part of the transaction funds the recipient only gets once the stream is finished.
+        /// - **Transaction funds:** The amount of funds to be transferred to the recipient through the stream, including `completion_bonus`.
+        ///
+        /// Validations:
+        /// - `completion_bonus` should be lower than the transaction funds.
+        /// - Same as `create_stream`.
+        ///
+        /// Behavior:
+        /// - Same as `create_stream`, starting immediately.
+        /// - The transaction funds minus `completion_bonus` stream linearly until the end date.
+        /// - `completion_bonus` becomes available at the end date, and is refunded to the payer if the stream is cancelled before.
+        /// - `completion_bonus` is stored with the stream when it is created, so `StreamCreated` reports it.
+        ///
+        /// Returns:
+        /// - The created stream ID.
+        ///
+        /// Errors:
+        /// - ValidationError
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_stream_with_bonus(
+            &mut self,
+            recipient: AccountId,
+            duration: u64,
+            completion_bonus: u128,
+        ) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, 1)?;
+            let stream_funds = self.transferred_funds()?;
 
-            if amount_to_withdraw > available_balance {
-                return Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance);
+            if completion_bonus >= stream_funds {
+                return Err(ContractError::ValidationError);
             }
 
-            stream.withdraw(amount_to_withdraw)?;
-            self.streams.insert(stream_id, &stream);
-
-            if self
-                .env()
-                .transfer(stream.recipient, amount_to_withdraw)
-                .is_err()
-            {
-                return Err(ContractError::WithdrawTransferFailed);
-            }
+            let mut new_stream =
+                self.build_new_stream(caller, recipient, None, None, Some(duration), stream_funds)?;
+            validate_tvl_cap(self.total_locked, stream_funds, self.tvl_cap)?;
+            new_stream.completion_bonus = completion_bonus;
 
-            Ok(amount_to_withdraw)
+            self.store_new_stream(&new_stream)
         }
 
-        /// Returns a stream by its ID.
+        /// Creates a token stream starting immediately, partially funded now and with a schedule of pledged top-ups.
         ///
         /// Parameters:
-        /// - `stream_id`: The expected stream ID.
+        /// - `recipient`: The recipient wallet address of the stream.
+        /// - `duration`: The duration of the stream, measured in seconds.
+        /// - `commitments`: The `(due_date, amount)` pledges to be fulfilled later with `fulfill_commitment`.
+        /// - **Transaction funds:** The funds streamed from the start.
         ///
         /// Validations:
-        /// - The stream should exist.
+        /// - The contract should not be streaming a PSP22 token.
+        /// - `commitments` should not have more than `MAX_COMMITMENTS` entries.
+        /// - Every commitment should pass the `add_commitment` validations.
+        /// - Same as `create_stream`.
+        ///
+        /// Behavior:
+        /// - Same as `create_stream`, starting immediately.
+        /// - The commitments are stored with the stream, and only the transaction funds are available to the recipient until they are fulfilled.
         ///
         /// Returns:
-        /// - The expected stream.
+        /// - The created stream ID.
         ///
         /// Errors:
-        /// - StreamDoesNotExist
-        #[ink(message)]
-        pub fn get_stream_by_id(&self, stream_id: u64) -> Result<Stream, ContractError> {
-            match self.streams.get(&stream_id) {
-                Some(stream) => Ok(stream),
-                None => Err(ContractError::StreamDoesNotExist),
+        /// - UnsupportedAsset
+        /// - TooManyCommitments
+        /// - EmptyFunds
+        /// - InvalidCommitment
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_stream_with_commitments(
+            &mut self,
+            recipient: AccountId,
+            duration: u64,
+            commitments: Vec<(u64, u128)>,
+        ) -> Result<u64, ContractError> {
+            if self.token.is_some() {
+                return Err(ContractError::UnsupportedAsset);
             }
-        }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use ink_lang as ink;
+            self.ensure_not_paused()?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, 1)?;
+            let stream_funds = self.transferred_funds()?;
 
-        fn get_contract_id() -> AccountId {
-            ink_env::test::callee::<ink_env::DefaultEnvironment>()
-        }
+            let new_stream =
+                self.build_new_stream(caller, recipient, None, None, Some(duration), stream_funds)?;
+            validate_tvl_cap(self.total_locked, stream_funds, self.tvl_cap)?;
 
-        fn get_default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
-            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
-        }
+            if commitments.len() > MAX_COMMITMENTS {
+                return Err(ContractError::TooManyCommitments);
+            }
 
-        fn set_sender(sender: AccountId) {
-            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(sender);
-        }
+            for (due_date, amount) in commitments.iter() {
+                new_stream.ensure_valid_commitment(*due_date, *amount)?;
+            }
 
-        fn set_balance(account_id: AccountId, balance: u128) {
-            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(account_id, balance)
+            let stream_id = self.store_new_stream(&new_stream)?;
+
+            if !commitments.is_empty() {
+                self.commitments.insert(stream_id, &commitments);
+            }
+
+            Ok(stream_id)
         }
 
-        fn advance_block() {
-            let _ = ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+        /// Escrows the transaction funds for a stream to whoever presents the secret behind `hash`, such as an airdrop claim code.
+        ///
+        /// Parameters:
+        /// - `hash`: The BLAKE2b 256-bit hash of the secret.
+        /// - `duration`: The duration of the stream once claimed, measured in seconds.
+        /// - **Transaction funds:** The amount of funds to be streamed to the claimer.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The sender should send funds in the transaction.
+        /// - `duration` should not be lower than the minimum duration nor greater than the maximum duration, if configured.
+        /// - `hash` should not be used by another claimable stream.
+        ///
+        /// Behavior:
+        /// - The funds are held by the contract until the stream is claimed with `claim_stream` or cancelled with `cancel_claimable_stream`.
+        /// - The escrowed funds are not part of the total locked funds until the stream is claimed, but they are not unallocated either, so they can't be swept.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - UnsupportedAsset
+        /// - EmptyFunds
+        /// - StreamDurationShouldBeGreater
+        /// - StreamDurationTooLong
+        /// - ClaimAlreadyExists
+        #[ink(message, payable)]
+        pub fn create_claimable_stream(
+            &mut self,
+            hash: [u8; 32],
+            duration: u64,
+        ) -> Result<(), ContractError> {
+            self.ensure_not_paused()?;
+            let funds = self.transferred_funds()?;
+
+            if funds == 0 {
+                return Err(ContractError::EmptyFunds);
+            }
+
+            validate_and_generate_stream_end_date(
+                None,
+                Some(duration),
+                get_current_time_in_seconds(&self),
+                self.min_duration,
+                self.max_duration,
+            )?;
+
+            if self.claimable_streams.get(hash).is_some() {
+                return Err(ContractError::ClaimAlreadyExists);
+            }
+
+            self.claimable_streams
+                .insert(hash, &(self.env().caller(), funds, duration));
+            self.total_escrowed = self.total_escrowed.saturating_add(funds);
+
+            Ok(())
         }
 
-        fn set_value_transferred(amount: u128) {
-            ink_env::test::set_value_transferred::<ink_env::DefaultEnvironment>(amount);
+        /// Commits to claiming a claimable stream, without revealing its secret yet.
+        ///
+        /// Parameters:
+        /// - `commitment`: The BLAKE2b 256-bit hash of the secret followed by the 32 bytes of the sender account, as computed by `hash_claim_commitment`.
+        ///
+        /// Validations:
+        /// - `commitment` should not be committed already.
+        ///
+        /// Behavior:
+        /// - The current block number is stored, so `claim_stream` can be called from the next block on.
+        ///
+        /// Errors:
+        /// - ClaimAlreadyCommitted
+        ///
+        /// NOTES
+        /// -----
+        /// - The commitment binds the secret to the sender, so copying it doesn't allow anyone else to claim the stream.
+        #[ink(message)]
+        pub fn commit_claim(&mut self, commitment: [u8; 32]) -> Result<(), ContractError> {
+            if self.claim_commitments.get(commitment).is_some() {
+                return Err(ContractError::ClaimAlreadyCommitted);
+            }
+
+            self.claim_commitments
+                .insert(commitment, &self.env().block_number());
+
+            Ok(())
         }
 
-        fn init() -> (
-            StreamsContract,
-            ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment>,
-        ) {
-            (StreamsContract::new(), get_default_accounts())
+        /// Claims a claimable stream, creating a stream from its payer to the sender.
+        ///
+        /// Parameters:
+        /// - `preimage`: The secret whose hash was used to create the claimable stream.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - A claimable stream should exist for the hash of `preimage`.
+        /// - The sender should have committed to `preimage` with `commit_claim` in an earlier block.
+        /// - The payer should not exceed the maximum number of active streams per payer.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - The stream should pass the `create_stream` validations. In particular, the sender can't be the payer.
+        ///
+        /// Behavior:
+        /// - The escrowed funds start vesting linearly to the sender from now, over the configured duration.
+        /// - The claimable stream and the sender commitment are removed, so it can't be claimed twice.
+        ///
+        /// Returns:
+        /// - The created stream ID.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - InvalidClaim
+        /// - ClaimNotCommitted
+        /// - ClaimRevealTooEarly
+        /// - StreamLimitReached
+        /// - TvlCapExceeded
+        /// - Same as `create_stream`.
+        ///
+        /// NOTES
+        /// -----
+        /// - The preimage is public once submitted. Anyone copying it would have to commit first and wait for the next block, by which time the stream is already claimed.
+        #[ink(message)]
+        pub fn claim_stream(&mut self, preimage: Vec<u8>) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let hash = hash_claim_preimage(&preimage);
+            let (payer, funds, duration) = self
+                .claimable_streams
+                .get(hash)
+                .ok_or(ContractError::InvalidClaim)?;
+
+            let caller = self.env().caller();
+            let commitment = hash_claim_commitment(&preimage, &caller);
+            let committed_at = self
+                .claim_commitments
+                .get(commitment)
+                .ok_or(ContractError::ClaimNotCommitted)?;
+
+            if self.env().block_number() <= committed_at {
+                return Err(ContractError::ClaimRevealTooEarly);
+            }
+
+            self.ensure_stream_slots(payer, 1)?;
+            validate_tvl_cap(self.total_locked, funds, self.tvl_cap)?;
+            let new_stream =
+                self.build_new_stream(payer, caller, None, None, Some(duration), funds)?;
+
+            self.claimable_streams.remove(hash);
+            self.claim_commitments.remove(commitment);
+            self.total_escrowed = self.total_escrowed.saturating_sub(funds);
+
+            self.store_new_stream(&new_stream)
         }
 
-        #[ink::test]
-        fn create_stream_with_duration_works() {
-            // Arrange
-            let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            let duration = 10000;
-            set_sender(sender);
-            set_value_transferred(funds);
+        /// Cancels a claimable stream that was not claimed yet, refunding its funds to the payer.
+        ///
+        /// Parameters:
+        /// - `hash`: The hash used to create the claimable stream.
+        ///
+        /// Validations:
+        /// - A claimable stream should exist for `hash`.
+        /// - The sender should be the payer of the claimable stream.
+        ///
+        /// Returns:
+        /// - The amount of tokens refunded.
+        ///
+        /// Errors:
+        /// - InvalidClaim
+        /// - Unauthorized
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn cancel_claimable_stream(&mut self, hash: [u8; 32]) -> Result<u128, ContractError> {
+            let (payer, funds, _) = self
+                .claimable_streams
+                .get(hash)
+                .ok_or(ContractError::InvalidClaim)?;
 
-            // Act
-            let current_time = get_current_time_in_seconds(&contract);
-            let stream_id = contract
-                .create_stream(recipient, None, Some(duration))
-                .unwrap();
+            if self.env().caller() != payer {
+                return Err(ContractError::Unauthorized);
+            }
 
-            // Assert
-            assert_eq!(stream_id, contract.next_stream_id - 1);
-            let stream = contract.get_stream_by_id(stream_id).unwrap();
-            assert_eq!(stream.payer, sender);
-            assert_eq!(stream.recipient, recipient);
-            assert_eq!(stream.original_balance, funds);
-            assert_eq!(stream.current_balance, funds);
-            assert_eq!(stream.start_date, current_time);
-            assert_eq!(
+            self.claimable_streams.remove(hash);
+            self.total_escrowed = self.total_escrowed.saturating_sub(funds);
+            self.transfer_funds(payer, funds)?;
+
+            Ok(funds)
+        }
+
+        /// Creates a token stream capping its funds at the maximum stream amount, refunding the excess to the sender.
+        ///
+        /// Parameters:
+        /// - `recipient`, `end_date`, `duration`, `options`: Same as `create_stream`.
+        /// - **Transaction funds:** The amount of funds to be transferred to the recipient through the stream, up to the maximum stream amount.
+        ///
+        /// Validations:
+        /// - Same as `create_stream`, except that funds above the maximum stream amount are accepted.
+        ///
+        /// Behavior:
+        /// - If a maximum stream amount is configured and the transaction funds exceed it, the stream is funded with the maximum and the excess is transferred back to the sender.
+        /// - Same as `create_stream`.
+        ///
+        /// Returns:
+        /// - A tuple with the created stream ID and the refunded amount.
+        ///
+        /// Errors:
+        /// - Same as `create_stream`.
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - `create_stream` keeps rejecting funds above the maximum with `AmountTooLarge`. Callers opt into the refund by using this message.
+        #[ink(message, payable)]
+        pub fn create_stream_with_refund(
+            &mut self,
+            recipient: AccountId,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            options: StreamOptions,
+        ) -> Result<(u64, u128), ContractError> {
+            self.ensure_not_paused()?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, 1)?;
+            let transferred_funds = self.transferred_funds()?;
+
+            let stream_funds = if self.max_stream_amount > 0 {
+                transferred_funds.min(self.max_stream_amount)
+            } else {
+                transferred_funds
+            };
+            let refunded_amount = transferred_funds - stream_funds;
+
+            let new_stream = self.build_new_stream_with_options(
+                caller,
+                recipient,
+                end_date,
+                duration,
+                stream_funds,
+                &options,
+            )?;
+            validate_tvl_cap(self.total_locked, stream_funds, self.tvl_cap)?;
+
+            if refunded_amount > 0 {
+                self.transfer_funds(caller, refunded_amount)?;
+            }
+
+            let stream_id = self.store_new_stream_with_options(&new_stream, &options)?;
+
+            Ok((stream_id, refunded_amount))
+        }
+
+        /// Creates several token streams from the sender in a single call.
+        ///
+        /// Parameters:
+        /// - `recipients`: A list of `(recipient, end_date, duration)` tuples, one per stream. Same as the `create_stream` parameters.
+        /// - `amounts`: The funds of each stream, in the same order as `recipients`.
+        /// - **Transaction funds:** The sum of `amounts`.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The batch should not have more than `MAX_BATCH_SIZE` streams.
+        /// - The sender should not exceed the maximum number of active streams per payer.
+        /// - `recipients` and `amounts` should have the same length.
+        /// - The transaction funds should be equal to the sum of `amounts`.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - Every stream should pass the `create_stream` validations.
+        ///
+        /// Behavior:
+        /// - If any stream is invalid, no stream is created.
+        /// - The streams will be stored with consecutive IDs.
+        ///
+        /// Returns:
+        /// - The created stream IDs, in the same order as `recipients`.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - BatchTooLarge
+        /// - StreamLimitReached
+        /// - BatchLengthMismatch
+        /// - ArithmeticOverflow
+        /// - BatchAmountMismatch
+        /// - TvlCapExceeded
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_streams_batch(
+            &mut self,
+            recipients: Vec<(AccountId, Option<u64>, Option<u64>)>,
+            amounts: Vec<u128>,
+        ) -> Result<Vec<u64>, ContractError> {
+            self.ensure_not_paused()?;
+            if recipients.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            if recipients.len() != amounts.len() {
+                return Err(ContractError::BatchLengthMismatch);
+            }
+
+            let total_funds = amounts
+                .iter()
+                .try_fold(0u128, |total, amount| total.checked_add(*amount))
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            if total_funds != self.transferred_funds()? {
+                return Err(ContractError::BatchAmountMismatch);
+            }
+
+            validate_tvl_cap(self.total_locked, total_funds, self.tvl_cap)?;
+
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, recipients.len())?;
+            let new_streams = recipients
+                .into_iter()
+                .zip(amounts)
+                .map(|((recipient, end_date, duration), amount)| {
+                    self.build_new_stream(caller, recipient, None, end_date, duration, amount)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            new_streams
+                .iter()
+                .map(|new_stream| self.store_new_stream(new_stream))
+                .collect()
+        }
+
+        /// Splits the transaction funds into proportionally weighted streams sharing the same dates.
+        ///
+        /// Parameters:
+        /// - `recipients`: The recipient wallet addresses, one stream per recipient.
+        /// - `weights`: The weight of each recipient, in the same order as `recipients`.
+        /// - `duration`: The duration of the streams measured in seconds.
+        /// - **Transaction funds:** The total amount of funds to be split across the streams.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The split should not have more than `MAX_BATCH_SIZE` recipients.
+        /// - `recipients` and `weights` should have the same length.
+        /// - The sum of `weights` should be greater than zero.
+        /// - The sender should not exceed the maximum number of active streams per payer.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - Every stream should pass the `create_stream` validations. In particular, every share should be greater than zero.
+        ///
+        /// Behavior:
+        /// - Each recipient receives `funds * weight / total_weight`, rounding down.
+        /// - The rounding remainder is added to the stream of the first recipient, so the streams add up to the transaction funds.
+        /// - If any stream is invalid, no stream is created.
+        ///
+        /// Returns:
+        /// - The created stream IDs, in the same order as `recipients`.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - BatchTooLarge
+        /// - BatchLengthMismatch
+        /// - ValidationError
+        /// - StreamLimitReached
+        /// - TvlCapExceeded
+        /// - ArithmeticOverflow
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_split_stream(
+            &mut self,
+            recipients: Vec<AccountId>,
+            weights: Vec<u16>,
+            duration: u64,
+        ) -> Result<Vec<u64>, ContractError> {
+            self.ensure_not_paused()?;
+            if recipients.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            if recipients.len() != weights.len() {
+                return Err(ContractError::BatchLengthMismatch);
+            }
+
+            let total_weight: u64 = weights.iter().map(|weight| *weight as u64).sum();
+
+            if total_weight == 0 {
+                return Err(ContractError::ValidationError);
+            }
+
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, recipients.len())?;
+            let total_funds = self.transferred_funds()?;
+            validate_tvl_cap(self.total_locked, total_funds, self.tvl_cap)?;
+
+            let mut amounts = weights
+                .iter()
+                .map(|weight| mul_div(total_funds, *weight as u64, total_weight))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(ContractError::ArithmeticOverflow)?;
+            let remainder = total_funds.saturating_sub(amounts.iter().sum::<u128>());
+            amounts[0] += remainder;
+
+            let new_streams = recipients
+                .into_iter()
+                .zip(amounts)
+                .map(|(recipient, amount)| {
+                    self.build_new_stream(caller, recipient, None, None, Some(duration), amount)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            new_streams
+                .iter()
+                .map(|new_stream| self.store_new_stream(new_stream))
+                .collect()
+        }
+
+        /// Splits the transaction funds equally into streams sharing the same dates.
+        ///
+        /// Parameters:
+        /// - `recipients`: The recipient wallet addresses, one stream per recipient.
+        /// - `duration`: The duration of the streams measured in seconds.
+        /// - **Transaction funds:** The total amount of funds to be split across the streams.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - `recipients` should not be empty.
+        /// - The split should not have more than `MAX_BATCH_SIZE` recipients.
+        /// - `recipients` should not have duplicates.
+        /// - The sender should not exceed the maximum number of active streams per payer.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - Every stream should pass the `create_stream` validations. In particular, no recipient can be the sender.
+        ///
+        /// Behavior:
+        /// - Each recipient receives `funds / recipients`, rounding down.
+        /// - The rounding remainder is added to the stream of the last recipient, so the streams add up to the transaction funds.
+        /// - If any stream is invalid, no stream is created.
+        ///
+        /// Returns:
+        /// - The created stream IDs, in the same order as `recipients`.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - ValidationError
+        /// - BatchTooLarge
+        /// - DuplicateRecipient
+        /// - StreamLimitReached
+        /// - TvlCapExceeded
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_equal_split(
+            &mut self,
+            recipients: Vec<AccountId>,
+            duration: u64,
+        ) -> Result<Vec<u64>, ContractError> {
+            self.ensure_not_paused()?;
+            if recipients.is_empty() {
+                return Err(ContractError::ValidationError);
+            }
+
+            if recipients.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            if recipients
+                .iter()
+                .enumerate()
+                .any(|(index, recipient)| recipients[..index].contains(recipient))
+            {
+                return Err(ContractError::DuplicateRecipient);
+            }
+
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, recipients.len())?;
+            let total_funds = self.transferred_funds()?;
+            validate_tvl_cap(self.total_locked, total_funds, self.tvl_cap)?;
+
+            let share = total_funds / recipients.len() as u128;
+            let mut amounts = vec![share; recipients.len()];
+            // The slice is not empty, it was checked above.
+            amounts[recipients.len() - 1] += total_funds % recipients.len() as u128;
+
+            let new_streams = recipients
+                .into_iter()
+                .zip(amounts)
+                .map(|(recipient, amount)| {
+                    self.build_new_stream(caller, recipient, None, None, Some(duration), amount)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            new_streams
+                .iter()
+                .map(|new_stream| self.store_new_stream(new_stream))
+                .collect()
+        }
+
+        /// Saves a template of streams that can be created repeatedly, such as a payroll run.
+        ///
+        /// Parameters:
+        /// - `template_id`: The template ID.
+        /// - `streams`: The streams of the template.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `streams` should not be empty.
+        /// - The template should not have more than `MAX_BATCH_SIZE` streams.
+        /// - The sum of the template amounts should not overflow.
+        ///
+        /// Behavior:
+        /// - A template previously saved with the same ID will be replaced.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - ValidationError
+        /// - BatchTooLarge
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - The streams are validated when the template is instantiated with `create_from_template`.
+        #[ink(message)]
+        pub fn save_template(
+            &mut self,
+            template_id: u64,
+            streams: Vec<StreamTemplate>,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if streams.is_empty() {
+                return Err(ContractError::ValidationError);
+            }
+
+            if streams.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            streams
+                .iter()
+                .try_fold(0u128, |total, stream| total.checked_add(stream.amount))
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            self.templates.insert(template_id, &streams);
+
+            Ok(())
+        }
+
+        /// Returns the streams of a template, if it exists.
+        #[ink(message)]
+        pub fn get_template(&self, template_id: u64) -> Option<Vec<StreamTemplate>> {
+            self.templates.get(template_id)
+        }
+
+        /// Creates all the streams of a template from the sender.
+        ///
+        /// Parameters:
+        /// - `template_id`: The template ID.
+        /// - **Transaction funds:** The sum of the template amounts.
+        ///
+        /// Validations:
+        /// - The template should exist.
+        /// - Same as `create_streams_batch`. In particular, the transaction funds should be equal to the sum of the template amounts.
+        ///
+        /// Behavior:
+        /// - Each stream starts immediately and lasts the duration set in the template.
+        /// - Same as `create_streams_batch`.
+        ///
+        /// Returns:
+        /// - The created stream IDs, in the same order as the template streams.
+        ///
+        /// Errors:
+        /// - TemplateDoesNotExist
+        /// - Same as `create_streams_batch`.
+        #[ink(message, payable)]
+        pub fn create_from_template(
+            &mut self,
+            template_id: u64,
+        ) -> Result<Vec<u64>, ContractError> {
+            let template = self
+                .templates
+                .get(template_id)
+                .ok_or(ContractError::TemplateDoesNotExist)?;
+
+            let (recipients, amounts): (Vec<_>, Vec<_>) = template
+                .iter()
+                .map(|stream| {
+                    (
+                        (stream.recipient, None, Some(stream.duration)),
+                        stream.amount,
+                    )
+                })
+                .unzip();
+
+            self.create_streams_batch(recipients, amounts)
+        }
+
+        /// Creates a PSP22 token stream from the sender to the specified recipient setting the end date or the duration.
+        ///
+        /// Parameters:
+        /// - `recipient`: The recipient wallet address of the stream.
+        /// - `amount`: The amount of tokens to be transferred to the recipient through the stream. The sender should have approved the contract to spend them.
+        /// - `end_date`, `duration`, `options`: Same as `create_stream`.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The contract should stream a PSP22 token.
+        /// - Same as `create_stream`.
+        ///
+        /// Behavior:
+        /// - The tokens will be transferred from the sender to the contract.
+        /// - Same as `create_stream`.
+        ///
+        /// Returns:
+        /// - The created stream ID.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - UnsupportedAsset
+        /// - StreamLimitReached
+        /// - TokenTransferFailed
+        /// - Same as `create_stream`.
+        #[ink(message)]
+        pub fn create_psp22_stream(
+            &mut self,
+            recipient: AccountId,
+            amount: u128,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            options: StreamOptions,
+        ) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let token = self.token.ok_or(ContractError::UnsupportedAsset)?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, 1)?;
+
+            let new_stream = self.build_new_stream_with_options(
+                caller, recipient, end_date, duration, amount, &options,
+            )?;
+            validate_tvl_cap(self.total_locked, amount, self.tvl_cap)?;
+
+            psp22_transfer_from(token, caller, self.env().account_id(), amount)?;
+
+            self.store_new_stream_with_options(&new_stream, &options)
+        }
+
+        /// Returns the PSP22 token streamed by the contract, if any.
+        ///
+        /// Returns:
+        /// - The PSP22 token contract address, or `None` if the contract streams the native token.
+        #[ink(message)]
+        pub fn token(&self) -> Option<AccountId> {
+            self.token
+        }
+
+        /// Allows a spender to create streams on behalf of the sender, up to a total amount.
+        ///
+        /// Parameters:
+        /// - `spender`: The wallet address allowed to create streams, such as a relayer.
+        /// - `amount`: The total amount of funds the spender can stream on behalf of the sender.
+        ///
+        /// Behavior:
+        /// - The previous allowance of the spender, if any, will be replaced.
+        ///
+        /// NOTES
+        /// -----
+        /// - The spender funds the streams, the sender is recorded as their payer.
+        #[ink(message)]
+        pub fn approve(&mut self, spender: AccountId, amount: u128) {
+            self.allowances
+                .insert((self.env().caller(), spender), &amount);
+        }
+
+        /// Returns the amount a spender can still stream on behalf of a payer.
+        ///
+        /// Parameters:
+        /// - `payer`: The wallet address recorded as payer of the streams.
+        /// - `spender`: The wallet address creating the streams.
+        #[ink(message)]
+        pub fn allowance(&self, payer: AccountId, spender: AccountId) -> u128 {
+            self.allowances.get((payer, spender)).unwrap_or(0)
+        }
+
+        /// Creates a token stream on behalf of a payer, funded by the sender.
+        ///
+        /// Parameters:
+        /// - `payer`: The wallet address recorded as payer of the stream. Should have approved the sender.
+        /// - `recipient`, `end_date`, `duration`, `options`: Same as `create_stream`.
+        /// - **Transaction funds:** The amount of funds to be transferred to the recipient through the stream.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The transaction funds should not exceed the allowance of the sender.
+        /// - Same as `create_stream`, with `payer` instead of the sender.
+        ///
+        /// Behavior:
+        /// - The allowance of the sender will be reduced by the transaction funds.
+        /// - Same as `create_stream`.
+        ///
+        /// Returns:
+        /// - The created stream ID.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - StreamLimitReached
+        /// - InsufficientAllowance
+        /// - Same as `create_stream`.
+        #[ink(message, payable)]
+        pub fn create_stream_from(
+            &mut self,
+            payer: AccountId,
+            recipient: AccountId,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            options: StreamOptions,
+        ) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(payer, 1)?;
+            let stream_funds = self.transferred_funds()?;
+
+            let remaining_allowance = self
+                .allowance(payer, caller)
+                .checked_sub(stream_funds)
+                .ok_or(ContractError::InsufficientAllowance)?;
+
+            let new_stream = self.build_new_stream_with_options(
+                payer,
+                recipient,
+                end_date,
+                duration,
+                stream_funds,
+                &options,
+            )?;
+            validate_tvl_cap(self.total_locked, stream_funds, self.tvl_cap)?;
+
+            let stream_id = self.store_new_stream_with_options(&new_stream, &options)?;
+            self.allowances
+                .insert((payer, caller), &remaining_allowance);
+
+            Ok(stream_id)
+        }
+
+        /// Withdraws tokens from a stream. The recipient can specify the expected amount of tokens or withdraw all the available balance.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `withdrawal_amount`: The amount of tokens to be withdrawn. If not specified, all the available balance will be withdrawn.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream or its authorized agent.
+        /// - The expected withdrawal amount should be greater or equal than the available balance.
+        /// - The stream should not be finished and already emptied.
+        /// - The withdrawal amount should not be lower than the existential deposit, if configured, unless it is the whole stream balance.
+        ///
+        /// Behavior:
+        /// - The stream available balance will be calculated based on the elapsed time.
+        /// - The current stream balance will be reduced by the withdrawal amount.
+        /// - The protocol fee, if configured and the recipient is not exempt, will be transferred to the contract owner.
+        /// - The rest of the requested funds will be transfered to the recipient, or to its payout address if specified, even if the sender is an agent.
+        /// - The withdrawal date and amount will be appended to the stream withdrawal history.
+        ///
+        /// Returns:
+        /// - The net amount of tokens received by the recipient.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - WithdrawalAmountShouldBeGreaterThanZero
+        /// - StreamFullyWithdrawn
+        /// - StreamAvailableBalanceisZero
+        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+        /// - AmountBelowExistential
+        /// - WithdrawOnCooldown
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn recipient_withdraw(
+            &mut self,
+            stream_id: u64,
+            withdrawal_amount: Option<u128>,
+        ) -> Result<u128, ContractError> {
+            let (stream, available_balance) =
+                self.prepare_withdrawal(stream_id, withdrawal_amount)?;
+
+            let amount_to_withdraw = withdrawal_amount.unwrap_or(available_balance);
+
+            if amount_to_withdraw > available_balance {
+                return Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance);
+            }
+
+            self.ensure_above_existential_deposit(&stream, amount_to_withdraw)?;
+
+            self.withdraw_from_stream(stream_id, stream, amount_to_withdraw, None, None)
+        }
+
+        /// Withdraws tokens from a stream like `recipient_withdraw`, but returns zero instead of an error when there is no available balance. Useful for clients polling and withdrawing in a loop.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `withdrawal_amount`: The amount of tokens to be withdrawn. If not specified, all the available balance will be withdrawn.
+        ///
+        /// Returns:
+        /// - The amount of tokens withdrawn, zero if the stream available balance is zero.
+        ///
+        /// Errors:
+        /// - Same as `recipient_withdraw`, except StreamAvailableBalanceIsZero and StreamFullyWithdrawn.
+        #[ink(message)]
+        pub fn recipient_withdraw_lenient(
+            &mut self,
+            stream_id: u64,
+            withdrawal_amount: Option<u128>,
+        ) -> Result<u128, ContractError> {
+            match self.recipient_withdraw(stream_id, withdrawal_amount) {
+                Err(ContractError::StreamAvailableBalanceIsZero)
+                | Err(ContractError::StreamFullyWithdrawn) => Ok(0),
+                result => result,
+            }
+        }
+
+        /// Withdraws tokens from a stream without exceeding a maximum amount. Protects the recipient from withdrawing more than expected when more tokens vest before the transaction is executed.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `max_amount`: The maximum amount of tokens to be withdrawn. If not specified, all the available balance will be withdrawn.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - If `max_amount` has value, should be greater than zero.
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream or its authorized agent.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished and already emptied.
+        /// - The withdrawal amount should not be lower than the existential deposit, if configured, unless it is the whole stream balance.
+        ///
+        /// Behavior:
+        /// - The withdrawn amount will be the minimum between the available balance and `max_amount`.
+        /// - The current stream balance will be reduced by the withdrawal amount.
+        /// - The withdrawn funds will be transfered to the recipient, or to its payout address if specified, even if the sender is an agent.
+        ///
+        /// Returns:
+        /// - The amount of tokens withdrawn.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - WithdrawalAmountShouldBeGreaterThanZero
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamFullyWithdrawn
+        /// - StreamAvailableBalanceIsZero
+        /// - AmountBelowExistential
+        /// - WithdrawOnCooldown
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn recipient_withdraw_capped(
+            &mut self,
+            stream_id: u64,
+            max_amount: Option<u128>,
+        ) -> Result<u128, ContractError> {
+            let (stream, available_balance) = self.prepare_withdrawal(stream_id, max_amount)?;
+
+            let amount_to_withdraw = match max_amount {
+                Some(max_amount) => available_balance.min(max_amount),
+                None => available_balance,
+            };
+
+            self.ensure_above_existential_deposit(&stream, amount_to_withdraw)?;
+
+            self.withdraw_from_stream(stream_id, stream, amount_to_withdraw, None, None)
+        }
+
+        /// Withdraws tokens from a stream like `recipient_withdraw`, sending a share of them to a secondary address.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `withdrawal_amount`: The amount of tokens to be withdrawn. If not specified, all the available balance will be withdrawn.
+        /// - `secondary`: The wallet address receiving the secondary share, such as a savings wallet.
+        /// - `secondary_bps`: The share of the net amount sent to `secondary`, in basis points.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - `secondary_bps` should not be greater than 10000.
+        /// - The sender should be the recipient of the stream or its authorized agent.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished and already emptied.
+        /// - The withdrawal amount should not exceed the stream available balance.
+        /// - The withdrawal amount should not be lower than the existential deposit, if configured, unless it is the whole stream balance.
+        ///
+        /// Behavior:
+        /// - The secondary share is rounded down, the rest goes to the recipient or to its payout address.
+        /// - If any transfer fails, the whole call fails and nothing is withdrawn.
+        ///
+        /// Returns:
+        /// - The net amount of tokens withdrawn, including the secondary share.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - InvalidSplit
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - WithdrawalAmountShouldBeGreaterThanZero
+        /// - StreamFullyWithdrawn
+        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+        /// - AmountBelowExistential
+        /// - WithdrawOnCooldown
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn recipient_withdraw_split(
+            &mut self,
+            stream_id: u64,
+            withdrawal_amount: Option<u128>,
+            secondary: AccountId,
+            secondary_bps: u16,
+        ) -> Result<u128, ContractError> {
+            if secondary_bps as u64 > BPS_DENOMINATOR {
+                return Err(ContractError::InvalidSplit);
+            }
+
+            let (stream, available_balance) =
+                self.prepare_withdrawal(stream_id, withdrawal_amount)?;
+
+            let amount_to_withdraw = withdrawal_amount.unwrap_or(available_balance);
+
+            if amount_to_withdraw > available_balance {
+                return Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance);
+            }
+
+            self.ensure_above_existential_deposit(&stream, amount_to_withdraw)?;
+
+            self.withdraw_from_stream(
+                stream_id,
+                stream,
+                amount_to_withdraw,
+                None,
+                Some((secondary, secondary_bps)),
+            )
+        }
+
+        /// Withdraws all the available balance from several streams in a single call.
+        ///
+        /// Parameters:
+        /// - `stream_ids`: The IDs of the streams to withdraw from.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The number of streams should not exceed `MAX_BATCH_SIZE`.
+        /// - Every stream should exist.
+        /// - The sender should be the recipient or the authorized agent of every stream.
+        /// - No stream should be cancelled.
+        /// - The withdrawal cooldown of every stream should have elapsed.
+        ///
+        /// Behavior:
+        /// - Every stream is validated before withdrawing from any of them.
+        /// - Streams without available balance, or with an available balance lower than the existential deposit that isn't the whole stream balance, are skipped.
+        /// - The rest of the streams are withdrawn like with `recipient_withdraw`, including the withdrawal history.
+        ///
+        /// Returns:
+        /// - The total net amount of tokens received by the recipient.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - BatchTooLarge
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - WithdrawOnCooldown
+        /// - ArithmeticOverflow
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - If any withdrawal fails, the whole call is reverted, including the withdrawals from the previous streams in the batch.
+        #[ink(message)]
+        pub fn recipient_withdraw_batch(
+            &mut self,
+            stream_ids: Vec<u64>,
+        ) -> Result<u128, ContractError> {
+            self.ensure_not_paused()?;
+            if stream_ids.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            let caller = self.env().caller();
+            let current_time = get_current_time_in_seconds(&self);
+            for stream_id in stream_ids.iter() {
+                let stream = self.get_stream_by_id(*stream_id)?;
+                self.ensure_can_withdraw(*stream_id, &stream, caller)?;
+                stream.ensure_active()?;
+                stream.ensure_withdraw_cooldown_elapsed(current_time)?;
+            }
+
+            let mut total_withdrawn: u128 = 0;
+
+            for stream_id in stream_ids {
+                let stream = self.get_stream_by_id(stream_id)?;
+                let available_balance = stream.available_balance_or_zero(current_time);
+
+                if available_balance == 0
+                    || self
+                        .ensure_above_existential_deposit(&stream, available_balance)
+                        .is_err()
+                {
+                    continue;
+                }
+
+                let withdrawn =
+                    self.withdraw_from_stream(stream_id, stream, available_balance, None, None)?;
+                total_withdrawn = total_withdrawn
+                    .checked_add(withdrawn)
+                    .ok_or(ContractError::ArithmeticOverflow)?;
+            }
+
+            Ok(total_withdrawn)
+        }
+
+        /// Removes a fully withdrawn stream from storage.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer or the recipient of the stream.
+        /// - The stream current balance should be zero.
+        /// - The stream should be finished.
+        ///
+        /// Behavior:
+        /// - The stream, its commitments, its subscriber, its withdraw agent, its withdrawal history and its contributions will be removed.
+        /// - The stream will be removed from the recipient streams index.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamNotEmpty
+        /// - StreamNotFinished
+        #[ink(message)]
+        pub fn close_stream(&mut self, stream_id: u64) -> Result<(), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            let caller = self.env().caller();
+
+            if caller != stream.payer && caller != stream.recipient {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if stream.current_balance > 0 {
+                return Err(ContractError::StreamNotEmpty);
+            }
+
+            if get_current_time_in_seconds(&self) < stream.end_date {
+                return Err(ContractError::StreamNotFinished);
+            }
+
+            self.streams.remove(stream_id);
+            self.commitments.remove(stream_id);
+            self.subscribers.remove(stream_id);
+            self.withdraw_agents.remove(stream_id);
+            self.withdrawal_history.remove(stream_id);
+            self.remove_contributions(stream_id);
+            self.remove_from_recipient_index(stream.recipient, stream_id);
+            self.remove_from_payer_index(stream.payer, stream_id);
+
+            Ok(())
+        }
+
+        /// Reduces the unvested principal of a stream, refunding it to the payer and the co-payers. Useful to scale down a stream without cancelling it.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `reduce_by`: The amount of tokens to be refunded.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable.
+        /// - The stream should not be finished.
+        /// - `reduce_by` should be greater than zero.
+        /// - `reduce_by` should not exceed the unvested balance.
+        ///
+        /// Behavior:
+        /// - The co-payers will be refunded their share of `reduce_by`, in proportion to their contributions, which are reduced accordingly.
+        /// - The rest of `reduce_by` will be transferred to the payer, or to its refund address if specified.
+        /// - The stream original and current balances will be decreased by `reduce_by`.
+        /// - The balance vested so far is kept and the rest of the unvested balance vests over the remaining duration.
+        /// - The refunded funds will be subtracted from the total locked funds.
+        ///
+        /// Returns:
+        /// - The amount of tokens refunded to the payer.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - StreamAlreadyFinished
+        /// - EmptyFunds
+        /// - ReductionExceedsUnvestedBalance
+        /// - ArithmeticOverflow
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn reduce_stream(
+            &mut self,
+            stream_id: u64,
+            reduce_by: u128,
+        ) -> Result<u128, ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+
+            if stream.is_finished(current_time) {
+                return Err(ContractError::StreamAlreadyFinished);
+            }
+
+            if reduce_by == 0 {
+                return Err(ContractError::EmptyFunds);
+            }
+
+            let funded_balance = stream.original_balance;
+            stream.reduce(reduce_by, current_time)?;
+            let payer_share = self.refund_contributors(stream_id, funded_balance, reduce_by)?;
+            if payer_share > 0 {
+                self.transfer_funds(stream.refund_destination(), payer_share)?;
+            }
+
+            self.streams.insert(stream_id, &stream);
+            self.total_locked = self.total_locked.saturating_sub(reduce_by);
+
+            self.check_invariants();
+
+            Ok(payer_share)
+        }
+
+        /// Moves part of the unvested principal of a stream into a new stream, without transferring funds.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The source stream ID.
+        /// - `new_recipient`: The recipient wallet address of the new stream.
+        /// - `amount`: The amount of tokens to be moved to the new stream.
+        /// - `duration`: The duration of the new stream, measured in seconds.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The source stream should exist.
+        /// - The sender should be the payer of the source stream.
+        /// - The source stream should not be cancelled.
+        /// - The source stream should not be immutable.
+        /// - The source stream should be cancellable.
+        /// - The source stream should not be finished.
+        /// - The source stream should not have co-payers, since they can't be recorded on the new stream.
+        /// - `amount` should be greater than zero.
+        /// - `amount` should not exceed the unvested balance of the source stream.
+        /// - The sender should not exceed the maximum number of active streams per payer.
+        /// - The new stream should pass the `create_stream` validations.
+        ///
+        /// Behavior:
+        /// - The source stream is reduced by `amount`, as `reduce_stream` does, but nothing is refunded.
+        /// - A new stream from the sender to `new_recipient` with `amount` is created, starting immediately.
+        /// - The total locked funds don't change.
+        ///
+        /// Returns:
+        /// - The new stream ID.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - StreamAlreadyFinished
+        /// - StreamHasContributors
+        /// - EmptyFunds
+        /// - ReductionExceedsUnvestedBalance
+        /// - ArithmeticOverflow
+        /// - StreamLimitReached
+        /// - Same as `create_stream`.
+        #[ink(message)]
+        pub fn fork_stream(
+            &mut self,
+            stream_id: u64,
+            new_recipient: AccountId,
+            amount: u128,
+            duration: u64,
+        ) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let mut stream = self.get_stream_by_id(stream_id)?;
+            let caller = self.env().caller();
+
+            if caller != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+
+            if stream.is_finished(current_time) {
+                return Err(ContractError::StreamAlreadyFinished);
+            }
+
+            if !self.get_contributors(stream_id).is_empty() {
+                return Err(ContractError::StreamHasContributors);
+            }
+
+            if amount == 0 {
+                return Err(ContractError::EmptyFunds);
+            }
+
+            self.ensure_stream_slots(caller, 1)?;
+            stream.reduce(amount, current_time)?;
+            let new_stream =
+                self.build_new_stream(caller, new_recipient, None, None, Some(duration), amount)?;
+
+            let new_stream_id = self.store_new_stream(&new_stream)?;
+            self.streams.insert(stream_id, &stream);
+            // The funds were already locked in the source stream.
+            self.total_locked = self.total_locked.saturating_sub(amount);
+
+            self.check_invariants();
+
+            Ok(new_stream_id)
+        }
+
+        /// Releases part of the stream balance to the recipient immediately, such as a bonus.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `amount`: The amount of tokens to be released to the recipient.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - `amount` should be greater than zero.
+        /// - `amount` should not exceed the stream current balance.
+        ///
+        /// Behavior:
+        /// - `amount` will be transferred to the recipient, or to its payout address if specified.
+        /// - The stream current balance will be decreased by `amount`.
+        /// - The balance already available is kept and the rest of the unvested balance vests over the remaining duration.
+        /// - The released funds will be subtracted from the total locked funds.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - EmptyFunds
+        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+        /// - ArithmeticOverflow
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - Immutable streams can be released, since it only benefits the recipient.
+        #[ink(message)]
+        pub fn release_now(&mut self, stream_id: u64, amount: u128) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            if amount == 0 {
+                return Err(ContractError::EmptyFunds);
+            }
+
+            stream.release(amount, get_current_time_in_seconds(&self))?;
+            self.transfer_funds(stream.payout_destination(), amount)?;
+
+            self.streams.insert(stream_id, &stream);
+            self.total_locked = self.total_locked.saturating_sub(amount);
+
+            self.check_invariants();
+
+            Ok(())
+        }
+
+        /// Returns the balance of a long finished stream to its payer, when the recipient never withdrew it.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The grace period after the stream end date should have passed.
+        /// - The stream current balance should be greater than zero.
+        ///
+        /// Behavior:
+        /// - The co-payers will be refunded their share of the stream current balance, in proportion to their contributions.
+        /// - The rest of the stream current balance will be transferred to the payer, or to its refund address if specified.
+        /// - The stream original balance will be reduced to the withdrawn balance.
+        /// - The reclaimed funds will be subtracted from the total locked funds.
+        ///
+        /// Returns:
+        /// - The amount of tokens reclaimed by the payer.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamNotReclaimable
+        /// - StreamAvailableBalanceIsZero
+        /// - ArithmeticOverflow
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - The grace period is `GRACE_PERIOD`, 30 days.
+        #[ink(message)]
+        pub fn reclaim_expired(&mut self, stream_id: u64) -> Result<u128, ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if get_current_time_in_seconds(&self)
+                <= stream.end_date.saturating_add(self.grace_period)
+            {
+                return Err(ContractError::StreamNotReclaimable);
+            }
+
+            let reclaimed_amount = stream.current_balance;
+            if reclaimed_amount == 0 {
+                return Err(ContractError::StreamAvailableBalanceIsZero);
+            }
+
+            let funded_balance = stream.original_balance;
+            stream.original_balance = stream
+                .original_balance
+                .checked_sub(reclaimed_amount)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+            stream.current_balance = 0;
+
+            let payer_share =
+                self.refund_contributors(stream_id, funded_balance, reclaimed_amount)?;
+            if payer_share > 0 {
+                self.transfer_funds(stream.refund_destination(), payer_share)?;
+            }
+
+            self.streams.insert(stream_id, &stream);
+            self.total_locked = self.total_locked.saturating_sub(reclaimed_amount);
+            self.env().emit_event(StreamDrained { stream_id });
+
+            self.check_invariants();
+
+            Ok(payer_share)
+        }
+
+        /// Redirects an existing stream to a new recipient.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `new_recipient`: The new recipient wallet address.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable, since the new recipient takes the unvested balance.
+        /// - The new recipient can't be the zero account or the streams contract.
+        /// - The payer can't be the new recipient.
+        /// - The new recipient should be allowed, if the recipient allowlist is enabled.
+        ///
+        /// Behavior:
+        /// - The available balance will be transferred to the old recipient, or to its payout address if specified, as `cancel_stream` does. The withdrawal cooldown does not apply.
+        /// - The payout to the old recipient goes through the same withdrawal path as `recipient_withdraw`: it holds the reentrancy lock, is charged the protocol fee, is recorded in the withdrawal history and notifies the stream subscriber.
+        /// - Emits `Withdrawn` for the payout to the old recipient, and `StreamDrained` or `SolvencyWarning` when they apply.
+        /// - The balance not vested yet will be withdrawable by the new recipient only.
+        /// - The payout address and the withdraw agent of the old recipient will be cleared.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - InvalidRecipient
+        /// - RecipientCannotBePayer
+        /// - RecipientNotAllowed
+        /// - ArithmeticOverflow
+        /// - Reentrancy
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn reassign_recipient(
+            &mut self,
+            stream_id: u64,
+            new_recipient: AccountId,
+        ) -> Result<(), ContractError> {
+            self.ensure_not_paused()?;
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            // The amount bounds only apply to new funds, so they are disabled here.
+            validate_stream_creation_parameters(
+                stream.payer,
+                new_recipient,
+                self.env().account_id(),
+                stream.original_balance,
+                0,
+                0,
+            )?;
+
+            self.ensure_recipient_allowed(new_recipient)?;
+
+            // The old recipient keeps what it already earned.
+            let current_time = get_current_time_in_seconds(&self);
+            let vested_payout = match stream.get_available_balance(current_time) {
+                Err(ContractError::StreamAvailableBalanceIsZero) => 0,
+                result => result?,
+            };
+            if vested_payout > 0 {
+                self.withdraw_from_stream(stream_id, stream, vested_payout, None, None)?;
+                stream = self.get_stream_by_id(stream_id)?;
+            }
+            stream.checkpoint(current_time)?;
+
+            self.remove_from_recipient_index(stream.recipient, stream_id);
+            self.add_to_recipient_index(new_recipient, stream_id);
+
+            stream.recipient = new_recipient;
+            stream.payout_address = None;
+            self.streams.insert(stream_id, &stream);
+            self.withdraw_agents.remove(stream_id);
+
+            Ok(())
+        }
+
+        /// Sets the minimum interval between two withdrawals from a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `withdraw_cooldown`: The minimum interval between withdrawals, measured in seconds. Zero disables it.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable, since the cooldown delays the recipient withdrawals.
+        /// - `withdraw_cooldown` should not be greater than the stream duration.
+        ///
+        /// Behavior:
+        /// - Withdrawals made before `withdraw_cooldown` elapses since the last withdrawal will fail.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - WithdrawCooldownTooLong
+        ///
+        /// NOTES
+        /// -----
+        /// - The cooldown is bounded by the stream duration, so the payer can't lock the vested funds of the recipient indefinitely.
+        #[ink(message)]
+        pub fn set_withdraw_cooldown(
+            &mut self,
+            stream_id: u64,
+            withdraw_cooldown: u64,
+        ) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            if withdraw_cooldown > stream.total_duration() {
+                return Err(ContractError::WithdrawCooldownTooLong);
+            }
+
+            stream.withdraw_cooldown = withdraw_cooldown;
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Rounds the vested balance of a stream half up instead of down from now on.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        ///
+        /// Behavior:
+        /// - The recipient gets the nearest token to its exact linear share instead of the lower one.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        ///
+        /// NOTES
+        /// -----
+        /// - Immutable streams can be changed, since it only benefits the recipient. It can't be reverted, so the vested balance never decreases.
+        #[ink(message)]
+        pub fn enable_half_up_rounding(&mut self, stream_id: u64) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            stream.rounding = Rounding::HalfUp;
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Transfers the payer role of an existing stream to a new account.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `new_payer`: The new payer wallet address.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The new payer can't be the recipient.
+        /// - The new payer should not exceed the maximum number of active streams per payer.
+        ///
+        /// Behavior:
+        /// - Only the new payer will be able to cancel, top up or modify the stream. Refunds will be transferred to the new payer.
+        /// - The refund address, if any, will be cleared.
+        /// - The stream will be moved to the payer streams index of the new payer.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - RecipientCannotBePayer
+        /// - StreamLimitReached
+        ///
+        /// NOTES
+        /// -----
+        /// - No funds are moved, since the stream balance is already held by the contract.
+        /// - Immutable streams can be transferred, since the recipient is not affected.
+        #[ink(message)]
+        pub fn transfer_payer_role(
+            &mut self,
+            stream_id: u64,
+            new_payer: AccountId,
+        ) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            if new_payer == stream.recipient {
+                return Err(ContractError::RecipientCannotBePayer);
+            }
+
+            self.ensure_stream_slots(new_payer, 1)?;
+
+            self.remove_from_payer_index(stream.payer, stream_id);
+            self.add_to_payer_index(new_payer, stream_id);
+
+            stream.payer = new_payer;
+            stream.refund_to = None;
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Sets the account receiving the withdrawn funds on behalf of the recipient.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `destination`: The payout address. Passing the recipient address clears it.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream.
+        ///
+        /// Behavior:
+        /// - Withdrawals and payouts will be transferred to `destination` instead of the recipient.
+        /// - Only the recipient can still withdraw.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn set_payout_address(
+            &mut self,
+            stream_id: u64,
+            destination: AccountId,
+        ) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+            stream.has_permission_to_withdraw(self.env().caller())?;
+
+            stream.payout_address = if destination == stream.recipient {
+                None
+            } else {
+                Some(destination)
+            };
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Sets the account receiving the refunded funds on behalf of the payer.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `destination`: The refund address. Passing the payer address clears it.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be immutable.
+        ///
+        /// Behavior:
+        /// - The funds refunded by `cancel_stream`, `renounce_stream`, `reduce_stream` and `reclaim_expired` will be transferred to `destination` instead of the payer.
+        /// - Only the payer can still cancel or modify the stream.
+        /// - The refund address is kept by the new stream created by `replace_stream`.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        #[ink(message)]
+        pub fn set_refund_address(
+            &mut self,
+            stream_id: u64,
+            destination: AccountId,
+        ) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+
+            stream.refund_to = if destination == stream.payer {
+                None
+            } else {
+                Some(destination)
+            };
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Authorizes an agent, such as a keeper bot, to withdraw from a stream on behalf of the recipient.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `agent`: The agent wallet address.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream.
+        ///
+        /// Behavior:
+        /// - The agent can call `recipient_withdraw` for the stream. The funds are still transferred to the recipient, or to its payout address if specified.
+        /// - Only one agent per stream is allowed, so any previous agent is replaced.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn authorize_agent(
+            &mut self,
+            stream_id: u64,
+            agent: AccountId,
+        ) -> Result<(), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            stream.has_permission_to_withdraw(self.env().caller())?;
+
+            self.withdraw_agents.insert(stream_id, &agent);
+
+            Ok(())
+        }
+
+        /// Revokes the withdraw agent of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn revoke_agent(&mut self, stream_id: u64) -> Result<(), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            stream.has_permission_to_withdraw(self.env().caller())?;
+
+            self.withdraw_agents.remove(stream_id);
+
+            Ok(())
+        }
+
+        /// Returns the withdraw agent of a stream, if any.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        #[ink(message)]
+        pub fn get_agent(&self, stream_id: u64) -> Option<AccountId> {
+            self.withdraw_agents.get(stream_id)
+        }
+
+        /// Returns the balance that the recipient can withdraw right now.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream available balance, zero if nothing is available yet.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn available_balance(&self, stream_id: u64) -> Result<u128, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            match stream.get_available_balance(get_current_time_in_seconds(&self)) {
+                Err(ContractError::StreamAvailableBalanceIsZero) => Ok(0),
+                result => result,
+            }
+        }
+
+        /// Checks if an account can withdraw from a stream right now.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `who`: The account to check.
+        ///
+        /// Returns:
+        /// - `true` if the contract is not paused, the stream exists, `who` is its recipient or its authorized agent, the withdrawal cooldown of the stream has elapsed and the stream available balance is greater than zero.
+        #[ink(message)]
+        pub fn can_withdraw(&self, stream_id: u64, who: AccountId) -> bool {
+            let stream = match self.get_stream_by_id(stream_id) {
+                Ok(stream) => stream,
+                Err(_) => return false,
+            };
+
+            let current_time = get_current_time_in_seconds(&self);
+            self.ensure_not_paused().is_ok()
+                && self.ensure_can_withdraw(stream_id, &stream, who).is_ok()
+                && stream
+                    .ensure_withdraw_cooldown_elapsed(current_time)
+                    .is_ok()
+                && stream.get_available_balance(current_time).is_ok()
+        }
+
+        /// Checks if a stream has reached its end date.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - `true` if the stream is finished, `false` otherwise.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn is_stream_finished(&self, stream_id: u64) -> Result<bool, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok(stream.is_finished(get_current_time_in_seconds(&self)))
+        }
+
+        /// Returns the lifecycle status of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - `Pending`, `Active`, `Finished`, `FullyWithdrawn` or `Cancelled`, at the current time.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn stream_status(&self, stream_id: u64) -> Result<StreamStatus, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok(stream.status(get_current_time_in_seconds(&self)))
+        }
+
+        /// Returns the balance already withdrawn by the recipient from a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The difference between the stream original balance and its current balance.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn withdrawn_amount(&self, stream_id: u64) -> Result<u128, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok(stream.withdrawn_balance())
+        }
+
+        /// Returns the number of streams created or imported.
+        ///
+        /// NOTES
+        /// -----
+        /// - Imported streams keep their IDs, so the count can be lower than the highest stream ID.
+        #[ink(message)]
+        pub fn total_streams(&self) -> u64 {
+            self.stream_count
+        }
+
+        /// Returns the total funds ever streamed through the contract, as the sum of the funds of every created stream.
+        ///
+        /// NOTES
+        /// -----
+        /// - Replaced streams count twice, since the unvested balance funds a new stream. Top-ups and commitments are not included.
+        #[ink(message)]
+        pub fn total_streamed_volume(&self) -> u128 {
+            self.total_streamed_volume
+        }
+
+        /// Returns the total funds ever withdrawn from streams by their recipients, including protocol fees.
+        #[ink(message)]
+        pub fn total_withdrawn_volume(&self) -> u128 {
+            self.total_withdrawn_volume
+        }
+
+        /// Returns the minimum duration that a stream can have, measured in seconds.
+        ///
+        /// NOTES
+        /// -----
+        /// - It is `STREAM_MINIMUM_DURATION` unless the contract was instantiated with `new_with_min_duration`.
+        #[ink(message)]
+        pub fn minimum_duration(&self) -> u64 {
+            self.min_duration
+        }
+
+        /// Returns the maximum duration that a new stream can have, measured in seconds. Zero means no bound.
+        #[ink(message)]
+        pub fn maximum_duration(&self) -> u64 {
+            self.max_duration
+        }
+
+        /// Returns the ID that will be assigned to the next stream.
+        #[ink(message)]
+        pub fn peek_next_stream_id(&self) -> u64 {
+            self.next_stream_id
+        }
+
+        /// Returns the IDs of the stored streams within a range of IDs. Useful for block explorers.
+        ///
+        /// Parameters:
+        /// - `start`: The first stream ID of the range.
+        /// - `limit`: The number of stream IDs of the range. Capped to `MAX_PAGE_SIZE`.
+        ///
+        /// Returns:
+        /// - The stream IDs in `[start, start + limit)`, skipping the closed ones.
+        ///
+        /// NOTES
+        /// -----
+        /// - The current `MAX_PAGE_SIZE` is 100 stream IDs. Fewer IDs are returned when the range has gaps, so the next page should start at `start + limit`.
+        #[ink(message)]
+        pub fn list_streams(&self, start: u64, limit: u64) -> Vec<u64> {
+            let end = start.saturating_add(limit.min(MAX_PAGE_SIZE));
+
+            (start..end)
+                .filter(|stream_id| self.streams.get(stream_id).is_some())
+                .collect()
+        }
+
+        /// Returns the IDs of the streams flowing to a recipient.
+        ///
+        /// Parameters:
+        /// - `recipient`: The recipient wallet address.
+        ///
+        /// Returns:
+        /// - The stream IDs in creation order. Cancelled streams are not included.
+        #[ink(message)]
+        pub fn get_streams_for_recipient(&self, recipient: AccountId) -> Vec<u64> {
+            self.streams_by_recipient.get(recipient).unwrap_or_default()
+        }
+
+        /// Returns the balance that a recipient can withdraw right now across all its streams.
+        ///
+        /// Parameters:
+        /// - `recipient`: The recipient wallet address.
+        ///
+        /// Returns:
+        /// - The sum of the available balances of the streams flowing to `recipient`. Cancelled streams are not included.
+        #[ink(message)]
+        pub fn total_available_for(&self, recipient: AccountId) -> u128 {
+            let current_time = get_current_time_in_seconds(&self);
+
+            self.get_streams_for_recipient(recipient)
+                .iter()
+                .filter_map(|stream_id| self.streams.get(stream_id))
+                .map(|stream| stream.get_available_balance(current_time).unwrap_or(0))
+                .fold(0, |total: u128, available| total.saturating_add(available))
+        }
+
+        /// Returns the IDs of the streams funded by a payer.
+        ///
+        /// Parameters:
+        /// - `payer`: The payer wallet address.
+        ///
+        /// Returns:
+        /// - The stream IDs in creation order. Cancelled streams are not included.
+        #[ink(message)]
+        pub fn get_streams_for_payer(&self, payer: AccountId) -> Vec<u64> {
+            self.streams_by_payer.get(payer).unwrap_or_default()
+        }
+
+        /// Returns the IDs of the streams where an account is the payer or the recipient.
+        ///
+        /// Parameters:
+        /// - `who`: The wallet address.
+        ///
+        /// Returns:
+        /// - The stream IDs in ascending order, without duplicates. Cancelled streams are not included.
+        #[ink(message)]
+        pub fn streams_involving(&self, who: AccountId) -> Vec<u64> {
+            let mut stream_ids = self.get_streams_for_payer(who);
+            stream_ids.extend(self.get_streams_for_recipient(who));
+            stream_ids.sort_unstable();
+            stream_ids.dedup();
+            stream_ids
+        }
+
+        /// Returns the IDs of the streams where an account is the payer and the ones where it is the recipient, for a unified activity view.
+        ///
+        /// Parameters:
+        /// - `account`: The wallet address.
+        ///
+        /// Returns:
+        /// - A tuple with the stream IDs funded by `account` and the stream IDs received by `account`, in creation order. Cancelled streams are not included.
+        ///
+        /// NOTES
+        /// -----
+        /// - Each list is bounded to the latest `MAX_PAGE_SIZE` streams. `get_streams_for_payer` and `get_streams_for_recipient` return the full lists.
+        /// - A stream from `account` to itself is included in both lists.
+        #[ink(message)]
+        pub fn streams_involving_by_role(&self, account: AccountId) -> (Vec<u64>, Vec<u64>) {
+            let latest = |mut stream_ids: Vec<u64>| {
+                let skipped = stream_ids.len().saturating_sub(MAX_PAGE_SIZE as usize);
+                stream_ids.drain(..skipped);
+                stream_ids
+            };
+
+            (
+                latest(self.get_streams_for_payer(account)),
+                latest(self.get_streams_for_recipient(account)),
+            )
+        }
+
+        /// Returns a stream by its ID.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The expected stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        ///
+        /// Returns:
+        /// - The expected stream.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn get_stream_by_id(&self, stream_id: u64) -> Result<Stream, ContractError> {
+            match self.streams.get(&stream_id) {
+                Some(stream) => Ok(stream),
+                None => Err(ContractError::StreamDoesNotExist),
+            }
+        }
+
+        /// Returns `true` if a stream with the given ID exists. Cancelled streams still exist.
+        #[ink(message)]
+        pub fn stream_exists(&self, stream_id: u64) -> bool {
+            self.streams.get(&stream_id).is_some()
+        }
+
+        /// Returns the payer and the recipient of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - A tuple with the payer and the recipient.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn parties(&self, stream_id: u64) -> Result<(AccountId, AccountId), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok((stream.payer, stream.recipient))
+        }
+
+        /// Returns the original balance of a stream, so clients don't need to decode the whole stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream original balance.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn original_balance_of(&self, stream_id: u64) -> Result<u128, ContractError> {
+            Ok(self.get_stream_by_id(stream_id)?.original_balance)
+        }
+
+        /// Returns the current balance of a stream, so clients don't need to decode the whole stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream current balance.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn current_balance_of(&self, stream_id: u64) -> Result<u128, ContractError> {
+            Ok(self.get_stream_by_id(stream_id)?.current_balance)
+        }
+
+        /// Returns the start date of a stream, so clients don't need to decode the whole stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream start date, measured in seconds.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn start_date_of(&self, stream_id: u64) -> Result<u64, ContractError> {
+            Ok(self.get_stream_by_id(stream_id)?.start_date)
+        }
+
+        /// Returns the end date of a stream, so clients don't need to decode the whole stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream end date, measured in seconds.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn end_date_of(&self, stream_id: u64) -> Result<u64, ContractError> {
+            Ok(self.get_stream_by_id(stream_id)?.end_date)
+        }
+
+        /// Withdraws all the available balance of a stream and wraps it, crediting the recipient with the wrapped tokens.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `wrapper`: The wrapper contract address. Should implement `Wrapper::deposit_for`.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The contract should stream the native token.
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream or its authorized agent.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished and already emptied.
+        /// - The withdrawal amount should not be lower than the existential deposit, if configured, unless it is the whole stream balance.
+        ///
+        /// Behavior:
+        /// - The current stream balance will be reduced by the available balance.
+        /// - The protocol fee, if configured and the recipient is not exempt, will be transferred to the contract owner.
+        /// - The rest of the available balance will be sent to the wrapper contract for the recipient account.
+        /// - If the wrapper call fails, the stream balance is restored.
+        ///
+        /// Returns:
+        /// - The net amount of tokens wrapped for the recipient.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - UnsupportedAsset
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamFullyWithdrawn
+        /// - StreamAvailableBalanceIsZero
+        /// - AmountBelowExistential
+        /// - WithdrawOnCooldown
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        /// - WrapFailed
+        #[ink(message)]
+        pub fn recipient_withdraw_wrapped(
+            &mut self,
+            stream_id: u64,
+            wrapper: AccountId,
+        ) -> Result<u128, ContractError> {
+            if self.token.is_some() {
+                return Err(ContractError::UnsupportedAsset);
+            }
+
+            let (stream, amount_to_withdraw) = self.prepare_withdrawal(stream_id, None)?;
+            self.ensure_above_existential_deposit(&stream, amount_to_withdraw)?;
+
+            self.withdraw_from_stream(stream_id, stream, amount_to_withdraw, Some(wrapper), None)
+        }
+
+        /// Cancels a stream, paying the recipient the vested balance and refunding the rest to the payer.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled already.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable.
+        ///
+        /// Behavior:
+        /// - The vested balance not withdrawn yet will be transferred to the recipient.
+        /// - The co-payers will be refunded their share of the unvested balance, in proportion to their contributions.
+        /// - The rest of the unvested balance will be transferred back to the payer, or to its refund address if specified.
+        /// - The stream will be closed and marked as cancelled, so no further withdrawals are allowed.
+        /// - The stream will be removed from the recipient and payer streams indexes.
+        /// - Emits `ContributionRefunded` for each co-payer refunded.
+        /// - Emits `StreamCancelled` with the recipient payout and the refund received by the payer.
+        ///
+        /// Returns:
+        /// - A tuple with the recipient payout and the refund received by the payer, excluding the co-payers shares.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - OnlyPayerCanCancel
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - ArithmeticOverflow
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn cancel_stream(&mut self, stream_id: u64) -> Result<(u128, u128), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::OnlyPayerCanCancel);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            let funded_balance = stream.original_balance;
+            self.total_locked = self.total_locked.saturating_sub(stream.current_balance);
+            let (recipient_payout, payer_refund) =
+                stream.settle(get_current_time_in_seconds(&self))?;
+            stream.cancelled = true;
+            self.streams.insert(stream_id, &stream);
+
+            self.remove_from_recipient_index(stream.recipient, stream_id);
+            self.remove_from_payer_index(stream.payer, stream_id);
+
+            if recipient_payout > 0 {
+                self.transfer_funds(stream.payout_destination(), recipient_payout)?;
+            }
+
+            let payer_share = self.refund_contributors(stream_id, funded_balance, payer_refund)?;
+            self.remove_contributions(stream_id);
+            if payer_share > 0 {
+                self.transfer_funds(stream.refund_destination(), payer_share)?;
+            }
+
+            self.env().emit_event(StreamCancelled {
+                stream_id,
+                recipient_paid: recipient_payout,
+                payer_refunded: payer_share,
+            });
+            self.env().emit_event(StreamDrained { stream_id });
+            self.notify_stream_subscriber(stream_id, StreamEventKind::Cancellation);
+
+            self.check_invariants();
+
+            Ok((recipient_payout, payer_share))
+        }
+
+        /// Cancels the active streams funded by the sender, as `cancel_stream` does for each one.
+        ///
+        /// Behavior:
+        /// - Finished streams are skipped, so their recipients can still withdraw the whole balance.
+        /// - Immutable and non-cancellable streams are skipped.
+        /// - At most `MAX_BATCH_SIZE` streams are cancelled per call.
+        ///
+        /// Returns:
+        /// - A list of `(stream_id, payer_refund)` tuples for the cancelled streams, in creation order. The refunds exclude the co-payers shares.
+        ///
+        /// Errors:
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - Cancelled streams are removed from the payer index, so calling it again cancels the remaining ones.
+        #[ink(message)]
+        pub fn cancel_all_streams(&mut self) -> Result<Vec<(u64, u128)>, ContractError> {
+            let current_time = get_current_time_in_seconds(&self);
+            let mut refunds = Vec::new();
+
+            for stream_id in self.get_streams_for_payer(self.env().caller()) {
+                if refunds.len() == MAX_BATCH_SIZE {
+                    break;
+                }
+
+                let stream = self.get_stream_by_id(stream_id)?;
+                if stream.is_finished(current_time)
+                    || stream.can_be_modified().is_err()
+                    || stream.ensure_cancellable().is_err()
+                {
+                    continue;
+                }
+
+                let (_, payer_refund) = self.cancel_stream(stream_id)?;
+                refunds.push((stream_id, payer_refund));
+            }
+
+            Ok(refunds)
+        }
+
+        /// Renounces a stream, paying the recipient the vested balance and refunding the rest to the payer.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream.
+        /// - The stream should not be cancelled already.
+        ///
+        /// Behavior:
+        /// - The vested balance not withdrawn yet will be transferred to the recipient.
+        /// - The co-payers will be refunded their share of the unvested balance, in proportion to their contributions.
+        /// - The rest of the unvested balance will be transferred back to the payer, or to its refund address if specified.
+        /// - The stream will be closed and marked as cancelled, so no further withdrawals are allowed.
+        /// - The stream will be removed from the recipient and payer streams indexes.
+        /// - Emits `ContributionRefunded` for each co-payer refunded.
+        /// - Emits `StreamRenounced` with the recipient payout and the refund received by the payer.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - ArithmeticOverflow
+        /// - WithdrawTransferFailed
+        ///
+        /// NOTES
+        /// -----
+        /// - Immutable streams can be renounced, since the immutability only protects the recipient from the payer.
+        #[ink(message)]
+        pub fn renounce_stream(&mut self, stream_id: u64) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.recipient {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            let funded_balance = stream.original_balance;
+            self.total_locked = self.total_locked.saturating_sub(stream.current_balance);
+            let (recipient_payout, payer_refund) =
+                stream.settle(get_current_time_in_seconds(&self))?;
+            stream.cancelled = true;
+            self.streams.insert(stream_id, &stream);
+
+            self.remove_from_recipient_index(stream.recipient, stream_id);
+            self.remove_from_payer_index(stream.payer, stream_id);
+
+            if recipient_payout > 0 {
+                self.transfer_funds(stream.payout_destination(), recipient_payout)?;
+            }
+
+            let payer_share = self.refund_contributors(stream_id, funded_balance, payer_refund)?;
+            self.remove_contributions(stream_id);
+            if payer_share > 0 {
+                self.transfer_funds(stream.refund_destination(), payer_share)?;
+            }
+
+            self.env().emit_event(StreamRenounced {
+                stream_id,
+                recipient_paid: recipient_payout,
+                payer_refunded: payer_share,
+            });
+            self.env().emit_event(StreamDrained { stream_id });
+            self.notify_stream_subscriber(stream_id, StreamEventKind::Cancellation);
+
+            self.check_invariants();
+
+            Ok(())
+        }
+
+        /// Pledges funds to be added to a stream at a future date.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `due_date`: The date when the funds should be added, measured in seconds.
+        /// - `amount`: The amount of funds pledged.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - `amount` should be greater than zero.
+        /// - `due_date` should be earlier than the stream end date, so the commitment can be fulfilled before the stream finishes.
+        /// - The stream should not have more than `MAX_COMMITMENTS` pending commitments.
+        ///
+        /// Behavior:
+        /// - The commitment will be stored until it is fulfilled.
+        ///
+        /// Errors:
+        /// - UnsupportedAsset
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - EmptyFunds
+        /// - InvalidCommitment
+        /// - TooManyCommitments
+        ///
+        /// NOTES
+        /// -----
+        /// - Commitments are not binding. The stream available balance only includes funds that were actually transferred.
+        /// - Not supported on contracts streaming a PSP22 token, since commitments are fulfilled with the transferred value.
+        #[ink(message)]
+        pub fn add_commitment(
+            &mut self,
+            stream_id: u64,
+            due_date: u64,
+            amount: u128,
+        ) -> Result<(), ContractError> {
+            if self.token.is_some() {
+                return Err(ContractError::UnsupportedAsset);
+            }
+
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+            stream.ensure_valid_commitment(due_date, amount)?;
+
+            let mut commitments = self.commitments.get(stream_id).unwrap_or_default();
+            if commitments.len() >= MAX_COMMITMENTS {
+                return Err(ContractError::TooManyCommitments);
+            }
+
+            commitments.push((due_date, amount));
+            self.commitments.insert(stream_id, &commitments);
+
+            Ok(())
+        }
+
+        /// Adds the funds of every due commitment to a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - **Transaction funds:** The sum of the due commitments.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished, as in `top_up_stream`.
+        /// - At least one commitment should be due.
+        /// - The transaction funds should be equal to the sum of the due commitments.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        ///
+        /// Behavior:
+        /// - The funds will be added to the stream original and current balances.
+        /// - As in `top_up_stream`, the balance vested so far is kept and the new funds vest over the remaining duration.
+        /// - The fulfilled commitments will be removed.
+        ///
+        /// Returns:
+        /// - The new stream current balance.
+        ///
+        /// Errors:
+        /// - UnsupportedAsset
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamAlreadyFinished
+        /// - NoCommitmentDue
+        /// - ArithmeticOverflow
+        /// - CommitmentAmountMismatch
+        /// - TvlCapExceeded
+        ///
+        /// NOTES
+        /// -----
+        /// - Not supported on contracts streaming a PSP22 token, since the funds are taken from the transferred value.
+        #[ink(message, payable)]
+        pub fn fulfill_commitment(&mut self, stream_id: u64) -> Result<u128, ContractError> {
+            if self.token.is_some() {
+                return Err(ContractError::UnsupportedAsset);
+            }
+
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+
+            if current_time >= stream.end_date {
+                return Err(ContractError::StreamAlreadyFinished);
+            }
+
+            let (due_commitments, pending_commitments): (Vec<_>, Vec<_>) = self
+                .commitments
+                .get(stream_id)
+                .unwrap_or_default()
+                .into_iter()
+                .partition(|(due_date, _)| *due_date <= current_time);
+
+            if due_commitments.is_empty() {
+                return Err(ContractError::NoCommitmentDue);
+            }
+
+            let due_amount = due_commitments
+                .iter()
+                .try_fold(0u128, |total, (_, amount)| total.checked_add(*amount))
+                .ok_or(ContractError::ArithmeticOverflow)?;
+            let funds = self.transferred_funds()?;
+
+            if funds != due_amount {
+                return Err(ContractError::CommitmentAmountMismatch);
+            }
+
+            validate_tvl_cap(self.total_locked, funds, self.tvl_cap)?;
+            let total_locked = self
+                .total_locked
+                .checked_add(funds)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            stream.top_up(funds, current_time)?;
+            self.streams.insert(stream_id, &stream);
+            self.commitments.insert(stream_id, &pending_commitments);
+            self.total_locked = total_locked;
+
+            self.check_invariants();
+
+            Ok(stream.current_balance)
+        }
+
+        /// Adds funds to an existing stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - **Transaction funds:** The amount of funds to be added to the stream.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender can't be the recipient of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished.
+        /// - The sender should send funds in the transaction.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - The stream should not have more than `MAX_CONTRIBUTORS` co-payers.
+        ///
+        /// Behavior:
+        /// - The funds will be added to the stream original and current balances.
+        /// - The top-up stretches vesting: the balance vested so far is kept and the new funds vest over the remaining duration, so the end date doesn't change.
+        /// - If the sender is not the payer, the funds will be recorded as its contribution, making it a co-payer of the stream.
+        /// - Emits `StreamToppedUp` with the added funds and the new current balance.
+        ///
+        /// Returns:
+        /// - The new stream current balance.
+        ///
+        /// Errors:
+        /// - UnsupportedAsset
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamAlreadyFinished
+        /// - EmptyFunds
+        /// - TvlCapExceeded
+        /// - ArithmeticOverflow
+        /// - TooManyContributors
+        ///
+        /// NOTES
+        /// -----
+        /// - Not supported on contracts streaming a PSP22 token, since the funds are taken from the transferred value.
+        /// - Every refund of the stream, such as `cancel_stream`, `renounce_stream`, `reduce_stream`, `replace_stream` or `reclaim_expired`, is split between the payer and the co-payers, in proportion to their contributions.
+        /// - Streams with co-payers can't be forked, since the co-payers can't be recorded on the new stream.
+        #[ink(message, payable)]
+        pub fn top_up_stream(&mut self, stream_id: u64) -> Result<u128, ContractError> {
+            if self.token.is_some() {
+                return Err(ContractError::UnsupportedAsset);
+            }
+
+            let mut stream = self.get_stream_by_id(stream_id)?;
+            let caller = self.env().caller();
+
+            if caller == stream.recipient {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.ensure_active()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+
+            if current_time >= stream.end_date {
+                return Err(ContractError::StreamAlreadyFinished);
+            }
+
+            let funds = self.transferred_funds()?;
+
+            if funds == 0 {
+                return Err(ContractError::EmptyFunds);
+            }
+
+            validate_tvl_cap(self.total_locked, funds, self.tvl_cap)?;
+            let total_locked = self
+                .total_locked
+                .checked_add(funds)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            stream.top_up(funds, current_time)?;
+            if caller != stream.payer {
+                self.record_contribution(stream_id, caller, funds)?;
+            }
+            self.streams.insert(stream_id, &stream);
+            self.total_locked = total_locked;
+
+            self.env().emit_event(StreamToppedUp {
+                stream_id,
+                added: funds,
+                new_balance: stream.current_balance,
+            });
+
+            self.check_invariants();
+
+            Ok(stream.current_balance)
+        }
+
+        /// Pushes out the end date of a stream, slowing down its flow rate. The stream balance doesn't change.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `new_end_date`: The new end date of the stream measured in seconds.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable, since extending it delays the unvested balance.
+        /// - The stream should not be finished.
+        /// - `new_end_date` should not be earlier than the current end date.
+        /// - The interval of a stepped stream should divide the new stream duration evenly.
+        ///
+        /// Behavior:
+        /// - The balance vested so far is kept and the unvested balance vests until `new_end_date`.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - StreamAlreadyFinished
+        /// - CannotShortenStream
+        /// - InvalidVestingInterval
+        #[ink(message)]
+        pub fn extend_stream(
+            &mut self,
+            stream_id: u64,
+            new_end_date: u64,
+        ) -> Result<(), ContractError> {
+            let mut stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+
+            if stream.is_finished(current_time) {
+                return Err(ContractError::StreamAlreadyFinished);
+            }
+
+            if new_end_date < stream.end_date {
+                return Err(ContractError::CannotShortenStream);
+            }
+
+            validate_and_generate_vesting_kind(
+                Some(stream.vesting),
+                stream.start_date,
+                new_end_date,
+            )?;
+
+            stream.extend(new_end_date, current_time)?;
+            self.streams.insert(stream_id, &stream);
+
+            Ok(())
+        }
+
+        /// Replaces a stream with a new one in a single step. The old recipient receives the vested balance and the unvested balance funds the new stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The ID of the stream to be replaced.
+        /// - `new_recipient`: The recipient wallet address of the new stream.
+        /// - `new_end_date`: The end date of the new stream measured in seconds.
+        /// - **Transaction funds:** Additional funds to be added to the new stream. Can be zero.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        /// - The stream should not be immutable.
+        /// - The stream should be cancellable, since the old stream is closed.
+        /// - The sender should not exceed the maximum number of active streams per payer, not counting the old stream.
+        /// - The new stream parameters should be valid.
+        ///   * The sender can't be the new recipient.
+        ///   * The new stream should have funds.
+        ///   * `new_end_date` should respect the **minimum duration**.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        ///
+        /// Behavior:
+        /// - The vested balance not withdrawn yet will be transferred to the old recipient.
+        /// - The co-payers of the old stream will be refunded their share of the unvested balance, in proportion to their contributions, and its contributions will be removed.
+        /// - The old stream will be cancelled as `cancel_stream` does: it ends now, its current balance will be zero and it is removed from the payer and recipient indexes.
+        /// - The commitments and the withdraw agent of the old stream will be removed, and its subscriber will be notified and removed.
+        /// - A new stream starting now will be funded with the payer share of the unvested balance of the old stream plus the transaction funds.
+        /// - The new stream keeps the memo, the token decimals, the withdraw cooldown, the rounding and the refund address of the old stream.
+        /// - The new stream is mutable and cancellable, like the old stream had to be.
+        /// - The new stream has no cliff, no completion bonus and vests linearly, since they were agreed with the old recipient. The completion bonus is part of the unvested balance funding the new stream.
+        /// - The new stream has no payout address, since it belonged to the old recipient.
+        /// - Emits `StreamCancelled` for the old stream with a zero payer refund, since the unvested balance funds the new stream instead.
+        ///
+        /// Returns:
+        /// - The new stream ID.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamImmutable
+        /// - StreamNotCancellable
+        /// - StreamLimitReached
+        /// - ArithmeticOverflow
+        /// - RecipientCannotBePayer
+        /// - EmptyFunds
+        /// - AmountTooSmall
+        /// - AmountTooLarge
+        /// - StreamEndDateInPast
+        /// - StreamDurationShouldBeGreater
+        /// - TvlCapExceeded
+        /// - WithdrawTransferFailed
+        #[ink(message, payable)]
+        pub fn replace_stream(
+            &mut self,
+            stream_id: u64,
+            new_recipient: AccountId,
+            new_end_date: u64,
+        ) -> Result<u64, ContractError> {
+            self.ensure_not_paused()?;
+            let mut stream = self.get_stream_by_id(stream_id)?;
+            let caller = self.env().caller();
+
+            if caller != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            stream.can_be_modified()?;
+            stream.ensure_cancellable()?;
+            // The new stream takes the slot of the old one.
+            self.ensure_stream_slots(caller, 0)?;
+
+            let current_time = get_current_time_in_seconds(&self);
+            let additional_funds = self.transferred_funds()?;
+            let funded_balance = stream.original_balance;
+            let (recipient_payout, payer_refund) = stream.cancellation_split(current_time)?;
+            let (_, payer_share) =
+                self.contributor_shares(stream_id, funded_balance, payer_refund)?;
+            let new_stream_funds = payer_share
+                .checked_add(additional_funds)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            validate_stream_creation_parameters(
+                caller,
+                new_recipient,
+                self.env().account_id(),
+                new_stream_funds,
+                self.min_stream_amount,
+                self.max_stream_amount,
+            )?;
+            self.ensure_recipient_allowed(new_recipient)?;
+            validate_tvl_cap(self.total_locked, additional_funds, self.tvl_cap)?;
+            let new_end_date = validate_and_generate_stream_end_date(
+                Some(new_end_date),
+                None,
+                current_time,
+                self.min_duration,
+                self.max_duration,
+            )?;
+
+            if recipient_payout > 0 {
+                self.transfer_funds(stream.payout_destination(), recipient_payout)?;
+            }
+
+            self.refund_contributors(stream_id, funded_balance, payer_refund)?;
+            self.remove_contributions(stream_id);
+
+            self.total_locked = self.total_locked.saturating_sub(stream.current_balance);
+            stream.settle(current_time)?;
+            stream.cancelled = true;
+            self.streams.insert(stream_id, &stream);
+
+            self.remove_from_recipient_index(stream.recipient, stream_id);
+            self.remove_from_payer_index(stream.payer, stream_id);
+            self.commitments.remove(stream_id);
+            self.withdraw_agents.remove(stream_id);
+
+            self.env().emit_event(StreamCancelled {
+                stream_id,
+                recipient_paid: recipient_payout,
+                payer_refunded: 0,
+            });
+            self.env().emit_event(StreamDrained { stream_id });
+            self.notify_stream_subscriber(stream_id, StreamEventKind::Cancellation);
+            self.subscribers.remove(stream_id);
+
+            let mut new_stream = Stream::new(
+                caller,
+                new_recipient,
+                new_stream_funds,
+                current_time,
+                new_end_date,
+            );
+            new_stream.memo = stream.memo;
+            new_stream.token_decimals = stream.token_decimals;
+            new_stream.withdraw_cooldown = stream.withdraw_cooldown;
+            new_stream.rounding = stream.rounding;
+            new_stream.refund_to = stream.refund_to;
+
+            self.store_new_stream(&new_stream)
+        }
+
+        /// Returns the pending commitments of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - A list of `(due_date, amount)` tuples.
+        #[ink(message)]
+        pub fn get_commitments(&self, stream_id: u64) -> Vec<(u64, u128)> {
+            self.commitments.get(stream_id).unwrap_or_default()
+        }
+
+        /// Returns the full audit trail of a stream in a single call.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Behavior:
+        /// - Only the first `MAX_AUDIT_COMMITMENTS` pending commitments are included. `commitments_truncated` is set when there are more.
+        ///
+        /// Returns:
+        /// - The stream audit.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn stream_audit(&self, stream_id: u64) -> Result<StreamAudit, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            let current_time = get_current_time_in_seconds(&self);
+
+            let mut commitments = self.get_commitments(stream_id);
+            let commitments_truncated = commitments.len() > MAX_AUDIT_COMMITMENTS;
+            commitments.truncate(MAX_AUDIT_COMMITMENTS);
+
+            Ok(StreamAudit {
+                stream,
+                created_at: stream.created_at,
+                last_withdraw_at: stream.last_withdraw_at,
+                withdrawn_balance: stream.withdrawn_balance(),
+                available_balance: stream.get_available_balance(current_time).unwrap_or(0),
+                commitments,
+                commitments_truncated,
+                is_finished: stream.is_finished(current_time),
+            })
+        }
+
+        /// Returns a stream along with its balances computed at the current time.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The stream details.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn get_stream_details(&self, stream_id: u64) -> Result<StreamDetails, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            let current_time = get_current_time_in_seconds(&self);
+
+            Ok(StreamDetails {
+                stream,
+                available_balance: stream.get_available_balance(current_time).unwrap_or(0),
+                withdrawn_amount: stream.withdrawn_balance(),
+                is_finished: stream.is_finished(current_time),
+            })
+        }
+
+        /// Returns several streams along with their balances computed at the current time.
+        ///
+        /// Parameters:
+        /// - `stream_ids`: The stream IDs. Only the first `MAX_PAGE_SIZE` IDs are considered.
+        ///
+        /// Returns:
+        /// - The `get_stream_details` result of each stream, in the same order as `stream_ids`. Missing streams don't fail the whole call.
+        #[ink(message)]
+        pub fn get_streams_details(
+            &self,
+            stream_ids: Vec<u64>,
+        ) -> Vec<Result<StreamDetails, ContractError>> {
+            stream_ids
+                .into_iter()
+                .take(MAX_PAGE_SIZE as usize)
+                .map(|stream_id| self.get_stream_details(stream_id))
+                .collect()
+        }
+
+        /// Returns the date of the last withdrawal of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        ///
+        /// Returns:
+        /// - The date of the last withdrawal measured in seconds, or `None` if nothing was withdrawn yet.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn last_withdrawal_time(&self, stream_id: u64) -> Result<Option<u64>, ContractError> {
+            Ok(self.get_stream_by_id(stream_id)?.last_withdraw_at)
+        }
+
+        /// Returns the latest withdrawals made from a stream, with any of the withdrawal messages.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - Up to `MAX_WITHDRAWAL_HISTORY` `(date, amount)` tuples from the oldest to the newest. The amount includes the protocol fee.
+        #[ink(message)]
+        pub fn withdrawal_history(&self, stream_id: u64) -> Vec<(u64, u128)> {
+            self.withdrawal_history.get(stream_id).unwrap_or_default()
+        }
+
+        /// Returns the funds a co-payer added to a stream with `top_up_stream`.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `contributor`: The co-payer wallet address.
+        ///
+        /// Returns:
+        /// - The contribution, or zero if the account is not a co-payer of the stream. The payer funds are not included.
+        #[ink(message)]
+        pub fn get_contribution(&self, stream_id: u64, contributor: AccountId) -> u128 {
+            self.contributions
+                .get((stream_id, contributor))
+                .unwrap_or(0)
+        }
+
+        /// Returns the co-payers of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The co-payers wallet addresses, in order of their first contribution.
+        #[ink(message)]
+        pub fn get_contributors(&self, stream_id: u64) -> Vec<AccountId> {
+            self.contributors.get(stream_id).unwrap_or_default()
+        }
+
+        /// Checks if the stream available balance is worth withdrawing. Useful for keepers automating withdrawals.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `min_net`: The minimum amount of tokens that the recipient expects to receive.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        ///
+        /// Returns:
+        /// - `true` if the amount the recipient would receive, after the protocol fee, is greater or equal than `min_net`, `false` otherwise.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn is_worth_withdrawing(
+            &self,
+            stream_id: u64,
+            min_net: u128,
+        ) -> Result<bool, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+            let available_balance = stream
+                .get_available_balance(get_current_time_in_seconds(&self))
+                .unwrap_or(0);
+            let net_amount =
+                available_balance - self.withdrawal_fee(stream.recipient, available_balance);
+
+            Ok(net_amount >= min_net)
+        }
+
+        /// Estimates the outcome of a withdrawal without executing it.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `amount`: The amount of tokens to be withdrawn. If not specified, all the available balance is considered.
+        ///
+        /// Validations:
+        /// - If `amount` has value, should be greater than zero.
+        /// - The stream should exist.
+        /// - The stream should not be cancelled.
+        /// - `amount` should not exceed the available balance.
+        ///
+        /// Returns:
+        /// - A tuple with the net amount the recipient would receive and the protocol fee.
+        ///
+        /// Errors:
+        /// - WithdrawalAmountShouldBeGreaterThanZero
+        /// - StreamDoesNotExist
+        /// - StreamAlreadyCancelled
+        /// - StreamAvailableBalanceIsZero
+        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+        #[ink(message)]
+        pub fn preview_withdraw(
+            &self,
+            stream_id: u64,
+            amount: Option<u128>,
+        ) -> Result<(u128, u128), ContractError> {
+            validate_recipient_withdrawal_amount(amount)?;
+            let stream = self.get_stream_by_id(stream_id)?;
+            stream.ensure_active()?;
+
+            let available_balance =
+                stream.get_available_balance(get_current_time_in_seconds(&self))?;
+            let amount_to_withdraw = amount.unwrap_or(available_balance);
+
+            if amount_to_withdraw > available_balance {
+                return Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance);
+            }
+
+            let fee = self.withdrawal_fee(stream.recipient, amount_to_withdraw);
+
+            Ok((amount_to_withdraw - fee, fee))
+        }
+
+        /// Returns the difference between the contract balance and the funds locked in streams or escrowed for claimable streams.
+        ///
+        /// Returns:
+        /// - A positive value if the contract holds more funds than the locked ones (fees or stray funds).
+        /// - A negative value if the contract holds less funds than the locked ones, signaling an under-collateralization.
+        ///
+        /// NOTES
+        /// -----
+        /// - Values that don't fit in `i128` saturate.
+        #[ink(message)]
+        pub fn accounting_delta(&self) -> i128 {
+            let balance = i128::try_from(self.held_balance()).unwrap_or(i128::MAX);
+            let outstanding = i128::try_from(self.outstanding_balance()).unwrap_or(i128::MAX);
+
+            balance.saturating_sub(outstanding)
+        }
+
+        /// Returns the contract balance and the funds it owes to streams, to verify that the contract is solvent.
+        ///
+        /// Returns:
+        /// - A tuple with the contract balance and the sum of the current balance of all the streams plus the funds escrowed for claimable streams.
+        ///
+        /// NOTES
+        /// -----
+        /// - The outstanding funds are the total locked and escrowed funds, which are kept up to date on every change, so no stream is iterated.
+        #[ink(message)]
+        pub fn solvency(&self) -> (u128, u128) {
+            (self.held_balance(), self.outstanding_balance())
+        }
+
+        /// Transfers the contract balance not backing any stream, such as funds sent by mistake to the contract address.
+        ///
+        /// Parameters:
+        /// - `to`: The account receiving the surplus.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Behavior:
+        /// - The contract balance above the total locked funds and the funds escrowed for claimable streams will be transferred to `to`, so every stream stays fully backed.
+        /// - Nothing is transferred if there is no surplus.
+        ///
+        /// Returns:
+        /// - The amount of tokens swept.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        #[ink(message)]
+        pub fn sweep_unallocated(&mut self, to: AccountId) -> Result<u128, ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            let surplus = self.unallocated_balance();
+
+            if surplus > 0 {
+                self.transfer_funds(to, surplus)?;
+            }
+
+            Ok(surplus)
+        }
+
+        /// Creates a token stream from the contract owner backed by the contract balance not allocated to any stream, without transferring funds. Useful for integrations that already hold funds in the contract.
+        ///
+        /// Parameters:
+        /// - `recipient`: The recipient wallet address of the stream.
+        /// - `amount`: The amount of funds to be transferred to the recipient through the stream.
+        /// - `duration`: The duration of the stream, measured in seconds.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `amount` should not be greater than the contract balance above the total locked funds and the funds escrowed for claimable streams.
+        /// - The total locked funds should not exceed the TVL cap, if configured.
+        /// - Same as `create_stream`.
+        ///
+        /// Behavior:
+        /// - The stream payer is the contract owner and the stream starts immediately.
+        /// - `amount` is added to the total locked funds, so it can't be swept anymore.
+        ///
+        /// Returns:
+        /// - The created stream ID.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - InsufficientContractBalance
+        /// - TvlCapExceeded
+        /// - Same as `create_stream`.
+        #[ink(message)]
+        pub fn create_stream_from_balance(
+            &mut self,
+            recipient: AccountId,
+            amount: u128,
+            duration: u64,
+        ) -> Result<u64, ContractError> {
+            let caller = self.env().caller();
+            if caller != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.ensure_not_paused()?;
+            self.ensure_stream_slots(caller, 1)?;
+
+            if amount > self.unallocated_balance() {
+                return Err(ContractError::InsufficientContractBalance);
+            }
+
+            let new_stream =
+                self.build_new_stream(caller, recipient, None, None, Some(duration), amount)?;
+            validate_tvl_cap(self.total_locked, amount, self.tvl_cap)?;
+
+            self.store_new_stream(&new_stream)
+        }
+
+        /// Imports a stream from a prior version of the contract, keeping its ID.
+        ///
+        /// Parameters:
+        /// - `id`: The stream ID in the prior contract.
+        /// - `stream`: The stream, as returned by the prior contract.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - The migration should not be finalized.
+        /// - The stream should be consistent: not cancelled, ending after it starts and with balances not exceeding its original balance.
+        /// - No stream should be stored with the same ID.
+        ///
+        /// Behavior:
+        /// - The stream will be stored with the given ID and added to the recipient and payer streams indexes.
+        /// - The stream current balance will be added to the total locked funds.
+        /// - The next stream ID will be moved past the imported ID.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - MigrationFinalized
+        /// - ValidationError
+        /// - StreamIdAlreadyExists
+        /// - StreamIdSpaceExhausted
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - Imports are only allowed until `finalize_migration` is called or a stream is created through the normal flow.
+        /// - The funds backing the imported streams should be transferred to the contract separately.
+        #[ink(message)]
+        pub fn import_stream(&mut self, id: u64, stream: Stream) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if self.migration_finalized {
+                return Err(ContractError::MigrationFinalized);
+            }
+
+            stream.ensure_consistent()?;
+
+            let next_stream_id = id
+                .checked_add(1)
+                .ok_or(ContractError::StreamIdSpaceExhausted)?;
+            let total_locked = self
+                .total_locked
+                .checked_add(stream.current_balance)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            self.insert_new_stream(id, &stream)?;
+            self.next_stream_id = self.next_stream_id.max(next_stream_id);
+            self.total_locked = total_locked;
+
+            self.add_to_recipient_index(stream.recipient, id);
+            self.add_to_payer_index(stream.payer, id);
+
+            self.check_invariants();
+
+            Ok(())
+        }
+
+        /// Finalizes the streams migration, rejecting further imports.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn finalize_migration(&mut self) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.migration_finalized = true;
+
+            Ok(())
+        }
+
+        /// Registers a contract to be notified about the lifecycle changes of a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `subscriber`: The subscriber contract address. Should implement `StreamSubscriber::notify`. If not specified, the current subscriber is removed.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The sender should be the payer of the stream.
+        ///
+        /// Behavior:
+        /// - Only one subscriber per stream is allowed, so any previous subscriber is replaced.
+        /// - The subscriber is notified after withdrawals and cancellations. Notifications are best-effort, so a failing subscriber doesn't block the stream.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn set_subscriber(
+            &mut self,
+            stream_id: u64,
+            subscriber: Option<AccountId>,
+        ) -> Result<(), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            if self.env().caller() != stream.payer {
+                return Err(ContractError::Unauthorized);
+            }
+
+            match subscriber {
+                Some(subscriber) => self.subscribers.insert(stream_id, &subscriber),
+                None => self.subscribers.remove(stream_id),
+            }
+
+            Ok(())
+        }
+
+        /// Returns the subscriber contract of a stream, if any.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        #[ink(message)]
+        pub fn get_subscriber(&self, stream_id: u64) -> Option<AccountId> {
+            self.subscribers.get(stream_id)
+        }
+
+        /// Transfers the contract ownership to a new account.
+        ///
+        /// Parameters:
+        /// - `new_owner`: The new owner wallet address.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `new_owner` can't be the zero account.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - InvalidAccount
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if new_owner == AccountId::from([0u8; 32]) {
+                return Err(ContractError::InvalidAccount);
+            }
+
+            let previous_owner = self.owner;
+            self.owner = new_owner;
+
+            self.env().emit_event(OwnershipTransferred {
+                previous: previous_owner,
+                new: new_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Sets the protocol fee charged on withdrawals.
+        ///
+        /// Parameters:
+        /// - `fee_bps`: The fee in basis points. The fee is transferred to the contract owner.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `fee_bps` should not be greater than `MAX_FEE_BPS` (10%).
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - FeeTooHigh
+        #[ink(message)]
+        pub fn set_fee_bps(&mut self, fee_bps: u16) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if fee_bps > MAX_FEE_BPS {
+                return Err(ContractError::FeeTooHigh);
+            }
+
+            self.fee_bps = fee_bps;
+
+            Ok(())
+        }
+
+        /// Adds or removes a recipient from the protocol fee exemption list.
+        ///
+        /// Parameters:
+        /// - `account`: The recipient wallet address.
+        /// - `exempt`: `true` to withdraw without paying the protocol fee, `false` to pay it again.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn set_fee_exempt(
+            &mut self,
+            account: AccountId,
+            exempt: bool,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if exempt {
+                self.fee_exempt.insert(account, &true);
+            } else {
+                self.fee_exempt.remove(account);
+            }
+
+            Ok(())
+        }
+
+        /// Returns `true` if a recipient is exempt from the protocol fee.
+        ///
+        /// Parameters:
+        /// - `account`: The recipient wallet address.
+        #[ink(message)]
+        pub fn is_fee_exempt(&self, account: AccountId) -> bool {
+            self.fee_exempt.get(account).unwrap_or(false)
+        }
+
+        /// Sets the minimum amount that can be withdrawn with `recipient_withdraw`.
+        ///
+        /// Parameters:
+        /// - `existential_deposit`: The existential deposit of the chain. Zero disables the check.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        ///
+        /// NOTES
+        /// -----
+        /// - Transfers below the existential deposit fail on Substrate chains, so rejecting them early tells the recipient to wait for more funds to vest.
+        /// - The remainder of a stream can always be withdrawn, even if it is lower than the existential deposit, so it can't get stuck.
+        #[ink(message)]
+        pub fn set_existential_deposit(
+            &mut self,
+            existential_deposit: u128,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.existential_deposit = existential_deposit;
+
+            Ok(())
+        }
+
+        /// Pauses or unpauses the stream creation and the withdrawals, as a circuit breaker.
+        ///
+        /// Parameters:
+        /// - `paused`: If `true`, new streams can't be created and recipients can't withdraw.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        ///
+        /// NOTES
+        /// -----
+        /// - Queries keep working while the contract is paused.
+        #[ink(message)]
+        pub fn set_paused(&mut self, paused: bool) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.paused = paused;
+
+            Ok(())
+        }
+
+        /// Returns `true` if the contract is paused.
+        #[ink(message)]
+        pub fn is_paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Returns the contract version and its current settings.
+        #[ink(message)]
+        pub fn config(&self) -> ContractConfig {
+            ContractConfig {
+                version: CONTRACT_VERSION,
+                min_duration: self.min_duration,
+                fee_bps: self.fee_bps,
+                paused: self.paused,
+                owner: self.owner,
+            }
+        }
+
+        /// Sets the maximum amount of funds that the contract can hold in streams.
+        ///
+        /// Parameters:
+        /// - `tvl_cap`: The maximum total value locked. If not specified, the contract will be uncapped.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        ///
+        /// NOTES
+        /// -----
+        /// - Lowering the cap below the current total locked funds doesn't affect existing streams, it only blocks new funds.
+        #[ink(message)]
+        pub fn set_tvl_cap(&mut self, tvl_cap: Option<u128>) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.tvl_cap = tvl_cap;
+
+            Ok(())
+        }
+
+        /// Sets the minimum and maximum funds that a new stream can have.
+        ///
+        /// Parameters:
+        /// - `min_stream_amount`: The minimum funds of a stream. Zero disables the check.
+        /// - `max_stream_amount`: The maximum funds of a stream. Zero disables the check.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `min_stream_amount` should not be greater than `max_stream_amount`, if both are configured.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - ValidationError
+        ///
+        /// NOTES
+        /// -----
+        /// - The bounds don't affect existing streams, top-ups or commitments.
+        #[ink(message)]
+        pub fn set_stream_amount_bounds(
+            &mut self,
+            min_stream_amount: u128,
+            max_stream_amount: u128,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if max_stream_amount > 0 && min_stream_amount > max_stream_amount {
+                return Err(ContractError::ValidationError);
+            }
+
+            self.min_stream_amount = min_stream_amount;
+            self.max_stream_amount = max_stream_amount;
+
+            Ok(())
+        }
+
+        /// Sets the maximum duration that a new stream can have.
+        ///
+        /// Parameters:
+        /// - `max_duration`: The maximum duration of a stream, measured in seconds. Zero disables the check.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - `max_duration` should not be lower than the stream minimum duration, if configured.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - ValidationError
+        ///
+        /// NOTES
+        /// -----
+        /// - The maximum applies to streams defined either by `end_date` or by `duration`. It doesn't affect existing streams.
+        #[ink(message)]
+        pub fn set_max_duration(&mut self, max_duration: u64) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if max_duration > 0 && max_duration < self.min_duration {
+                return Err(ContractError::ValidationError);
+            }
+
+            self.max_duration = max_duration;
+
+            Ok(())
+        }
+
+        /// Returns the minimum and maximum funds that a new stream can have. Zero means no bound.
+        #[ink(message)]
+        pub fn stream_amount_bounds(&self) -> (u128, u128) {
+            (self.min_stream_amount, self.max_stream_amount)
+        }
+
+        /// Enables or disables the recipient allowlist. While enabled, streams can only flow to allowed recipients.
+        ///
+        /// Parameters:
+        /// - `enabled`: `true` to enforce the allowlist, `false` to allow any recipient.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        ///
+        /// NOTES
+        /// -----
+        /// - Existing streams are not affected, their recipients can keep withdrawing.
+        #[ink(message)]
+        pub fn set_allowlist_enabled(&mut self, enabled: bool) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            self.allowlist_enabled = enabled;
+
+            Ok(())
+        }
+
+        /// Returns `true` if the recipient allowlist is enforced.
+        #[ink(message)]
+        pub fn is_allowlist_enabled(&self) -> bool {
+            self.allowlist_enabled
+        }
+
+        /// Adds or removes an account from the recipient allowlist.
+        ///
+        /// Parameters:
+        /// - `account`: The recipient wallet address.
+        /// - `allowed`: `true` to allow the account to receive streams, `false` to remove it from the allowlist.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        #[ink(message)]
+        pub fn set_recipient_allowed(
+            &mut self,
+            account: AccountId,
+            allowed: bool,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if allowed {
+                self.allowed_recipients.insert(account, &true);
+            } else {
+                self.allowed_recipients.remove(account);
+            }
+
+            Ok(())
+        }
+
+        /// Returns `true` if an account is in the recipient allowlist.
+        ///
+        /// Parameters:
+        /// - `account`: The recipient wallet address.
+        #[ink(message)]
+        pub fn is_recipient_allowed(&self, account: AccountId) -> bool {
+            self.allowed_recipients.get(account).unwrap_or(false)
+        }
+
+        /// Sets the duration used to create streams when neither `end_date` nor `duration` are specified.
+        ///
+        /// Parameters:
+        /// - `default_duration`: The default duration measured in seconds. If not specified, creating a stream without dates will fail.
+        ///
+        /// Validations:
+        /// - The sender should be the contract owner.
+        /// - If `default_duration` has value, should be greater than the **minimum duration**.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        /// - StreamDurationShouldBeGreater
+        ///
+        /// NOTES
+        /// -----
+        /// - The stream **minimum duration** is configured when the contract is instantiated. It is 5 minutes by default.
+        #[ink(message)]
+        pub fn set_default_duration(
+            &mut self,
+            default_duration: Option<u64>,
+        ) -> Result<(), ContractError> {
+            if self.env().caller() != self.owner {
+                return Err(ContractError::Unauthorized);
+            }
+
+            if let Some(default_duration) = default_duration {
+                validate_stream_duration(default_duration, self.min_duration)?;
+            }
+
+            self.default_duration = default_duration;
+
+            Ok(())
+        }
+
+        /// Simulates the vesting of a hypothetical stream without storing anything.
+        ///
+        /// Parameters:
+        /// - `balance`: The balance of the hypothetical stream.
+        /// - `start`: The start date of the hypothetical stream measured in seconds.
+        /// - `end`: The end date of the hypothetical stream measured in seconds.
+        /// - `at`: The date to calculate the vested amount at, measured in seconds.
+        ///
+        /// Returns:
+        /// - The amount vested at `at`.
+        ///
+        /// NOTES
+        /// -----
+        /// - Uses the same math as the stored streams. Nothing is vested before `start` and the whole balance is vested from `end`.
+        #[ink(message)]
+        pub fn simulate_vesting(&self, balance: u128, start: u64, end: u64, at: u64) -> u128 {
+            Stream::compute_unlocked(balance, start, end, at, Rounding::Down)
+        }
+
+        /// Returns the amount that a recipient will be able to withdraw from a stream at a given time.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `timestamp`: The date to calculate the available balance at, measured in seconds. Can be in the past or the future.
+        ///
+        /// Returns:
+        /// - The stream available balance at `timestamp`, zero before the stream starts or its cliff date is reached.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - The balance already withdrawn is discounted, assuming no other withdrawal happens until `timestamp`.
+        #[ink(message)]
+        pub fn vested_at(&self, stream_id: u64, timestamp: u64) -> Result<u128, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            match stream.get_available_balance(timestamp) {
+                Err(ContractError::StreamAvailableBalanceIsZero) => Ok(0),
+                result => result,
+            }
+        }
+
+        /// Returns the amount streamed per second by a stream.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The stream duration should be greater than zero.
+        ///
+        /// Returns:
+        /// - The stream flow rate.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - InvalidStreamDuration
+        ///
+        /// NOTES
+        /// -----
+        /// - The rate is rounded down, so the stream may vest slightly more than `flow_rate * duration` in total.
+        #[ink(message)]
+        pub fn flow_rate(&self, stream_id: u64) -> Result<u128, ContractError> {
+            self.get_stream_by_id(stream_id)?.flow_rate()
+        }
+
+        /// Returns the amount streamed per second by a stream from now on.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The unvested balance divided by the time left until the end date, or zero if the stream is finished.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - Unlike `flow_rate`, it reflects top-ups and extensions made while the stream is running.
+        #[ink(message)]
+        pub fn current_flow_rate(&self, stream_id: u64) -> Result<u128, ContractError> {
+            self.get_stream_by_id(stream_id)?
+                .current_flow_rate(get_current_time_in_seconds(&self))
+        }
+
+        /// Returns the key points of a stream schedule in a single call, for tooling building vesting calendars.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - A tuple with the start date, the end date, the original balance and the flow rate of the stream.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - InvalidStreamDuration
+        #[ink(message)]
+        pub fn schedule(&self, stream_id: u64) -> Result<(u64, u64, u128, u128), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok((
+                stream.start_date,
+                stream.end_date,
+                stream.original_balance,
+                stream.flow_rate()?,
+            ))
+        }
+
+        /// Returns how much of a stream duration has elapsed, in basis points. Useful for progress bars.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - A value from 0, before the stream starts, to 10000, once it finishes.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn progress_bps(&self, stream_id: u64) -> Result<u16, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok(stream.progress_bps(get_current_time_in_seconds(&self)))
+        }
+
+        /// Returns the time left until a stream finishes. Useful for countdowns.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Returns:
+        /// - The remaining duration measured in seconds, zero if the stream is finished.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        #[ink(message)]
+        pub fn remaining_duration(&self, stream_id: u64) -> Result<u64, ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            Ok(stream.remaining_duration(get_current_time_in_seconds(&self)))
+        }
+
+        /// Returns the amount that would be streamed in a year at the stream rate. Useful to compare streams with different balances and durations.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        /// - The stream duration should be greater than zero.
+        ///
+        /// Returns:
+        /// - The stream annualized rate.
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - InvalidStreamDuration
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - A year is considered to have 365 days.
+        #[ink(message)]
+        pub fn annualized_rate(&self, stream_id: u64) -> Result<u128, ContractError> {
+            self.get_stream_by_id(stream_id)?.annualized_rate()
+        }
+
+        /// Runs the `create_stream` validations without creating the stream. Useful to validate a form before signing the transaction.
+        ///
+        /// Parameters:
+        /// - `recipient`, `end_date`, `duration`: Same as `create_stream`.
+        /// - `funds`: The amount of funds that would be attached to the transaction.
+        ///
+        /// Validations:
+        /// - Same as `create_stream`, with the sender as payer and a stream starting now.
+        ///
+        /// Returns:
+        /// - A tuple with the stream end date and its flow rate.
+        ///
+        /// Errors:
+        /// - Same as `create_stream`.
+        /// - InvalidStreamDuration
+        #[ink(message)]
+        pub fn preview_stream(
+            &self,
+            recipient: AccountId,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            funds: u128,
+        ) -> Result<(u64, u128), ContractError> {
+            self.ensure_not_paused()?;
+            let caller = self.env().caller();
+            self.ensure_stream_slots(caller, 1)?;
+
+            let new_stream =
+                self.build_new_stream(caller, recipient, None, end_date, duration, funds)?;
+            validate_tvl_cap(self.total_locked, funds, self.tvl_cap)?;
+
+            Ok((new_stream.end_date, new_stream.flow_rate()?))
+        }
+
+        /// Returns how the stream balance would be split if the stream was cancelled now.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        ///
+        /// Validations:
+        /// - The stream should exist.
+        ///
+        /// Returns:
+        /// - A tuple with the recipient payout (vested balance not withdrawn yet) and the payer refund (unvested balance).
+        ///
+        /// Errors:
+        /// - StreamDoesNotExist
+        /// - ArithmeticOverflow
+        #[ink(message)]
+        pub fn preview_cancellation(&self, stream_id: u64) -> Result<(u128, u128), ContractError> {
+            let stream = self.get_stream_by_id(stream_id)?;
+
+            stream.cancellation_split(get_current_time_in_seconds(&self))
+        }
+
+        /// Returns the aggregated cancellation split of a list of streams.
+        ///
+        /// Parameters:
+        /// - `stream_ids`: The stream IDs to preview.
+        ///
+        /// Validations:
+        /// - The list should not exceed the **maximum batch size**.
+        /// - `stream_ids` should not have duplicates.
+        /// - Every stream should exist.
+        ///
+        /// Returns:
+        /// - A tuple with the total recipient payout and the total payer refund.
+        ///
+        /// Errors:
+        /// - BatchTooLarge
+        /// - DuplicateStreamId
+        /// - StreamDoesNotExist
+        /// - ArithmeticOverflow
+        ///
+        /// NOTES
+        /// -----
+        /// - The current **maximum batch size** is 50 streams.
+        /// - The caller is not required to be the payer of the streams. This is a read-only preview, the cancellation itself enforces the ownership.
+        #[ink(message)]
+        pub fn preview_cancel_batch(
+            &self,
+            stream_ids: Vec<u64>,
+        ) -> Result<(u128, u128), ContractError> {
+            if stream_ids.len() > MAX_BATCH_SIZE {
+                return Err(ContractError::BatchTooLarge);
+            }
+
+            if stream_ids
+                .iter()
+                .enumerate()
+                .any(|(index, stream_id)| stream_ids[..index].contains(stream_id))
+            {
+                return Err(ContractError::DuplicateStreamId);
+            }
+
+            let mut total_recipient_payout: u128 = 0;
+            let mut total_payer_refund: u128 = 0;
+
+            for stream_id in stream_ids {
+                let (recipient_payout, payer_refund) = self.preview_cancellation(stream_id)?;
+                total_recipient_payout = total_recipient_payout
+                    .checked_add(recipient_payout)
+                    .ok_or(ContractError::ArithmeticOverflow)?;
+                total_payer_refund = total_payer_refund
+                    .checked_add(payer_refund)
+                    .ok_or(ContractError::ArithmeticOverflow)?;
+            }
+
+            Ok((total_recipient_payout, total_payer_refund))
+        }
+
+        /// Validates the stream creation parameters and builds a new stream.
+        ///
+        /// Behavior:
+        /// - If `start_date` is not specified, the stream starts now.
+        /// - If neither `end_date` nor `duration` are specified, the default duration is used when configured.
+        ///
+        /// Returns:
+        /// - The new stream, not stored yet.
+        ///
+        /// Errors:
+        /// - RecipientCannotBePayer
+        /// - EmptyFunds
+        /// - AmountTooSmall
+        /// - AmountTooLarge
+        /// - RecipientNotAllowed
+        /// - StreamStartDateShouldBeLater
+        /// - EndDateAndDurationAreEmpty
+        /// - EndDateDurationConflict
+        /// - StreamEndDateInPast
+        /// - StreamDurationShouldBeGreater
+        fn build_new_stream(
+            &self,
+            payer: AccountId,
+            recipient: AccountId,
+            start_date: Option<u64>,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            stream_funds: u128,
+        ) -> Result<Stream, ContractError> {
+            let current_time = get_current_time_in_seconds(&self);
+
+            validate_stream_creation_parameters(
+                payer,
+                recipient,
+                self.env().account_id(),
+                stream_funds,
+                self.min_stream_amount,
+                self.max_stream_amount,
+            )?;
+            self.ensure_recipient_allowed(recipient)?;
+            let start_date = validate_and_generate_stream_start_date(start_date, current_time)?;
+
+            let duration = if end_date.is_none() && duration.is_none() {
+                self.default_duration
+            } else {
+                duration
+            };
+            let end_date = validate_and_generate_stream_end_date(
+                end_date,
+                duration,
+                start_date,
+                self.min_duration,
+                self.max_duration,
+            )?;
+
+            let mut new_stream = Stream::new(payer, recipient, stream_funds, start_date, end_date);
+            new_stream.created_at = current_time;
+
+            Ok(new_stream)
+        }
+
+        /// Validates the stream creation parameters and builds a new stream with the optional settings of `options`.
+        ///
+        /// Behavior:
+        /// - The start date is `options.start_date`, or `options.start_offset` seconds from now, or now if neither is specified.
+        /// - Same as `build_new_stream`, then the cliff, vesting, immutability, memo, token decimals and cancellability are set from `options`.
+        /// - `options.notify` is applied by `store_new_stream_with_options`, once the stream has an ID.
+        ///
+        /// Returns:
+        /// - The new stream, not stored yet.
+        ///
+        /// Errors:
+        /// - StartDateOffsetConflict
+        /// - StreamCliffShouldBeEarlier
+        /// - InvalidVestingInterval
+        /// - ArithmeticOverflow
+        /// - Same as `build_new_stream`.
+        fn build_new_stream_with_options(
+            &self,
+            payer: AccountId,
+            recipient: AccountId,
+            end_date: Option<u64>,
+            duration: Option<u64>,
+            stream_funds: u128,
+            options: &StreamOptions,
+        ) -> Result<Stream, ContractError> {
+            let start_date = generate_stream_start_date_from_offset(
+                options.start_date,
+                options.start_offset,
+                get_current_time_in_seconds(&self),
+            )?;
+
+            let mut new_stream = self.build_new_stream(
+                payer,
+                recipient,
+                start_date,
+                end_date,
+                duration,
+                stream_funds,
+            )?;
+            new_stream.cliff_date = validate_and_generate_stream_cliff_date(
+                options.cliff,
+                new_stream.start_date,
+                new_stream.end_date,
+            )?;
+            new_stream.vesting = validate_and_generate_vesting_kind(
+                options.vesting,
+                new_stream.start_date,
+                new_stream.end_date,
+            )?;
+            new_stream.immutable = options.immutable;
+            new_stream.memo = options.memo;
+            new_stream.token_decimals = options.token_decimals.unwrap_or(NATIVE_TOKEN_DECIMALS);
+            new_stream.cancellable = options.cancellable;
+
+            Ok(new_stream)
+        }
+
+        /// Stores a new stream built by `build_new_stream_with_options`.
+        ///
+        /// Behavior:
+        /// - Same as `store_new_stream`.
+        /// - If `options.notify` is `true` and the recipient is a contract, its `on_stream_created` message will be called. Failures are ignored.
+        ///
+        /// Returns:
+        /// - The new stream ID.
+        ///
+        /// Errors:
+        /// - Same as `store_new_stream`.
+        fn store_new_stream_with_options(
+            &mut self,
+            stream: &Stream,
+            options: &StreamOptions,
+        ) -> Result<u64, ContractError> {
+            let stream_id = self.store_new_stream(stream)?;
+
+            if options.notify && is_contract_account(&self, &stream.recipient) {
+                notify_stream_created(stream.recipient, stream_id);
+            }
+
+            Ok(stream_id)
+        }
+
+        /// Inserts a new stream with an explicit ID, refusing to overwrite an existing one.
+        ///
+        /// Errors:
+        /// - StreamIdAlreadyExists
+        ///
+        /// NOTES
+        /// -----
+        /// - Every path storing a new stream should go through this function, so an ID collision can never overwrite a stream.
+        fn insert_new_stream(
+            &mut self,
+            stream_id: u64,
+            stream: &Stream,
+        ) -> Result<(), ContractError> {
+            if self.streams.get(stream_id).is_some() {
+                return Err(ContractError::StreamIdAlreadyExists);
+            }
+
+            self.streams.insert(stream_id, stream);
+            self.stream_count += 1;
+
+            Ok(())
+        }
+
+        /// Checks the storage invariants, panicking if any of them is broken. Meant for fuzzing and auditing builds.
+        ///
+        /// Invariants:
+        /// - The current balance of every stream should not exceed its original balance.
+        /// - The end date of every stream not cancelled should not be earlier than its start date.
+        /// - The sum of the current balances of all the streams should be equal to the total locked funds.
+        ///
+        /// NOTES
+        /// -----
+        /// - Cancelled streams are excluded from the dates check, since cancelling before the start date moves the end date before it.
+        /// - Zero duration streams are allowed when the minimum duration is zero, so the end date can be equal to the start date.
+        #[cfg(feature = "invariant-checks")]
+        fn assert_invariants(&self) {
+            let mut total_outstanding: u128 = 0;
+
+            for stream_id in 0..self.next_stream_id {
+                if let Some(stream) = self.streams.get(stream_id) {
+                    assert!(
+                        stream.current_balance <= stream.original_balance,
+                        "stream {} current balance exceeds its original balance",
+                        stream_id
+                    );
+                    assert!(
+                        stream.cancelled || stream.end_date >= stream.start_date,
+                        "stream {} ends before it starts",
+                        stream_id
+                    );
+                    total_outstanding = total_outstanding.saturating_add(stream.current_balance);
+                }
+            }
+
+            assert_eq!(
+                total_outstanding, self.total_locked,
+                "stream balances don't match the total locked funds"
+            );
+        }
+
+        /// Runs `assert_invariants` at the end of the messages moving funds. Does nothing unless the `invariant-checks` feature is enabled.
+        fn check_invariants(&self) {
+            #[cfg(feature = "invariant-checks")]
+            self.assert_invariants();
+        }
+
+        /// Check if an account can trigger withdrawals from a stream.
+        ///
+        /// Validations:
+        /// - `caller` should be the recipient of the stream or its authorized agent.
+        ///
+        /// Errors:
+        /// - Unauthorized
+        fn ensure_can_withdraw(
+            &self,
+            stream_id: u64,
+            stream: &Stream,
+            caller: AccountId,
+        ) -> Result<(), ContractError> {
+            if self.withdraw_agents.get(stream_id) == Some(caller) {
+                return Ok(());
+            }
+
+            stream.has_permission_to_withdraw(caller)
+        }
+
+        /// Stores a new stream with the next available ID.
+        ///
+        /// Behavior:
+        /// - IDs already present in the streams storage will be skipped.
+        /// - The next available ID will be increased by 1.
+        /// - The stream current balance will be added to the total locked funds and to the total streamed volume.
+        /// - The stream ID will be added to the recipient and payer streams indexes.
+        /// - The migration will be finalized, so no more streams can be imported.
+        /// - A `StreamCreated` event will be emitted.
+        ///
+        /// Returns:
+        /// - The new stream ID.
+        ///
+        /// Errors:
+        /// - StreamIdSpaceExhausted
+        /// - ArithmeticOverflow
+        fn store_new_stream(&mut self, stream: &Stream) -> Result<u64, ContractError> {
+            let mut new_stream_id = self.next_stream_id;
+            while self.streams.get(new_stream_id).is_some() {
+                new_stream_id = new_stream_id
+                    .checked_add(1)
+                    .ok_or(ContractError::StreamIdSpaceExhausted)?;
+            }
+
+            let next_stream_id = new_stream_id
+                .checked_add(1)
+                .ok_or(ContractError::StreamIdSpaceExhausted)?;
+            let total_locked = self
+                .total_locked
+                .checked_add(stream.current_balance)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+
+            self.insert_new_stream(new_stream_id, stream)?;
+            self.next_stream_id = next_stream_id;
+            self.migration_finalized = true;
+            self.total_locked = total_locked;
+            // Statistics only, they saturate instead of blocking new streams.
+            self.total_streamed_volume = self
+                .total_streamed_volume
+                .saturating_add(stream.current_balance);
+
+            self.add_to_recipient_index(stream.recipient, new_stream_id);
+            self.add_to_payer_index(stream.payer, new_stream_id);
+
+            self.env().emit_event(StreamCreated {
+                stream_id: new_stream_id,
+                payer: stream.payer,
+                recipient: stream.recipient,
+                amount: stream.current_balance,
+                start_date: stream.start_date,
+                end_date: stream.end_date,
+                completion_bonus: stream.completion_bonus,
+            });
+
+            self.check_invariants();
+
+            Ok(new_stream_id)
+        }
+
+        /// Withdraws from a stream, rejecting calls made while another withdrawal is in progress.
+        ///
+        /// Behavior:
+        /// - The reentrancy lock is held during the withdrawal and released afterwards, whether it succeeds or not.
+        /// - Same as `execute_withdrawal`.
+        ///
+        /// Errors:
+        /// - Reentrancy
+        /// - Same as `execute_withdrawal`.
+        fn withdraw_from_stream(
+            &mut self,
+            stream_id: u64,
+            stream: Stream,
+            amount: u128,
+            wrapper: Option<AccountId>,
+            split: Option<(AccountId, u16)>,
+        ) -> Result<u128, ContractError> {
+            if self.reentrancy_lock.get(()).unwrap_or(false) {
+                return Err(ContractError::Reentrancy);
+            }
+
+            self.reentrancy_lock.insert((), &true);
+            let result = self.execute_withdrawal(stream_id, stream, amount, wrapper, split);
+            self.reentrancy_lock.remove(());
+
+            result
+        }
+
+        /// Reduces the stream balance by the specified amount and transfers it to the stream recipient, or to its payout address if specified.
+        ///
+        /// Parameters:
+        /// - `wrapper`: If specified, the funds are wrapped through this wrapper contract instead of transferred.
+        /// - `split`: If specified, `(secondary, secondary_bps)` sends `secondary_bps` of the net amount to `secondary`.
+        ///
+        /// Validations:
+        /// - The native contract balance, minus the existential deposit, should cover `amount`, so the withdrawal fails before any state change.
+        ///
+        /// Behavior:
+        /// - The current time is stored as the stream last withdrawal date.
+        /// - The protocol fee, if configured, is transferred to the contract owner.
+        /// - If the funds go to the contract owner without a wrapper, the fee is included in that transfer instead of sent separately.
+        /// - The withdrawn amount, including the fee, is added to the total withdrawn volume.
+        /// - The withdrawal date and amount, including the fee, are appended to the stream withdrawal history.
+        /// - Transfers too small to create the destination account still fail with `TransferBelowExistential`.
+        /// - If a transfer fails, the stream and the total locked funds are restored and the error is returned, which reverts the whole call, including the transfers already made.
+        /// - Emits `StreamDrained` if the stream current balance reaches zero.
+        /// - Emits `SolvencyWarning` if the contract balance is left below the total locked funds. The withdrawal is not blocked.
+        ///
+        /// Returns:
+        /// - The net amount of tokens received by the recipient, including the secondary amount.
+        ///
+        /// Errors:
+        /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        /// - WrapFailed
+        fn execute_withdrawal(
+            &mut self,
+            stream_id: u64,
+            mut stream: Stream,
+            amount: u128,
+            wrapper: Option<AccountId>,
+            split: Option<(AccountId, u16)>,
+        ) -> Result<u128, ContractError> {
+            let current_time = get_current_time_in_seconds(&self);
+
+            // The contract account has to keep the existential deposit to stay alive.
+            let spendable_balance = self
+                .env()
+                .balance()
+                .saturating_sub(get_minimum_balance(&self));
+            if self.token.is_none() && spendable_balance < amount {
+                return Err(ContractError::InsufficientContractBalance);
+            }
+
+            let previous_stream = stream;
+            let previous_total_locked = self.total_locked;
+
+            stream.withdraw(amount)?;
+            stream.last_withdraw_at = Some(current_time);
+            self.streams.insert(stream_id, &stream);
+            self.total_locked = self.total_locked.saturating_sub(amount);
+
+            let fee = self.withdrawal_fee(stream.recipient, amount);
+            let net_amount = amount - fee;
+            let secondary_amount = match split {
+                // It can't overflow because `secondary_bps` is not greater than `BPS_DENOMINATOR`.
+                Some((_, secondary_bps)) => {
+                    mul_div(net_amount, secondary_bps as u64, BPS_DENOMINATOR).unwrap_or(0)
+                }
+                None => 0,
+            };
+            let mut primary_amount = net_amount - secondary_amount;
+            // Sending the fee and the withdrawal to the same account would take two transfers for nothing.
+            let fee_included = wrapper.is_none() && stream.payout_destination() == self.owner;
+            if fee_included {
+                primary_amount += fee;
+            }
+
+            let fee_result = if fee > 0 && !fee_included {
+                self.transfer_funds(self.owner, fee)
+            } else {
+                Ok(())
+            };
+
+            let transfer_result = fee_result
+                .and_then(|_| match split {
+                    Some((secondary, _)) if secondary_amount > 0 => {
+                        self.transfer_funds(secondary, secondary_amount)
+                    }
+                    _ => Ok(()),
+                })
+                .and_then(|_| match wrapper {
+                    Some(wrapper) => {
+                        wrapper_deposit_for(wrapper, stream.payout_destination(), primary_amount)
+                    }
+                    None => self.transfer_funds(stream.payout_destination(), primary_amount),
+                });
+
+            if let Err(error) = transfer_result {
+                self.streams.insert(stream_id, &previous_stream);
+                self.total_locked = previous_total_locked;
+                return Err(error);
+            }
+
+            self.record_payout(stream_id, stream.recipient, current_time, amount, fee);
+
+            if stream.current_balance == 0 {
+                self.env().emit_event(StreamDrained { stream_id });
+            }
+
+            let (held, outstanding) = self.solvency();
+            if held < outstanding {
+                self.env().emit_event(SolvencyWarning { held, outstanding });
+            }
+
+            self.notify_stream_subscriber(stream_id, StreamEventKind::Withdrawal);
+
+            self.check_invariants();
+
+            Ok(net_amount)
+        }
+
+        /// Runs the validations shared by the recipient withdrawal messages.
+        ///
+        /// Parameters:
+        /// - `stream_id`: The stream ID.
+        /// - `withdrawal_amount`: The amount requested by the sender, if any.
+        ///
+        /// Validations:
+        /// - The contract should not be paused.
+        /// - If `withdrawal_amount` has value, should be greater than zero.
+        /// - The stream should exist.
+        /// - The sender should be the recipient of the stream or its authorized agent.
+        /// - The stream should not be cancelled.
+        /// - The stream should not be finished and already emptied.
+        /// - The stream available balance should be greater than zero.
+        /// - The withdrawal cooldown of the stream should have elapsed since the last withdrawal.
+        ///
+        /// Returns:
+        /// - A tuple with the stream and its available balance.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        /// - WithdrawalAmountShouldBeGreaterThanZero
+        /// - StreamDoesNotExist
+        /// - Unauthorized
+        /// - StreamAlreadyCancelled
+        /// - StreamFullyWithdrawn
+        /// - StreamAvailableBalanceIsZero
+        /// - WithdrawOnCooldown
+        fn prepare_withdrawal(
+            &self,
+            stream_id: u64,
+            withdrawal_amount: Option<u128>,
+        ) -> Result<(Stream, u128), ContractError> {
+            self.ensure_not_paused()?;
+            validate_recipient_withdrawal_amount(withdrawal_amount)?;
+            let stream = self.get_stream_by_id(stream_id)?;
+            self.ensure_can_withdraw(stream_id, &stream, self.env().caller())?;
+            stream.ensure_active()?;
+
+            let current_time = get_current_time_in_seconds(&self);
+            if stream.is_finished(current_time) && stream.current_balance == 0 {
+                return Err(ContractError::StreamFullyWithdrawn);
+            }
+
+            let available_balance = self.withdrawable_balance(&stream, current_time)?;
+            stream.ensure_withdraw_cooldown_elapsed(current_time)?;
+
+            Ok((stream, available_balance))
+        }
+
+        /// Calculates the balance that can be withdrawn from a stream, failing if there is none.
+        ///
+        /// Parameters:
+        /// - `stream`: The stream to withdraw from.
+        /// - `current_time`: Current time in seconds.
+        ///
+        /// Errors:
+        /// - StreamAvailableBalanceIsZero
+        fn withdrawable_balance(
+            &self,
+            stream: &Stream,
+            current_time: u64,
+        ) -> Result<u128, ContractError> {
+            let available_balance = stream.available_balance_or_zero(current_time);
+
+            if available_balance == 0 {
+                return Err(ContractError::StreamAvailableBalanceIsZero);
+            }
+
+            Ok(available_balance)
+        }
+
+        /// Checks that a withdrawal amount is not lower than the existential deposit, if configured.
+        ///
+        /// Parameters:
+        /// - `stream`: The stream to withdraw from.
+        /// - `amount`: The withdrawal amount.
+        ///
+        /// Validations:
+        /// - `amount` should not be lower than the existential deposit, unless it is the whole stream balance.
+        ///
+        /// Errors:
+        /// - AmountBelowExistential
+        fn ensure_above_existential_deposit(
+            &self,
+            stream: &Stream,
+            amount: u128,
+        ) -> Result<(), ContractError> {
+            if amount < self.existential_deposit && amount < stream.current_balance {
+                return Err(ContractError::AmountBelowExistential);
+            }
+
+            Ok(())
+        }
+
+        /// Checks that the contract is not paused by the owner.
+        ///
+        /// Errors:
+        /// - ContractPaused
+        fn ensure_not_paused(&self) -> Result<(), ContractError> {
+            if self.paused {
+                return Err(ContractError::ContractPaused);
+            }
+
+            Ok(())
+        }
+
+        /// Checks that an account can receive streams.
+        ///
+        /// Validations:
+        /// - If the recipient allowlist is enabled, the account should be allowed by the owner.
+        ///
+        /// Errors:
+        /// - RecipientNotAllowed
+        fn ensure_recipient_allowed(&self, recipient: AccountId) -> Result<(), ContractError> {
+            if self.allowlist_enabled && !self.is_recipient_allowed(recipient) {
+                return Err(ContractError::RecipientNotAllowed);
+            }
+
+            Ok(())
+        }
+
+        /// Checks that a payer can have `count` more active streams.
+        ///
+        /// Errors:
+        /// - StreamLimitReached
+        fn ensure_stream_slots(&self, payer: AccountId, count: usize) -> Result<(), ContractError> {
+            let active_streams = self.get_streams_for_payer(payer).len();
+
+            if active_streams.saturating_add(count) > self.max_streams_per_payer as usize {
+                return Err(ContractError::StreamLimitReached);
+            }
+
+            Ok(())
+        }
+
+        /// Returns the balance held by the contract that neither backs a stream nor is escrowed for a claimable stream.
+        fn unallocated_balance(&self) -> u128 {
+            self.held_balance()
+                .saturating_sub(self.outstanding_balance())
+        }
+
+        /// Returns the funds owed by the contract, locked in streams or escrowed for claimable streams.
+        fn outstanding_balance(&self) -> u128 {
+            self.total_locked.saturating_add(self.total_escrowed)
+        }
+
+        /// Returns the balance held by the contract, in the streamed token.
+        fn held_balance(&self) -> u128 {
+            match self.token {
+                Some(token) => psp22_balance_of(token, self.env().account_id()),
+                None => self.env().balance(),
+            }
+        }
+
+        /// Returns the native funds transferred in the transaction.
+        ///
+        /// Validations:
+        /// - No native funds should be transferred to a contract streaming a PSP22 token.
+        ///
+        /// Errors:
+        /// - UnsupportedAsset
+        fn transferred_funds(&self) -> Result<u128, ContractError> {
+            let funds = self.env().transferred_value();
+
+            if self.token.is_some() && funds > 0 {
+                return Err(ContractError::UnsupportedAsset);
+            }
+
+            Ok(funds)
+        }
+
+        /// Transfers funds from the contract in the streamed asset, the PSP22 token if configured or the native token otherwise.
+        ///
+        /// Behavior:
+        /// - Native transfer failures are mapped to the most specific error available.
+        ///   * InsufficientContractBalance if the contract balance is lower than `amount`.
+        ///   * TransferBelowExistential if the transfer would leave the contract below the existential deposit,
+        ///     or the amount is too small to create the destination account.
+        ///   * WithdrawTransferFailed for any other failure.
+        ///
+        /// Errors:
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        fn transfer_funds(&self, to: AccountId, amount: u128) -> Result<(), ContractError> {
+            match self.token {
+                Some(token) => psp22_transfer(token, to, amount)
+                    .map_err(|_| ContractError::WithdrawTransferFailed),
+                None => self
+                    .env()
+                    .transfer(to, amount)
+                    .map_err(|error| match error {
+                        ink_env::Error::TransferFailed if self.env().balance() < amount => {
+                            ContractError::InsufficientContractBalance
+                        }
+                        ink_env::Error::TransferFailed
+                            if self.env().balance() - amount < self.env().minimum_balance()
+                                || amount < self.env().minimum_balance() =>
+                        {
+                            ContractError::TransferBelowExistential
+                        }
+                        ink_env::Error::_BelowSubsistenceThreshold => {
+                            ContractError::TransferBelowExistential
+                        }
+                        _ => ContractError::WithdrawTransferFailed,
+                    }),
+            }
+        }
+
+        /// Calculates the protocol fee charged on a withdrawal.
+        ///
+        /// Returns:
+        /// - Zero if the recipient is exempt from fees, `amount * fee_bps / BPS_DENOMINATOR` otherwise, rounding down.
+        fn withdrawal_fee(&self, recipient: AccountId, amount: u128) -> u128 {
+            if self.is_fee_exempt(recipient) {
+                return 0;
+            }
+
+            // It can't overflow because the fee is capped below `BPS_DENOMINATOR`.
+            mul_div(amount, self.fee_bps as u64, BPS_DENOMINATOR).unwrap_or(0)
+        }
+
+        /// Adds funds to the contribution of a co-payer of a stream.
+        ///
+        /// Errors:
+        /// - TooManyContributors
+        /// - ArithmeticOverflow
+        fn record_contribution(
+            &mut self,
+            stream_id: u64,
+            contributor: AccountId,
+            amount: u128,
+        ) -> Result<(), ContractError> {
+            let mut contributors = self.get_contributors(stream_id);
+            if !contributors.contains(&contributor) {
+                if contributors.len() >= MAX_CONTRIBUTORS {
+                    return Err(ContractError::TooManyContributors);
+                }
+                contributors.push(contributor);
+                self.contributors.insert(stream_id, &contributors);
+            }
+
+            let contribution = self
+                .get_contribution(stream_id, contributor)
+                .checked_add(amount)
+                .ok_or(ContractError::ArithmeticOverflow)?;
+            self.contributions
+                .insert((stream_id, contributor), &contribution);
+
+            Ok(())
+        }
+
+        /// Splits a refund of a stream between its co-payers and its payer, without transferring anything.
+        ///
+        /// Parameters:
+        /// - `funded_balance`: Funds added to the stream by the payer and the co-payers.
+        /// - `refund`: The balance to be split.
+        ///
+        /// Behavior:
+        /// - Each co-payer is owed `refund * contribution / funded_balance`, rounding down.
+        ///
+        /// Returns:
+        /// - A tuple with the `(contributor, share)` list and the rest of the refund, owed to the payer.
+        ///
+        /// Errors:
+        /// - ArithmeticOverflow
+        fn contributor_shares(
+            &self,
+            stream_id: u64,
+            funded_balance: u128,
+            refund: u128,
+        ) -> Result<(Vec<(AccountId, u128)>, u128), ContractError> {
+            let mut shares = Vec::new();
+            let mut payer_share = refund;
+
+            if refund == 0 {
+                return Ok((shares, payer_share));
+            }
+
+            for contributor in self.get_contributors(stream_id) {
+                let share = proportional_share(
+                    refund,
+                    self.get_contribution(stream_id, contributor),
+                    funded_balance,
+                )
+                .ok_or(ContractError::ArithmeticOverflow)?;
+                payer_share = payer_share
+                    .checked_sub(share)
+                    .ok_or(ContractError::ArithmeticOverflow)?;
+                shares.push((contributor, share));
+            }
+
+            Ok((shares, payer_share))
+        }
+
+        /// Refunds the co-payers of a stream their share of a refund, as computed by `contributor_shares`.
+        ///
+        /// Parameters:
+        /// - `funded_balance`: Funds added to the stream by the payer and the co-payers, before the refund.
+        /// - `refund`: The balance to be split.
+        ///
+        /// Behavior:
+        /// - Each co-payer share is transferred to the co-payer and subtracted from its contribution, so later refunds stay proportional.
+        /// - Emits `ContributionRefunded` for each co-payer refunded.
+        ///
+        /// Returns:
+        /// - The rest of the refund, owed to the payer.
+        ///
+        /// Errors:
+        /// - ArithmeticOverflow
+        /// - InsufficientContractBalance
+        /// - TransferBelowExistential
+        /// - WithdrawTransferFailed
+        fn refund_contributors(
+            &mut self,
+            stream_id: u64,
+            funded_balance: u128,
+            refund: u128,
+        ) -> Result<u128, ContractError> {
+            let (shares, payer_share) =
+                self.contributor_shares(stream_id, funded_balance, refund)?;
+
+            for (contributor, share) in shares {
+                if share == 0 {
+                    continue;
+                }
+
+                self.transfer_funds(contributor, share)?;
+                let contribution = self.get_contribution(stream_id, contributor);
+                self.contributions.insert(
+                    (stream_id, contributor),
+                    &contribution.saturating_sub(share),
+                );
+
+                self.env().emit_event(ContributionRefunded {
+                    stream_id,
+                    contributor,
+                    amount: share,
+                });
+            }
+
+            Ok(payer_share)
+        }
+
+        /// Removes the contributions of every co-payer of a stream.
+        fn remove_contributions(&mut self, stream_id: u64) {
+            for contributor in self.get_contributors(stream_id) {
+                self.contributions.remove((stream_id, contributor));
+            }
+            self.contributors.remove(stream_id);
+        }
+
+        /// Records a withdrawal whose funds were already transferred.
+        ///
+        /// Behavior:
+        /// - `amount`, including the fee, is added to the total withdrawn volume and to the stream withdrawal history.
+        /// - Emits `Withdrawn` with the net amount and the fee.
+        fn record_payout(
+            &mut self,
+            stream_id: u64,
+            recipient: AccountId,
+            date: u64,
+            amount: u128,
+            fee: u128,
+        ) {
+            // Statistics only, they saturate instead of blocking withdrawals.
+            self.total_withdrawn_volume = self.total_withdrawn_volume.saturating_add(amount);
+            self.record_withdrawal(stream_id, date, amount);
+
+            self.env().emit_event(Withdrawn {
+                stream_id,
+                recipient,
+                amount: amount - fee,
+                fee,
+            });
+        }
+
+        /// Appends a withdrawal to the history of a stream, dropping the oldest entries beyond `MAX_WITHDRAWAL_HISTORY`.
+        fn record_withdrawal(&mut self, stream_id: u64, date: u64, amount: u128) {
+            let mut history = self.withdrawal_history(stream_id);
+            history.push((date, amount));
+            if history.len() > MAX_WITHDRAWAL_HISTORY {
+                history.drain(..history.len() - MAX_WITHDRAWAL_HISTORY);
+            }
+            self.withdrawal_history.insert(stream_id, &history);
+        }
+
+        /// Adds a stream ID to the streams index of a recipient.
+        fn add_to_recipient_index(&mut self, recipient: AccountId, stream_id: u64) {
+            let mut recipient_streams = self.get_streams_for_recipient(recipient);
+            recipient_streams.push(stream_id);
+            self.streams_by_recipient
+                .insert(recipient, &recipient_streams);
+        }
+
+        /// Removes a stream ID from the streams index of a recipient.
+        fn remove_from_recipient_index(&mut self, recipient: AccountId, stream_id: u64) {
+            let mut recipient_streams = self.get_streams_for_recipient(recipient);
+            recipient_streams.retain(|id| *id != stream_id);
+            self.streams_by_recipient
+                .insert(recipient, &recipient_streams);
+        }
+
+        /// Adds a stream ID to the streams index of a payer.
+        fn add_to_payer_index(&mut self, payer: AccountId, stream_id: u64) {
+            let mut payer_streams = self.get_streams_for_payer(payer);
+            payer_streams.push(stream_id);
+            self.streams_by_payer.insert(payer, &payer_streams);
+        }
+
+        /// Removes a stream ID from the streams index of a payer.
+        fn remove_from_payer_index(&mut self, payer: AccountId, stream_id: u64) {
+            let mut payer_streams = self.get_streams_for_payer(payer);
+            payer_streams.retain(|id| *id != stream_id);
+            self.streams_by_payer.insert(payer, &payer_streams);
+        }
+
+        /// Notifies the subscriber of a stream, if any, about a lifecycle change.
+        fn notify_stream_subscriber(&self, stream_id: u64, event_kind: StreamEventKind) {
+            if let Some(subscriber) = self.subscribers.get(stream_id) {
+                notify_subscriber(subscriber, stream_id, event_kind);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::stream::{VestingKind, SECONDS_PER_YEAR};
+        use crate::utils::{set_mock_minimum_balance, set_mock_now};
+        use ink_lang as ink;
+
+        fn get_contract_id() -> AccountId {
+            ink_env::test::callee::<ink_env::DefaultEnvironment>()
+        }
+
+        fn get_default_accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+        }
+
+        fn set_sender(sender: AccountId) {
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(sender);
+        }
+
+        fn set_balance(account_id: AccountId, balance: u128) {
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(account_id, balance)
+        }
+
+        fn advance_block() {
+            let _ = ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+        }
+
+        fn commit_claim_and_wait(
+            contract: &mut StreamsContract,
+            preimage: &[u8],
+            claimer: AccountId,
+        ) {
+            set_sender(claimer);
+            contract
+                .commit_claim(hash_claim_commitment(preimage, &claimer))
+                .unwrap();
+            advance_block();
+        }
+
+        fn set_now(seconds: u64) {
+            set_mock_now(Some(seconds));
+        }
+
+        fn set_value_transferred(amount: u128) {
+            ink_env::test::set_value_transferred::<ink_env::DefaultEnvironment>(amount);
+        }
+
+        type Event = <StreamsContract as ::ink_lang::reflect::ContractEventBase>::Type;
+
+        fn get_recorded_events() -> Vec<Event> {
+            ink_env::test::recorded_events()
+                .map(|event| <Event as scale::Decode>::decode(&mut &event.data[..]).unwrap())
+                .collect()
+        }
+
+        fn get_distinct_recipients(count: usize) -> Vec<AccountId> {
+            (0..count)
+                .map(|index| AccountId::from([100 + index as u8; 32]))
+                .collect()
+        }
+
+        /// Rebuilds the recipient and payer indexes of `accounts` by scanning the streams storage, and asserts they match the maintained ones.
+        fn assert_indexes_consistent(contract: &StreamsContract, accounts: &[AccountId]) {
+            for account in accounts {
+                let mut expected_recipient_streams = Vec::new();
+                let mut expected_payer_streams = Vec::new();
+
+                for stream_id in 1..contract.next_stream_id {
+                    match contract.streams.get(stream_id) {
+                        Some(stream) if !stream.cancelled => {
+                            if stream.recipient == *account {
+                                expected_recipient_streams.push(stream_id);
+                            }
+                            if stream.payer == *account {
+                                expected_payer_streams.push(stream_id);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                let mut recipient_streams = contract.get_streams_for_recipient(*account);
+                recipient_streams.sort_unstable();
+                let mut payer_streams = contract.get_streams_for_payer(*account);
+                payer_streams.sort_unstable();
+                assert_eq!(
+                    recipient_streams, expected_recipient_streams,
+                    "recipient {:?}",
+                    account
+                );
+                assert_eq!(payer_streams, expected_payer_streams, "payer {:?}", account);
+            }
+        }
+
+        fn get_balance(account_id: AccountId) -> u128 {
+            ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(account_id).unwrap()
+        }
+
+        fn init() -> (
+            StreamsContract,
+            ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment>,
+        ) {
+            (StreamsContract::new(), get_default_accounts())
+        }
+
+        #[ink::test]
+        fn create_stream_with_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            let duration = 10000;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let current_time = get_current_time_in_seconds(&contract);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(duration), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_id, contract.next_stream_id - 1);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.payer, sender);
+            assert_eq!(stream.recipient, recipient);
+            assert_eq!(stream.original_balance, funds);
+            assert_eq!(stream.current_balance, funds);
+            assert_eq!(stream.start_date, current_time);
+            assert_eq!(
                 stream.end_date,
                 get_current_time_in_seconds(&contract) + duration
             );
         }
 
         #[ink::test]
-        fn create_stream_with_end_date_works() {
+        fn create_stream_emits_stream_created_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let payer = accounts.alice;
+            let recipient = accounts.bob;
+            set_sender(payer);
+            set_value_transferred(funds);
+
+            // Act
+            let current_time = get_current_time_in_seconds(&contract);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::StreamCreated(StreamCreated {
+                    stream_id: event_stream_id,
+                    payer: event_payer,
+                    recipient: event_recipient,
+                    amount,
+                    start_date,
+                    end_date,
+                    completion_bonus: 0,
+                }) if event_stream_id == stream_id
+                    && event_payer == payer
+                    && event_recipient == recipient
+                    && amount == funds
+                    && start_date == current_time
+                    && end_date == current_time + 300
+            ));
+        }
+
+        #[ink::test]
+        fn protocol_volumes_track_creations_and_withdrawals() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.charlie);
+            for funds in [1000, 2000] {
+                set_value_transferred(funds);
+                contract
+                    .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                    .unwrap();
+            }
+            set_sender(accounts.bob);
+
+            // Half of the streams elapsed
+            set_now(150);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+            contract.recipient_withdraw(2, Some(400)).unwrap();
+
+            // Assert
+            assert_eq!(contract.total_streamed_volume(), 3000);
+            assert_eq!(contract.total_withdrawn_volume(), 900);
+        }
+
+        #[ink::test]
+        fn minimum_duration_defaults_to_constant() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.minimum_duration();
+
+            // Assert
+            assert_eq!(result, STREAM_MINIMUM_DURATION);
+        }
+
+        #[ink::test]
+        fn minimum_duration_of_custom_contract_works() {
+            // Arrange
+            let contract = StreamsContract::new_with_min_duration(60);
+
+            // Act
+            let result = contract.minimum_duration();
+
+            // Assert
+            assert_eq!(result, 60);
+        }
+
+        #[ink::test]
+        fn config_works() {
+            // Arrange
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            let mut contract = StreamsContract::new_with_min_duration(60);
+            contract.set_fee_bps(250).unwrap();
+
+            // Act
+            let result = contract.config();
+
+            // Assert
+            assert_eq!(
+                result,
+                ContractConfig {
+                    version: CONTRACT_VERSION,
+                    min_duration: 60,
+                    fee_bps: 250,
+                    paused: false,
+                    owner: accounts.alice,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn create_stream_with_custom_min_duration_works() {
+            // Arrange
+            let mut contract = StreamsContract::new_with_min_duration(60);
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(120), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+            let (mut default_contract, _) = init();
+            assert_eq!(
+                default_contract.create_stream(
+                    accounts.bob,
+                    None,
+                    Some(120),
+                    StreamOptions::default(),
+                ),
+                Err(ContractError::StreamDurationShouldBeGreater)
+            );
+        }
+
+        #[ink::test]
+        fn create_streams_batch_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.alice;
+            set_sender(payer);
+            set_value_transferred(600);
+
+            // Act
+            let stream_ids = contract
+                .create_streams_batch(
+                    vec![
+                        (accounts.bob, None, Some(300)),
+                        (accounts.charlie, None, Some(600)),
+                        (accounts.django, None, Some(900)),
+                    ],
+                    vec![100, 200, 300],
+                )
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_ids, vec![1, 2, 3]);
+            let stream = contract.get_stream_by_id(2).unwrap();
+            assert_eq!(stream.payer, payer);
+            assert_eq!(stream.recipient, accounts.charlie);
+            assert_eq!(stream.original_balance, 200);
+            assert_eq!(stream.total_duration(), 600);
+            assert_eq!(contract.total_locked, 600);
+        }
+
+        #[ink::test]
+        fn create_split_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.alice;
+            set_sender(payer);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_ids = contract
+                .create_split_stream(
+                    vec![accounts.bob, accounts.charlie, accounts.django],
+                    vec![1, 1, 2],
+                    300,
+                )
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_ids, vec![1, 2, 3]);
+            let streams: Vec<Stream> = stream_ids
+                .iter()
+                .map(|stream_id| contract.get_stream_by_id(*stream_id).unwrap())
+                .collect();
+            assert_eq!(streams[0].original_balance, 250);
+            assert_eq!(streams[1].original_balance, 250);
+            assert_eq!(streams[2].original_balance, 500);
+            assert!(streams
+                .iter()
+                .all(|stream| stream.start_date == streams[0].start_date
+                    && stream.end_date == streams[0].end_date));
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn create_split_stream_assigns_remainder_to_first_recipient() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_ids = contract
+                .create_split_stream(
+                    vec![accounts.bob, accounts.charlie, accounts.django],
+                    vec![1, 1, 1],
+                    300,
+                )
+                .unwrap();
+
+            // Assert
+            let balances: Vec<u128> = stream_ids
+                .iter()
+                .map(|stream_id| {
+                    contract
+                        .get_stream_by_id(*stream_id)
+                        .unwrap()
+                        .original_balance
+                })
+                .collect();
+            assert_eq!(balances, vec![334, 333, 333]);
+            assert_eq!(balances.iter().sum::<u128>(), 1000);
+        }
+
+        #[ink::test]
+        fn create_split_stream_with_mismatched_weights_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_split_stream(vec![accounts.bob, accounts.charlie], vec![1], 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchLengthMismatch));
+        }
+
+        #[ink::test]
+        fn create_split_stream_with_zero_weights_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_split_stream(vec![accounts.bob, accounts.charlie], vec![0, 0], 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ValidationError));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_split_stream_with_empty_share_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_split_stream(vec![accounts.bob, accounts.charlie], vec![1, 0], 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::EmptyFunds));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_equal_split_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(900);
+
+            // Act
+            let stream_ids = contract
+                .create_equal_split(vec![accounts.bob, accounts.charlie, accounts.django], 300)
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_ids, vec![1, 2, 3]);
+            let balances: Vec<u128> = stream_ids
+                .iter()
+                .map(|stream_id| {
+                    contract
+                        .get_stream_by_id(*stream_id)
+                        .unwrap()
+                        .original_balance
+                })
+                .collect();
+            assert_eq!(balances, vec![300, 300, 300]);
+            assert_eq!(contract.total_locked, 900);
+        }
+
+        #[ink::test]
+        fn create_equal_split_assigns_remainder_to_last_recipient() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_ids = contract
+                .create_equal_split(vec![accounts.bob, accounts.charlie, accounts.django], 300)
+                .unwrap();
+
+            // Assert
+            let balances: Vec<u128> = stream_ids
+                .iter()
+                .map(|stream_id| {
+                    contract
+                        .get_stream_by_id(*stream_id)
+                        .unwrap()
+                        .original_balance
+                })
+                .collect();
+            assert_eq!(balances, vec![333, 333, 334]);
+            assert_eq!(balances.iter().sum::<u128>(), 1000);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn create_equal_split_with_duplicate_recipients_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract
+                .create_equal_split(vec![accounts.bob, accounts.charlie, accounts.bob], 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::DuplicateRecipient));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_equal_split_to_payer_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_equal_split(vec![accounts.charlie, accounts.bob], 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientCannotBePayer));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_streams_batch_at_max_batch_size_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * MAX_BATCH_SIZE as u128);
+            let recipients = get_distinct_recipients(MAX_BATCH_SIZE)
+                .into_iter()
+                .map(|recipient| (recipient, None, Some(300)))
+                .collect();
+
+            // Act
+            let result = contract.create_streams_batch(recipients, vec![1000; MAX_BATCH_SIZE]);
+
+            // Assert
+            assert_eq!(result.unwrap().len(), MAX_BATCH_SIZE);
+            assert_eq!(contract.total_streams(), MAX_BATCH_SIZE as u64);
+        }
+
+        #[ink::test]
+        fn create_streams_batch_over_max_batch_size_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * (MAX_BATCH_SIZE as u128 + 1));
+            let recipients = get_distinct_recipients(MAX_BATCH_SIZE + 1)
+                .into_iter()
+                .map(|recipient| (recipient, None, Some(300)))
+                .collect();
+
+            // Act
+            let result = contract.create_streams_batch(recipients, vec![1000; MAX_BATCH_SIZE + 1]);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchTooLarge));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_split_stream_at_max_batch_size_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * MAX_BATCH_SIZE as u128);
+
+            // Act
+            let result = contract.create_split_stream(
+                get_distinct_recipients(MAX_BATCH_SIZE),
+                vec![1; MAX_BATCH_SIZE],
+                300,
+            );
+
+            // Assert
+            assert_eq!(result.unwrap().len(), MAX_BATCH_SIZE);
+            assert_eq!(contract.total_streams(), MAX_BATCH_SIZE as u64);
+        }
+
+        #[ink::test]
+        fn create_split_stream_over_max_batch_size_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * (MAX_BATCH_SIZE as u128 + 1));
+
+            // Act
+            let result = contract.create_split_stream(
+                get_distinct_recipients(MAX_BATCH_SIZE + 1),
+                vec![1; MAX_BATCH_SIZE + 1],
+                300,
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchTooLarge));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_equal_split_at_max_batch_size_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * MAX_BATCH_SIZE as u128);
+
+            // Act
+            let result = contract.create_equal_split(get_distinct_recipients(MAX_BATCH_SIZE), 300);
+
+            // Assert
+            assert_eq!(result.unwrap().len(), MAX_BATCH_SIZE);
+            assert_eq!(contract.total_streams(), MAX_BATCH_SIZE as u64);
+        }
+
+        #[ink::test]
+        fn create_equal_split_over_max_batch_size_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000 * (MAX_BATCH_SIZE as u128 + 1));
+
+            // Act
+            let result =
+                contract.create_equal_split(get_distinct_recipients(MAX_BATCH_SIZE + 1), 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchTooLarge));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_streams_batch_with_mismatched_amounts_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(500);
+
+            // Act
+            let result = contract.create_streams_batch(
+                vec![
+                    (accounts.bob, None, Some(300)),
+                    (accounts.charlie, None, Some(300)),
+                ],
+                vec![100, 200],
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchAmountMismatch));
+            assert_eq!(contract.next_stream_id, 1);
+        }
+
+        #[ink::test]
+        fn create_streams_batch_with_invalid_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(300);
+
+            // Act
+            let result = contract.create_streams_batch(
+                vec![
+                    (accounts.bob, None, Some(300)),
+                    (accounts.charlie, None, Some(10)),
+                ],
+                vec![100, 200],
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDurationShouldBeGreater));
+            assert_eq!(
+                contract.get_stream_by_id(1),
+                Err(ContractError::StreamDoesNotExist)
+            );
+        }
+
+        fn payroll_template() -> Vec<StreamTemplate> {
+            let accounts = get_default_accounts();
+            vec![
+                StreamTemplate {
+                    recipient: accounts.bob,
+                    amount: 600,
+                    duration: 300,
+                },
+                StreamTemplate {
+                    recipient: accounts.charlie,
+                    amount: 400,
+                    duration: 600,
+                },
+            ]
+        }
+
+        #[ink::test]
+        fn create_from_template_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.alice;
+            set_sender(payer);
+            contract.save_template(1, payroll_template()).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let stream_ids = contract.create_from_template(1).unwrap();
+
+            // Assert
+            assert_eq!(contract.get_template(1), Some(payroll_template()));
+            assert_eq!(stream_ids, vec![1, 2]);
+            let stream = contract.get_stream_by_id(2).unwrap();
+            assert_eq!(stream.payer, payer);
+            assert_eq!(stream.recipient, accounts.charlie);
+            assert_eq!(stream.original_balance, 400);
+            assert_eq!(stream.total_duration(), 600);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn create_from_template_with_mismatched_amount_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.save_template(1, payroll_template()).unwrap();
+            set_value_transferred(999);
+
+            // Act
+            let result = contract.create_from_template(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchAmountMismatch));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_from_non_existent_template_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_from_template(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::TemplateDoesNotExist));
+        }
+
+        #[ink::test]
+        fn save_template_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.save_template(1, payroll_template());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_template(1), None);
+        }
+
+        #[ink::test]
+        fn create_stream_with_future_start_date_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            let current_time = get_current_time_in_seconds(&contract);
+            let start_date = current_time + 3600;
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        start_date: Some(start_date),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.created_at, current_time);
+            assert_eq!(stream.start_date, start_date);
+            assert_eq!(stream.end_date, start_date + 300);
+
+            // Half of the stream duration elapsed, but the stream didn't start yet
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            assert_eq!(contract.available_balance(stream_id), Ok(0));
+            set_sender(recipient);
+            assert_eq!(
+                contract.recipient_withdraw(stream_id, None),
+                Err(ContractError::StreamAvailableBalanceIsZero)
+            );
+        }
+
+        #[ink::test]
+        fn create_stream_with_past_start_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            for _ in 0..1000 {
+                advance_block();
+            }
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let current_time = get_current_time_in_seconds(&contract);
+
+            // Act
+            let result = contract.create_stream(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions {
+                    start_date: Some(current_time - 1),
+                    ..StreamOptions::default()
+                },
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamStartDateShouldBeLater));
+        }
+
+        #[ink::test]
+        fn stepped_stream_unlocks_at_interval_boundaries() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            // Four intervals of 75 seconds
+            let stream_id = contract
+                .create_stream(
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        vesting: Some(VestingKind::Stepped { interval: 75 }),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Act
+            let mut balances = Vec::new();
+            for blocks in [12499, 1, 12499, 1, 25000] {
+                for _ in 0..blocks {
+                    advance_block();
+                }
+                balances.push(contract.available_balance(stream_id).unwrap());
+            }
+
+            // Assert
+            assert_eq!(balances, vec![0, 250, 250, 500, 1000]);
+        }
+
+        #[ink::test]
+        fn create_stream_with_invalid_vesting_interval_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            for interval in [0, 70] {
+                // Act
+                let result = contract.create_stream(
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        vesting: Some(VestingKind::Stepped { interval }),
+                        ..StreamOptions::default()
+                    },
+                );
+
+                // Assert
+                assert_eq!(result, Err(ContractError::InvalidVestingInterval));
+            }
+        }
+
+        #[ink::test]
+        fn create_stream_to_contract_account_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream(
+                get_contract_id(),
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidRecipient));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_to_zero_account_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream(
+                AccountId::from([0u8; 32]),
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidRecipient));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn reclaim_expired_before_grace_period_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            contract.grace_period = 300;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.bob, 1000, 0, 300));
+            set_sender(payer);
+
+            // Grace period ends exactly now
+            for _ in 0..100000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.reclaim_expired(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamNotReclaimable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn reclaim_expired_after_grace_period_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let funds = 1000;
+            contract.grace_period = 300;
+            set_sender(payer);
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let payer_balance = get_balance(payer);
+
+            // Grace period ended
+            for _ in 0..100167 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.reclaim_expired(stream_id);
+
+            // Assert
+            assert_eq!(result, Ok(funds));
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.current_balance, 0);
+            assert_eq!(stream.original_balance, 0);
+            assert_eq!(contract.total_locked, 0);
+            assert_eq!(get_balance(payer), payer_balance + funds);
+        }
+
+        #[ink::test]
+        fn reclaim_expired_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.grace_period = 0;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            for _ in 0..50167 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.reclaim_expired(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn create_stream_with_overflowing_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions {
+                    start_date: Some(u64::MAX - 100),
+                    ..StreamOptions::default()
+                },
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+        }
+
+        #[cfg(feature = "invariant-checks")]
+        #[ink::test]
+        #[should_panic(expected = "stream balances don't match the total locked funds")]
+        fn invariant_checks_with_corrupted_total_locked_panics() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract.total_locked += 1;
+
+            // Act
+            let _ = contract.top_up_stream(stream_id);
+        }
+
+        #[ink::test]
+        fn create_stream_with_max_stream_id_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.next_stream_id = u64::MAX;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamIdSpaceExhausted));
+            assert_eq!(contract.streams.get(u64::MAX), None);
+            assert_eq!(contract.next_stream_id, u64::MAX);
+            assert_eq!(contract.total_locked, 0);
+        }
+
+        #[ink::test]
+        fn create_stream_from_approved_payer_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let relayer = accounts.django;
+            set_sender(payer);
+            contract.approve(relayer, 1500);
+
+            // Act
+            set_sender(relayer);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream_from(
+                    payer,
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions::default(),
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.payer, payer);
+            assert_eq!(stream.current_balance, 1000);
+            assert_eq!(contract.allowance(payer, relayer), 500);
+            assert_eq!(contract.get_streams_for_payer(payer), vec![stream_id]);
+        }
+
+        #[ink::test]
+        fn create_stream_from_with_options_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let relayer = accounts.django;
+            let memo = [1; 32];
+            set_sender(payer);
+            contract.approve(relayer, 1000);
+            set_now(100);
+
+            // Act
+            set_sender(relayer);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream_from(
+                    payer,
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        start_offset: Some(50),
+                        cliff: Some(100),
+                        memo: Some(memo),
+                        cancellable: false,
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.start_date, 150);
+            assert_eq!(stream.end_date, 450);
+            assert_eq!(stream.cliff_date, 250);
+            assert_eq!(stream.memo, Some(memo));
+            assert!(!stream.cancellable);
+        }
+
+        #[ink::test]
+        fn create_stream_from_exceeding_allowance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let relayer = accounts.django;
+            set_sender(payer);
+            contract.approve(relayer, 500);
+
+            // Act
+            set_sender(relayer);
+            set_value_transferred(1000);
+            let result = contract.create_stream_from(
+                payer,
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientAllowance));
+            assert_eq!(contract.allowance(payer, relayer), 500);
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_from_without_approval_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.django);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream_from(
+                accounts.charlie,
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientAllowance));
+        }
+
+        #[ink::test]
+        fn create_stream_with_memo_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let mut memo = [0u8; 32];
+            memo[..12].copy_from_slice(b"March salary");
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        memo: Some(memo),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.memo, Some(memo));
+        }
+
+        #[ink::test]
+        fn create_stream_with_token_decimals_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    accounts.bob,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        token_decimals: Some(18),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let details = contract.get_stream_details(stream_id).unwrap();
+            assert_eq!(details.stream.token_decimals, 18);
+        }
+
+        #[ink::test]
+        fn create_stream_without_token_decimals_defaults_to_native() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.token_decimals, NATIVE_TOKEN_DECIMALS);
+        }
+
+        #[ink::test]
+        fn create_stream_without_memo_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.memo, None);
+        }
+
+        #[ink::test]
+        fn create_stream_with_cliff_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            let current_time = get_current_time_in_seconds(&contract);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        cliff: Some(150),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.start_date, current_time);
+            assert_eq!(stream.cliff_date, current_time + 150);
+        }
+
+        #[ink::test]
+        fn create_stream_with_cliff_later_than_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions {
+                    cliff: Some(301),
+                    ..StreamOptions::default()
+                },
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamCliffShouldBeEarlier));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_before_cliff_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        cliff: Some(150),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // A quarter of the stream duration elapsed, before the cliff date
+            set_now(75);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAvailableBalanceIsZero));
+            assert_eq!(contract.available_balance(stream_id), Ok(0));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_after_cliff_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        cliff: Some(150),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Half of the stream duration elapsed, the cliff date is reached
+            set_now(150);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            // The balance vested since the start date is released at once
+            assert_eq!(result, Ok(500));
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.current_balance, 500);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_finished_stream_with_cliff_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        cliff: Some(300),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // The stream duration elapsed
+            set_now(300);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_batch_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            let mut stream_ids = Vec::new();
+            for duration in [300, 600, 1200] {
+                set_value_transferred(1000);
+                stream_ids.push(
+                    contract
+                        .create_stream(recipient, None, Some(duration), StreamOptions::default())
+                        .unwrap(),
+                );
+            }
+
+            set_now(150);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw_batch(stream_ids.clone());
+
+            // Assert
+            assert_eq!(result, Ok(500 + 250 + 125));
+            let balances: Vec<u128> = stream_ids
+                .iter()
+                .map(|stream_id| {
+                    contract
+                        .get_stream_by_id(*stream_id)
+                        .unwrap()
+                        .current_balance
+                })
+                .collect();
+            assert_eq!(balances, vec![500, 750, 875]);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_batch_skips_empty_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let active_stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let start_date = get_current_time_in_seconds(&contract) + 3600;
+            set_value_transferred(1000);
+            let pending_stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        start_date: Some(start_date),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            set_now(150);
+
+            // Act
+            set_sender(recipient);
+            let result =
+                contract.recipient_withdraw_batch(vec![pending_stream_id, active_stream_id]);
+
+            // Assert
+            assert_eq!(result, Ok(500));
+            let pending_stream = contract.get_stream_by_id(pending_stream_id).unwrap();
+            assert_eq!(pending_stream.current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_batch_by_agent_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.eve;
+            for stream_id in [1, 2] {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.charlie, recipient, 1000, 0, 300),
+                );
+            }
+            contract.total_locked = 2000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            contract.authorize_agent(2, agent).unwrap();
+            let recipient_balance = get_balance(recipient);
+            set_now(150);
+
+            // Act
+            set_sender(agent);
+            let result = contract.recipient_withdraw_batch(vec![1, 2]);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(recipient), recipient_balance + 1000);
+            assert_eq!(get_balance(agent), 0);
+            assert_eq!(contract.withdrawal_history(1), vec![(150, 500)]);
+            assert_eq!(contract.withdrawal_history(2), vec![(150, 500)]);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_batch_skips_streams_below_existential_deposit() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract
+                .streams
+                .insert(2, &Stream::new(accounts.charlie, recipient, 300, 0, 300));
+            contract.total_locked = 3300;
+            set_sender(recipient);
+            set_now(50);
+
+            // Act
+            let result = contract.recipient_withdraw_batch(vec![1, 2]);
+
+            // Assert
+            assert_eq!(result, Ok(500));
+            assert_eq!(contract.get_stream_by_id(2).unwrap().current_balance, 300);
+            assert!(contract.withdrawal_history(2).is_empty());
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_batch_with_unauthorized_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let own_stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_value_transferred(1000);
+            let other_stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            set_now(150);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw_batch(vec![own_stream_id, other_stream_id]);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            let own_stream = contract.get_stream_by_id(own_stream_id).unwrap();
+            assert_eq!(own_stream.current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn create_psp22_stream_on_native_contract_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.create_psp22_stream(
+                accounts.bob,
+                1000,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::UnsupportedAsset));
+        }
+
+        #[ink::test]
+        fn create_psp22_stream_without_funds_fails() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let mut contract = StreamsContract::new_psp22(accounts.django);
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.create_psp22_stream(
+                accounts.bob,
+                0,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::EmptyFunds));
+        }
+
+        #[ink::test]
+        fn top_up_stream_on_psp22_contract_fails() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let mut contract = StreamsContract::new_psp22(accounts.django);
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            contract.total_locked = 1000;
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.top_up_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::UnsupportedAsset));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().original_balance, 1000);
+        }
+
+        #[ink::test]
+        fn commitments_on_psp22_contract_fail() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let mut contract = StreamsContract::new_psp22(accounts.django);
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            contract.commitments.insert(1, &vec![(0, 1000)]);
+            set_sender(accounts.bob);
+
+            // Act
+            let add_result = contract.add_commitment(1, 150, 1000);
+            let fulfill_result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(add_result, Err(ContractError::UnsupportedAsset));
+            assert_eq!(fulfill_result, Err(ContractError::UnsupportedAsset));
+            assert_eq!(contract.get_commitments(1), vec![(0, 1000)]);
+        }
+
+        #[ink::test]
+        fn create_stream_with_native_funds_on_psp22_contract_fails() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let mut contract = StreamsContract::new_psp22(accounts.django);
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::UnsupportedAsset));
+            assert_eq!(contract.token(), Some(accounts.django));
+        }
+
+        #[ink::test]
+        fn create_stream_skips_ids_already_in_use() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let existing_stream = Stream::new(accounts.bob, accounts.charlie, 500, 0, 300);
+            contract.streams.insert(1, &existing_stream);
+            set_sender(accounts.alice);
+            set_value_transferred(1);
+
+            // Act
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(10000), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_id, 2);
+            assert_eq!(contract.next_stream_id, 3);
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), existing_stream);
+            assert_eq!(contract.get_stream_by_id(2).unwrap().payer, accounts.alice);
+        }
+
+        #[ink::test]
+        fn insert_new_stream_over_existing_id_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let existing_stream = Stream::new(accounts.bob, accounts.charlie, 500, 0, 300);
+            contract.streams.insert(1, &existing_stream);
+
+            // Act
+            let result = contract
+                .insert_new_stream(1, &Stream::new(accounts.django, accounts.eve, 1000, 0, 300));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamIdAlreadyExists));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), existing_stream);
+        }
+
+        #[ink::test]
+        fn create_stream_units_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            for (count, unit, duration) in [
+                (300, TimeUnit::Seconds, 300),
+                (5, TimeUnit::Minutes, 300),
+                (2, TimeUnit::Hours, 7_200),
+                (30, TimeUnit::Days, 2_592_000),
+            ] {
+                // Act
+                let stream_id = contract
+                    .create_stream_units(accounts.bob, count, unit)
+                    .unwrap();
+
+                // Assert
+                let stream = contract.get_stream_by_id(stream_id).unwrap();
+                assert_eq!(stream.total_duration(), duration);
+                assert_eq!(stream.original_balance, 1000);
+            }
+        }
+
+        #[ink::test]
+        fn create_stream_units_overflow_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream_units(accounts.bob, u64::MAX, TimeUnit::Days);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_with_bonus_withholds_bonus_until_completion() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_now(0);
+            set_sender(accounts.bob);
+            set_value_transferred(1300);
+            let stream_id = contract
+                .create_stream_with_bonus(accounts.charlie, 300, 300)
+                .unwrap();
+            set_balance(get_contract_id(), 1300);
+
+            // Act
+            set_now(150);
+            let halfway_available = contract.available_balance(stream_id);
+            set_now(299);
+            let before_end_available = contract.available_balance(stream_id);
+            set_now(300);
+            let finished_available = contract.available_balance(stream_id);
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.completion_bonus, 300);
+            assert_eq!(stream.current_balance, 1300);
+            assert_eq!(halfway_available, Ok(500));
+            assert_eq!(before_end_available, Ok(996));
+            assert_eq!(finished_available, Ok(1300));
+        }
+
+        #[ink::test]
+        fn create_stream_with_bonus_emits_bonus() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1300);
+
+            // Act
+            let stream_id = contract
+                .create_stream_with_bonus(accounts.charlie, 300, 300)
+                .unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::StreamCreated(StreamCreated {
+                    stream_id: event_stream_id,
+                    amount: 1300,
+                    completion_bonus: 300,
+                    ..
+                }) if event_stream_id == stream_id
+            ));
+        }
+
+        #[ink::test]
+        fn flow_rate_and_annualized_rate_exclude_bonus() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 3300, 0, 300);
+            stream.completion_bonus = 300;
+            contract.streams.insert(1, &stream);
+
+            // Act
+            let flow_rate = contract.flow_rate(1);
+            let annualized_rate = contract.annualized_rate(1);
+
+            // Assert
+            assert_eq!(flow_rate, Ok(10));
+            assert_eq!(annualized_rate, Ok(10 * 365 * 24 * 60 * 60));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_releases_bonus_at_completion() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.charlie;
+            set_now(0);
+            set_sender(accounts.bob);
+            set_value_transferred(1300);
+            let stream_id = contract
+                .create_stream_with_bonus(recipient, 300, 300)
+                .unwrap();
+            set_balance(get_contract_id(), 1300);
+            set_sender(recipient);
+            set_now(150);
+            assert_eq!(contract.recipient_withdraw(stream_id, None), Ok(500));
+
+            // Act
+            set_now(300);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Ok(800));
+            assert_eq!(
+                contract
+                    .get_stream_by_id(stream_id)
+                    .unwrap()
+                    .current_balance,
+                0
+            );
+            assert_eq!(contract.total_locked, 0);
+        }
+
+        #[ink::test]
+        fn cancel_stream_refunds_bonus_to_payer() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let recipient = accounts.charlie;
+            set_now(0);
+            set_sender(payer);
+            set_value_transferred(1300);
+            let stream_id = contract
+                .create_stream_with_bonus(recipient, 300, 300)
+                .unwrap();
+            set_balance(get_contract_id(), 1300);
+            let payer_balance = get_balance(payer);
+            let recipient_balance = get_balance(recipient);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.cancel_stream(stream_id);
+
+            // Assert
+            assert_eq!(result, Ok((500, 800)));
+            assert_eq!(get_balance(recipient), recipient_balance + 500);
+            assert_eq!(get_balance(payer), payer_balance + 800);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.completion_bonus, 0);
+            assert_eq!(stream.original_balance, 500);
+            assert_eq!(contract.total_locked, 0);
+        }
+
+        #[ink::test]
+        fn claim_stream_with_correct_preimage_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let preimage = b"claim code".to_vec();
+            set_now(100);
+            set_sender(payer);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(&preimage), 300)
+                .unwrap();
+            assert_eq!(contract.total_locked, 0);
+            commit_claim_and_wait(&mut contract, &preimage, accounts.charlie);
+
+            // Act
+            let result = contract.claim_stream(preimage.clone());
+
+            // Assert
+            let stream_id = result.unwrap();
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.payer, payer);
+            assert_eq!(stream.recipient, accounts.charlie);
+            assert_eq!(stream.original_balance, 1000);
+            assert_eq!(stream.start_date, 100);
+            assert_eq!(stream.end_date, 400);
+            assert_eq!(contract.total_locked, 1000);
+            set_sender(accounts.django);
+            assert_eq!(
+                contract.claim_stream(preimage),
+                Err(ContractError::InvalidClaim)
+            );
+        }
+
+        #[ink::test]
+        fn claim_stream_with_incorrect_preimage_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let hash = hash_claim_preimage(b"claim code");
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract.create_claimable_stream(hash, 300).unwrap();
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.claim_stream(b"wrong code".to_vec());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidClaim));
+            assert_eq!(contract.total_streams(), 0);
+            assert_eq!(
+                contract.claimable_streams.get(hash),
+                Some((accounts.bob, 1000, 300))
+            );
+        }
+
+        #[ink::test]
+        fn create_claimable_stream_with_used_hash_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let hash = hash_claim_preimage(b"claim code");
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract.create_claimable_stream(hash, 300).unwrap();
+
+            // Act
+            set_value_transferred(500);
+            let result = contract.create_claimable_stream(hash, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ClaimAlreadyExists));
+            assert_eq!(
+                contract.claimable_streams.get(hash),
+                Some((accounts.bob, 1000, 300))
+            );
+            assert_eq!(contract.total_escrowed, 1000);
+        }
+
+        #[ink::test]
+        fn claim_stream_without_commitment_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let preimage = b"claim code".to_vec();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(&preimage), 300)
+                .unwrap();
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.claim_stream(preimage);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ClaimNotCommitted));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn claim_stream_in_commitment_block_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let preimage = b"claim code".to_vec();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(&preimage), 300)
+                .unwrap();
+            set_sender(accounts.charlie);
+            contract
+                .commit_claim(hash_claim_commitment(&preimage, &accounts.charlie))
+                .unwrap();
+
+            // Act
+            let result = contract.claim_stream(preimage.clone());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ClaimRevealTooEarly));
+            advance_block();
+            assert!(contract.claim_stream(preimage).is_ok());
+        }
+
+        #[ink::test]
+        fn claim_stream_with_commitment_of_other_account_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let preimage = b"claim code".to_vec();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(&preimage), 300)
+                .unwrap();
+            commit_claim_and_wait(&mut contract, &preimage, accounts.charlie);
+            set_sender(accounts.django);
+
+            // Act
+            let result = contract.claim_stream(preimage);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ClaimNotCommitted));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn commit_claim_twice_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let commitment = hash_claim_commitment(b"claim code", &accounts.charlie);
+            set_sender(accounts.charlie);
+            contract.commit_claim(commitment).unwrap();
+            advance_block();
+            set_sender(accounts.django);
+
+            // Act
+            let result = contract.commit_claim(commitment);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ClaimAlreadyCommitted));
+        }
+
+        #[ink::test]
+        fn cancel_claimable_stream_refunds_payer() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let hash = hash_claim_preimage(b"claim code");
+            set_sender(payer);
+            set_value_transferred(1000);
+            contract.create_claimable_stream(hash, 300).unwrap();
+            set_balance(get_contract_id(), 1000);
+            let payer_balance = get_balance(payer);
+
+            // Act
+            let result = contract.cancel_claimable_stream(hash);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(payer), payer_balance + 1000);
+            assert_eq!(contract.claimable_streams.get(hash), None);
+        }
+
+        #[ink::test]
+        fn claim_stream_above_payer_stream_limit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let preimage = b"claim code".to_vec();
+            contract.max_streams_per_payer = 1;
+            set_sender(payer);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(&preimage), 300)
+                .unwrap();
+            contract
+                .create_stream(accounts.django, None, Some(300), StreamOptions::default())
+                .unwrap();
+            commit_claim_and_wait(&mut contract, &preimage, accounts.charlie);
+
+            // Act
+            let result = contract.claim_stream(preimage);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamLimitReached));
+            assert_eq!(contract.total_streams(), 1);
+            assert_eq!(contract.total_escrowed, 1000);
+        }
+
+        #[ink::test]
+        fn solvency_counts_escrowed_funds() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+            contract
+                .create_claimable_stream(hash_claim_preimage(b"claim code"), 300)
+                .unwrap();
+            set_balance(get_contract_id(), 1000);
+
+            // Act
+            let solvency = contract.solvency();
+            let accounting_delta = contract.accounting_delta();
+
+            // Assert
+            assert_eq!(solvency, (1000, 1000));
+            assert_eq!(accounting_delta, 0);
+        }
+
+        #[ink::test]
+        fn create_stream_with_bonus_not_lower_than_funds_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream_with_bonus(accounts.charlie, 300, 1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ValidationError));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_with_end_date_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            let end_date = 1910126705;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let current_time = get_current_time_in_seconds(&contract);
+            let stream_id = contract
+                .create_stream(recipient, Some(end_date), None, StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_id, contract.next_stream_id - 1);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.payer, sender);
+            assert_eq!(stream.recipient, recipient);
+            assert_eq!(stream.original_balance, funds);
+            assert_eq!(stream.current_balance, funds);
+            assert_eq!(stream.start_date, current_time);
+            assert_eq!(stream.end_date, end_date);
+        }
+
+        #[ink::test]
+        fn create_stream_detailed_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+
+            // Act
+            let current_time = get_current_time_in_seconds(&contract);
+            let (stream_id, stream) = contract
+                .create_stream_detailed(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(stream_id, 1);
+            assert_eq!(stream, contract.get_stream_by_id(stream_id).unwrap());
+            assert_eq!(stream.recipient, accounts.bob);
+            assert_eq!(stream.original_balance, funds);
+            assert_eq!(stream.end_date, current_time + 300);
+        }
+
+        #[ink::test]
+        fn create_stream_with_agreeing_end_date_and_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let duration = 600;
+            let end_date = get_current_time_in_seconds(&contract) + duration;
+            set_sender(accounts.alice);
+            set_value_transferred(1);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    accounts.bob,
+                    Some(end_date),
+                    Some(duration),
+                    StreamOptions::default(),
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.end_date, end_date);
+        }
+
+        #[ink::test]
+        fn create_stream_with_conflicting_end_date_and_duration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let duration = 600;
+            let end_date = get_current_time_in_seconds(&contract) + duration + 1;
+            set_sender(accounts.alice);
+            set_value_transferred(1);
+
+            // Act
+            let result = contract.create_stream(
+                accounts.bob,
+                Some(end_date),
+                Some(duration),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::EndDateDurationConflict));
+        }
+
+        #[ink::test]
+        fn create_immutable_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    accounts.bob,
+                    None,
+                    Some(10000),
+                    StreamOptions {
+                        immutable: true,
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert!(stream.immutable);
+            assert_eq!(
+                stream.can_be_modified(),
+                Err(ContractError::StreamImmutable)
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_from_immutable_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            let mut stream = Stream::new(accounts.bob, recipient, funds, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, funds);
+        }
+
+        #[ink::test]
+        fn create_stream_exceeding_tvl_cap_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 600;
+            set_sender(accounts.alice);
+            contract.set_tvl_cap(Some(1200)).unwrap();
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::TvlCapExceeded));
+            assert_eq!(contract.total_locked, 2 * funds);
+        }
+
+        #[ink::test]
+        fn create_stream_after_withdrawal_frees_tvl_cap_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 600;
+            set_sender(accounts.alice);
+            contract.set_tvl_cap(Some(funds)).unwrap();
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds);
+
+            // Stream finished
+            set_now(300);
+
+            set_sender(accounts.bob);
+            contract.recipient_withdraw(stream_id, None).unwrap();
+
+            // Act
+            set_sender(accounts.alice);
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+            assert_eq!(contract.total_locked, funds);
+        }
+
+        #[ink::test]
+        fn accounting_delta_with_stray_funds_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let stray_funds = 500;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), 2 * funds + stray_funds);
+
+            // Act
+            let delta = contract.accounting_delta();
+
+            // Assert
+            assert_eq!(delta, stray_funds as i128);
+        }
+
+        #[ink::test]
+        fn accounting_delta_with_missing_funds_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds - 100);
+
+            // Act
+            let delta = contract.accounting_delta();
+
+            // Assert
+            assert_eq!(delta, -100);
+        }
+
+        #[ink::test]
+        fn solvency_after_creates_and_withdrawals_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            for recipient in [accounts.bob, accounts.charlie] {
+                contract
+                    .create_stream(recipient, None, Some(300), StreamOptions::default())
+                    .unwrap();
+            }
+            set_balance(get_contract_id(), 2 * funds);
+            let after_creates = contract.solvency();
+
+            // 50% of the streams elapsed
+            set_now(150);
+
+            // Act
+            set_sender(accounts.bob);
+            contract.recipient_withdraw(1, None).unwrap();
+            let after_withdrawal = contract.solvency();
+
+            // Assert
+            assert_eq!(after_creates, (2 * funds, 2 * funds));
+            assert_eq!(after_withdrawal, (1500, 1500));
+        }
+
+        #[ink::test]
+        fn solvency_with_missing_funds_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds - 100);
+
+            // Act
+            let (held, outstanding) = contract.solvency();
+
+            // Assert
+            assert!(held < outstanding);
+            assert_eq!(outstanding, funds);
+        }
+
+        #[ink::test]
+        fn sweep_unallocated_only_sweeps_surplus() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let stray_funds = 500;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds + stray_funds);
+
+            // Act
+            let swept = contract.sweep_unallocated(accounts.django);
+            let swept_again = contract.sweep_unallocated(accounts.django);
+
+            // Assert
+            assert_eq!(swept, Ok(stray_funds));
+            assert_eq!(swept_again, Ok(0));
+            assert_eq!(get_balance(accounts.django), stray_funds);
+            assert_eq!(contract.solvency(), (funds, funds));
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            set_sender(accounts.bob);
+            assert_eq!(contract.recipient_withdraw(1, None), Ok(funds));
+        }
+
+        #[ink::test]
+        fn create_stream_from_balance_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let surplus = 500;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds + surplus);
+            set_value_transferred(0);
+
+            // Act
+            let result = contract.create_stream_from_balance(accounts.charlie, surplus, 300);
+
+            // Assert
+            assert_eq!(result, Ok(2));
+            let stream = contract.get_stream_by_id(2).unwrap();
+            assert_eq!(stream.payer, accounts.alice);
+            assert_eq!(stream.recipient, accounts.charlie);
+            assert_eq!(stream.original_balance, surplus);
+            assert_eq!(stream.end_date - stream.start_date, 300);
+            assert_eq!(contract.solvency(), (funds + surplus, funds + surplus));
+            assert_eq!(
+                contract.create_stream_from_balance(accounts.charlie, 1, 300),
+                Err(ContractError::InsufficientContractBalance)
+            );
+            assert_eq!(contract.sweep_unallocated(accounts.django), Ok(0));
+        }
+
+        #[ink::test]
+        fn create_stream_from_balance_excludes_escrowed_funds() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.bob);
+            set_value_transferred(funds);
+            contract
+                .create_claimable_stream(hash_claim_preimage(b"claim code"), 300)
+                .unwrap();
+            set_balance(get_contract_id(), funds);
+            set_value_transferred(0);
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.create_stream_from_balance(accounts.charlie, 1, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            assert_eq!(contract.sweep_unallocated(accounts.django), Ok(0));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_from_balance_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_balance(get_contract_id(), 1000);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.create_stream_from_balance(accounts.charlie, 500, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn sweep_unallocated_with_missing_funds_sweeps_nothing() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds - 100);
+
+            // Act
+            let swept = contract.sweep_unallocated(accounts.django);
+
+            // Assert
+            assert_eq!(swept, Ok(0));
+            assert_eq!(get_balance(get_contract_id()), funds - 100);
+        }
+
+        #[ink::test]
+        fn sweep_unallocated_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.sweep_unallocated(accounts.bob);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn import_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let first_stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            let second_stream = Stream::new(accounts.bob, accounts.django, 500, 0, 300);
+            set_sender(accounts.alice);
+
+            // Act
+            let first_result = contract.import_stream(7, first_stream);
+            let second_result = contract.import_stream(3, second_stream);
+
+            // Assert
+            assert_eq!(first_result, Ok(()));
+            assert_eq!(second_result, Ok(()));
+            assert_eq!(contract.get_stream_by_id(7), Ok(first_stream));
+            assert_eq!(contract.get_stream_by_id(3), Ok(second_stream));
+            assert_eq!(contract.next_stream_id, 8);
+            assert_eq!(contract.total_streams(), 2);
+            assert_eq!(contract.total_locked, 1500);
+            assert_eq!(contract.get_streams_for_payer(accounts.bob), vec![7, 3]);
+            assert_eq!(
+                contract.get_streams_for_recipient(accounts.charlie),
+                vec![7]
+            );
+        }
+
+        #[ink::test]
+        fn import_stream_after_finalize_migration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract
+                .import_stream(1, Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300))
+                .unwrap();
+            contract.finalize_migration().unwrap();
+
+            // Act
+            let result = contract
+                .import_stream(2, Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::MigrationFinalized));
+            assert_eq!(
+                contract.get_stream_by_id(2),
+                Err(ContractError::StreamDoesNotExist)
+            );
+        }
+
+        #[ink::test]
+        fn import_stream_after_create_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            let result = contract
+                .import_stream(5, Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::MigrationFinalized));
+        }
+
+        #[ink::test]
+        fn import_inconsistent_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            let mut overfunded_stream = stream;
+            overfunded_stream.current_balance = 2000;
+            let mut reversed_stream = stream;
+            reversed_stream.end_date = 0;
+            let mut cancelled_stream = stream;
+            cancelled_stream.cancelled = true;
+            set_sender(accounts.alice);
+
+            for invalid_stream in [overfunded_stream, reversed_stream, cancelled_stream] {
+                // Act
+                let result = contract.import_stream(1, invalid_stream);
+
+                // Assert
+                assert_eq!(result, Err(ContractError::ValidationError));
+                assert_eq!(
+                    contract.get_stream_by_id(1),
+                    Err(ContractError::StreamDoesNotExist)
+                );
+                assert!(contract.get_streams_for_payer(accounts.bob).is_empty());
+                assert_eq!(contract.total_locked, 0);
+            }
+        }
+
+        #[ink::test]
+        fn import_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let import_result = contract
+                .import_stream(1, Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300));
+            let finalize_result = contract.finalize_migration();
+
+            // Assert
+            assert_eq!(import_result, Err(ContractError::Unauthorized));
+            assert_eq!(finalize_result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn set_subscriber_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.bob);
+
+            // Act
+            contract.set_subscriber(1, Some(accounts.django)).unwrap();
+
+            // Assert
+            assert_eq!(contract.get_subscriber(1), Some(accounts.django));
+        }
+
+        #[ink::test]
+        fn set_subscriber_to_none_removes_it() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.bob);
+            contract.set_subscriber(1, Some(accounts.django)).unwrap();
+
+            // Act
+            contract.set_subscriber(1, None).unwrap();
+
+            // Assert
+            assert_eq!(contract.get_subscriber(1), None);
+        }
+
+        #[ink::test]
+        fn set_subscriber_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.set_subscriber(1, Some(accounts.django));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_subscriber(1), None);
+        }
+
+        #[ink::test]
+        fn transfer_ownership_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            contract.transfer_ownership(accounts.bob).unwrap();
+
+            // Assert
+            assert_eq!(contract.owner, accounts.bob);
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::OwnershipTransferred(OwnershipTransferred { previous, new })
+                    if previous == accounts.alice && new == accounts.bob
+            ));
+            assert_eq!(
+                contract.transfer_ownership(accounts.charlie),
+                Err(ContractError::Unauthorized)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_ownership_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.transfer_ownership(accounts.bob);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.owner, accounts.alice);
+        }
+
+        #[ink::test]
+        fn transfer_ownership_to_zero_account_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.transfer_ownership(AccountId::from([0u8; 32]));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidAccount));
+            assert_eq!(contract.owner, accounts.alice);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_fee_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 10000;
+            let owner = accounts.django;
+            let recipient = accounts.bob;
+            contract.owner = owner;
+            set_sender(owner);
+            contract.set_fee_bps(250).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, funds, 0, 300));
+            let owner_balance = get_balance(owner);
+            let recipient_balance = get_balance(recipient);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, 9750);
+            assert_eq!(get_balance(recipient), recipient_balance + 9750);
+            assert_eq!(get_balance(owner), owner_balance + 250);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 0);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_fee_exempt_recipient_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let owner = accounts.django;
+            let exempt_recipient = accounts.bob;
+            let recipient = accounts.charlie;
+            contract.owner = owner;
+            set_sender(owner);
+            contract.set_fee_bps(250).unwrap();
+            contract.set_fee_exempt(exempt_recipient, true).unwrap();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.eve, exempt_recipient, 10000, 0, 300),
+            );
+            contract
+                .streams
+                .insert(2, &Stream::new(accounts.eve, recipient, 10000, 0, 300));
+            contract.total_locked = 20000;
+            let owner_balance = get_balance(owner);
+            let exempt_recipient_balance = get_balance(exempt_recipient);
+            let recipient_balance = get_balance(recipient);
+            set_now(300);
+
+            // Act
+            set_sender(exempt_recipient);
+            let exempt_withdrawn = contract.recipient_withdraw(1, None);
+            set_sender(recipient);
+            let withdrawn = contract.recipient_withdraw(2, None);
+
+            // Assert
+            assert_eq!(exempt_withdrawn, Ok(10000));
+            assert_eq!(withdrawn, Ok(9750));
+            assert_eq!(
+                get_balance(exempt_recipient),
+                exempt_recipient_balance + 10000
+            );
+            assert_eq!(get_balance(recipient), recipient_balance + 9750);
+            assert_eq!(get_balance(owner), owner_balance + 250);
+            assert!(matches!(
+                get_recorded_events().last().unwrap(),
+                Event::Withdrawn(Withdrawn { fee: 250, .. })
+            ));
+        }
+
+        #[ink::test]
+        fn set_fee_exempt_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            contract.set_fee_exempt(accounts.bob, true).unwrap();
+            let exempt = contract.is_fee_exempt(accounts.bob);
+            contract.set_fee_exempt(accounts.bob, false).unwrap();
+
+            // Assert
+            assert!(exempt);
+            assert!(!contract.is_fee_exempt(accounts.bob));
+            set_sender(accounts.bob);
+            assert_eq!(
+                contract.set_fee_exempt(accounts.bob, true),
+                Err(ContractError::Unauthorized)
+            );
+            assert!(!contract.is_fee_exempt(accounts.bob));
+        }
+
+        #[ink::test]
+        fn preview_withdraw_with_fee_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_fee_bps(250).unwrap();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 10000, 0, 300),
+            );
+            set_now(150);
+
+            // Act
+            let full_preview = contract.preview_withdraw(1, None);
+            let partial_preview = contract.preview_withdraw(1, Some(2000));
+            let excessive_preview = contract.preview_withdraw(1, Some(5001));
+
+            // Assert
+            assert_eq!(full_preview, Ok((4875, 125)));
+            assert_eq!(partial_preview, Ok((1950, 50)));
+            assert_eq!(
+                excessive_preview,
+                Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance)
+            );
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 10000);
+        }
+
+        #[ink::test]
+        fn preview_withdraw_matches_recipient_withdraw() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_fee_bps(250).unwrap();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 10000, 0, 300),
+            );
+            contract.total_locked = 10000;
+            set_now(300);
+            let (net_amount, _) = contract.preview_withdraw(1, None).unwrap();
+            set_sender(accounts.bob);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(amount_withdrawn, Ok(net_amount));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_without_fee_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 10000;
+            let owner = accounts.django;
+            let recipient = accounts.bob;
+            contract.owner = owner;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, funds, 0, 300));
+            let owner_balance = get_balance(owner);
+            let recipient_balance = get_balance(recipient);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, funds);
+            assert_eq!(get_balance(recipient), recipient_balance + funds);
+            assert_eq!(get_balance(owner), owner_balance);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_to_owner_with_fee_sends_full_amount() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let owner = accounts.django;
+            contract.transfer_ownership(owner).unwrap();
+            set_sender(owner);
+            contract.set_fee_bps(250).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, owner, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_balance(get_contract_id(), 3000);
+            let owner_balance = get_balance(owner);
+            set_now(100);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(975));
+            assert_eq!(get_balance(owner), owner_balance + 1000);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 2000);
+            assert_eq!(contract.total_locked, 2000);
+            let events = get_recorded_events();
+            assert!(matches!(
+                events.last().unwrap(),
+                Event::Withdrawn(Withdrawn {
+                    stream_id: 1,
+                    amount: 975,
+                    fee: 25,
+                    ..
+                })
+            ));
+        }
+
+        #[ink::test]
+        fn set_fee_bps_too_high_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.set_fee_bps(MAX_FEE_BPS + 1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::FeeTooHigh));
+            assert_eq!(contract.fee_bps, 0);
+        }
+
+        #[ink::test]
+        fn set_fee_bps_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_fee_bps(250);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_below_existential_deposit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            set_now(5);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::AmountBelowExistential));
+            assert_eq!(contract.available_balance(1), Ok(50));
+            set_now(10);
+            assert_eq!(
+                contract.recipient_withdraw(1, Some(99)),
+                Err(ContractError::AmountBelowExistential)
+            );
+            assert_eq!(contract.recipient_withdraw(1, None), Ok(100));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_remainder_below_existential_deposit_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            set_now(300);
+            contract.recipient_withdraw(1, Some(2950)).unwrap();
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(50));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 0);
+            assert_eq!(contract.total_locked, 0);
+        }
+
+        #[ink::test]
+        fn withdrawal_history_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+
+            // Act
+            for (now, amount) in [(10, None), (20, Some(50)), (50, None)] {
+                set_now(now);
+                contract.recipient_withdraw(1, amount).unwrap();
+            }
+
+            // Assert
+            assert_eq!(
+                contract.withdrawal_history(1),
+                vec![(10, 100), (20, 50), (50, 350)]
+            );
+            assert!(contract.withdrawal_history(2).is_empty());
+        }
+
+        #[ink::test]
+        fn withdrawal_history_records_every_withdrawal_message() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+
+            // Act
+            set_now(10);
+            contract.recipient_withdraw_capped(1, Some(60)).unwrap();
+            set_now(20);
+            contract
+                .recipient_withdraw_split(1, None, accounts.django, 5000)
+                .unwrap();
+
+            // Assert
+            assert_eq!(contract.withdrawal_history(1), vec![(10, 60), (20, 140)]);
+        }
+
+        #[ink::test]
+        fn withdrawal_history_keeps_latest_entries() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+
+            // Act
+            for now in 1..=MAX_WITHDRAWAL_HISTORY as u64 + 1 {
+                set_now(now);
+                contract.recipient_withdraw(1, None).unwrap();
+            }
+
+            // Assert
+            let history = contract.withdrawal_history(1);
+            assert_eq!(history.len(), MAX_WITHDRAWAL_HISTORY);
+            assert_eq!(history.first(), Some(&(2, 10)));
+            assert_eq!(
+                history.last(),
+                Some(&(MAX_WITHDRAWAL_HISTORY as u64 + 1, 10))
+            );
+        }
+
+        #[ink::test]
+        fn set_existential_deposit_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_existential_deposit(100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.existential_deposit, 0);
+        }
+
+        #[ink::test]
+        fn create_stream_with_notify_to_plain_account_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        notify: true,
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+            let recipient_balance = get_balance(recipient);
+            set_now(300);
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(recipient), recipient_balance + 1000);
+            assert_eq!(contract.total_streams(), 1);
+        }
+
+        #[ink::test]
+        fn create_stream_while_paused_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_paused(true).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ContractPaused));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_while_paused_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract.set_paused(true).unwrap();
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ContractPaused));
+            // Queries keep working
+            assert_eq!(contract.available_balance(stream_id), Ok(500));
+        }
+
+        #[ink::test]
+        fn unpaused_contract_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            contract.set_paused(true).unwrap();
+            contract.set_paused(false).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            for _ in 0..25000 {
+                advance_block();
+            }
+            set_sender(recipient);
+            let result = contract.recipient_withdraw(stream_id, None);
+
+            // Assert
+            assert_eq!(result, Ok(500));
+            assert!(!contract.is_paused());
+        }
+
+        #[ink::test]
+        fn set_paused_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_paused(true);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert!(!contract.is_paused());
+        }
+
+        #[ink::test]
+        fn set_tvl_cap_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_tvl_cap(Some(1000));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.tvl_cap, None);
+        }
+
+        #[ink::test]
+        fn create_stream_within_amount_bounds_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_stream_amount_bounds(100, 1000).unwrap();
+
+            for funds in [100, 1000] {
+                // Act
+                set_value_transferred(funds);
+                let result =
+                    contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+                // Assert
+                assert!(result.is_ok());
+            }
+        }
+
+        #[ink::test]
+        fn create_stream_outside_amount_bounds_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_stream_amount_bounds(100, 1000).unwrap();
+
+            for (funds, error) in [
+                (99, ContractError::AmountTooSmall),
+                (1001, ContractError::AmountTooLarge),
+            ] {
+                // Act
+                set_value_transferred(funds);
+                let result =
+                    contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+                // Assert
+                assert_eq!(result, Err(error));
+            }
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn create_stream_with_refund_above_max_amount_refunds_excess() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_stream_amount_bounds(0, 1000).unwrap();
+            let payer = accounts.charlie;
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+            set_value_transferred(1500);
+
+            // Act
+            let result = contract.create_stream_with_refund(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Ok((1, 500)));
+            assert_eq!(get_balance(payer), payer_balance + 500);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().original_balance, 1000);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn create_stream_with_refund_within_max_amount_refunds_nothing() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_stream_amount_bounds(0, 1000).unwrap();
+            let payer = accounts.charlie;
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+            set_value_transferred(800);
+
+            // Act
+            let result = contract.create_stream_with_refund(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions::default(),
+            );
+
+            // Assert
+            assert_eq!(result, Ok((1, 0)));
+            assert_eq!(get_balance(payer), payer_balance);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().original_balance, 800);
+        }
+
+        #[ink::test]
+        fn create_stream_over_max_duration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_max_duration(1000).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let within =
+                contract.create_stream(accounts.bob, None, Some(1000), StreamOptions::default());
+            let over =
+                contract.create_stream(accounts.bob, None, Some(1001), StreamOptions::default());
+
+            // Assert
+            assert!(within.is_ok());
+            assert_eq!(over, Err(ContractError::StreamDurationTooLong));
+            assert_eq!(contract.total_streams(), 1);
+        }
+
+        #[ink::test]
+        fn set_max_duration_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_max_duration(1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.maximum_duration(), 0);
+        }
+
+        #[ink::test]
+        fn set_max_duration_lower_than_min_duration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.set_max_duration(299);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ValidationError));
+        }
+
+        #[ink::test]
+        fn set_stream_amount_bounds_with_min_greater_than_max_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.set_stream_amount_bounds(1000, 100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ValidationError));
+            assert_eq!(contract.stream_amount_bounds(), (0, 0));
+        }
+
+        #[ink::test]
+        fn set_stream_amount_bounds_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_stream_amount_bounds(100, 1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.stream_amount_bounds(), (0, 0));
+        }
+
+        #[ink::test]
+        fn create_stream_to_allowed_recipient_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_allowlist_enabled(true).unwrap();
+            contract.set_recipient_allowed(accounts.bob, true).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn create_stream_to_not_allowed_recipient_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_allowlist_enabled(true).unwrap();
+            contract.set_recipient_allowed(accounts.bob, true).unwrap();
+            contract.set_recipient_allowed(accounts.bob, false).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientNotAllowed));
+        }
+
+        #[ink::test]
+        fn create_stream_with_disabled_allowlist_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            contract.set_allowlist_enabled(true).unwrap();
+            contract.set_allowlist_enabled(false).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+            assert!(!contract.is_recipient_allowed(accounts.bob));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_to_not_allowed_recipient_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.alice);
+            contract.set_allowlist_enabled(true).unwrap();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientNotAllowed));
+        }
+
+        #[ink::test]
+        fn set_recipient_allowed_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_recipient_allowed(accounts.bob, true);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert!(!contract.is_recipient_allowed(accounts.bob));
+        }
+
+        #[ink::test]
+        fn set_allowlist_enabled_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_allowlist_enabled(true);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert!(!contract.is_allowlist_enabled());
+        }
+
+        #[ink::test]
+        fn create_stream_without_funds_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            set_sender(sender);
+
+            // Act
+            let result = contract.create_stream(recipient, None, None, StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::EmptyFunds));
+        }
+
+        #[ink::test]
+        fn create_stream_without_end_date_and_duration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let result = contract.create_stream(recipient, None, None, StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::EndDateAndDurationAreEmpty));
+        }
+
+        #[ink::test]
+        fn create_stream_with_default_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let default_duration = 600;
+            set_sender(accounts.alice);
+            contract
+                .set_default_duration(Some(default_duration))
+                .unwrap();
+            set_value_transferred(1);
+
+            // Act
+            let stream_id = contract
+                .create_stream(accounts.bob, None, None, StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.end_date, stream.start_date + default_duration);
+        }
+
+        #[ink::test]
+        fn set_default_duration_shorter_than_minimum_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.set_default_duration(Some(100));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDurationShouldBeGreater));
+            assert_eq!(contract.default_duration, None);
+        }
+
+        #[ink::test]
+        fn set_default_duration_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_default_duration(Some(600));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn create_stream_with_same_payer_and_recipient_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.bob;
+            let recipient = accounts.bob;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let result = contract.create_stream(recipient, None, None, StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientCannotBePayer));
+        }
+
+        #[ink::test]
+        fn create_stream_with_short_duration_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            let duration = 100;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let result =
+                contract.create_stream(recipient, None, Some(duration), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDurationShouldBeGreater));
+        }
+
+        #[ink::test]
+        fn create_stream_with_short_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1;
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            let end_date = 100;
+            set_sender(sender);
+            set_value_transferred(funds);
+
+            // Act
+            let result =
+                contract.create_stream(recipient, Some(end_date), None, StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDurationShouldBeGreater));
+        }
+
+        #[ink::test]
+        fn create_stream_with_start_offset_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_now(1000);
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream(
+                    recipient,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        start_offset: Some(3600),
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.created_at, 1000);
+            assert_eq!(stream.start_date, 4600);
+            assert_eq!(stream.end_date, 4900);
+
+            set_now(4599);
+            assert_eq!(contract.available_balance(stream_id), Ok(0));
+            set_sender(recipient);
+            assert_eq!(
+                contract.recipient_withdraw(stream_id, None),
+                Err(ContractError::StreamAvailableBalanceIsZero)
+            );
+
+            set_now(4750);
+            assert_eq!(contract.available_balance(stream_id), Ok(500));
+        }
+
+        #[ink::test]
+        fn create_stream_with_start_date_and_start_offset_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.create_stream(
+                accounts.bob,
+                None,
+                Some(300),
+                StreamOptions {
+                    start_date: Some(3600),
+                    start_offset: Some(3600),
+                    ..StreamOptions::default()
+                },
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StartDateOffsetConflict));
+        }
+
+        #[ink::test]
+        fn create_stream_with_past_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let sender = accounts.alice;
+            let recipient = accounts.bob;
+            set_now(1000);
+            set_sender(sender);
+            set_value_transferred(1);
+
+            // Act
+            let result =
+                contract.create_stream(recipient, Some(900), None, StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamEndDateInPast));
+        }
+
+        #[ink::test]
+        fn preview_stream_works() {
+            // Arrange
+            let (contract, accounts) = init();
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.preview_stream(accounts.bob, None, Some(300), 3000);
+
+            // Assert
+            assert_eq!(
+                result,
+                Ok((get_current_time_in_seconds(&contract) + 300, 10))
+            );
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn preview_stream_returns_create_stream_errors() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            let cases = [
+                (accounts.bob, None, Some(300), 0),
+                (accounts.bob, None, None, 1),
+                (accounts.alice, None, Some(300), 1),
+                (accounts.bob, None, Some(100), 1),
+                (accounts.bob, Some(100), None, 1),
+                (accounts.bob, Some(1000), Some(300), 1),
+            ];
+
+            for (recipient, end_date, duration, funds) in cases {
+                // Act
+                let preview = contract.preview_stream(recipient, end_date, duration, funds);
+                set_value_transferred(funds);
+                let result =
+                    contract.create_stream(recipient, end_date, duration, StreamOptions::default());
+
+                // Assert
+                assert!(preview.is_err());
+                assert_eq!(preview.err(), result.err());
+            }
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_all_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, funds);
+        }
+
+        #[ink::test]
+        fn available_balance_at_half_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let even_stream = Stream::new(accounts.bob, accounts.charlie, 1000, 100, 400);
+            let odd_stream = Stream::new(accounts.bob, accounts.charlie, 1001, 100, 401);
+            contract.streams.insert(1, &even_stream);
+            contract.streams.insert(2, &odd_stream);
+
+            // Act
+            set_now(even_stream.start_date + (even_stream.end_date - even_stream.start_date) / 2);
+            let even_available = contract.available_balance(1);
+            set_now(odd_stream.start_date + (odd_stream.end_date - odd_stream.start_date) / 2);
+            let odd_available = contract.available_balance(2);
+
+            // Assert
+            assert_eq!(even_available, Ok(500));
+            // 1001 * 150 / 301 = 498.8, rounded down.
+            assert_eq!(odd_available, Ok(498));
+        }
+
+        #[ink::test]
+        fn available_balance_at_quarters_of_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 100, 500),
+            );
+            contract.streams.insert(
+                2,
+                &Stream::new(accounts.bob, accounts.charlie, 1001, 100, 400),
+            );
+            let mut half_up_stream = Stream::new(accounts.bob, accounts.charlie, 1001, 100, 400);
+            half_up_stream.rounding = Rounding::HalfUp;
+            contract.streams.insert(3, &half_up_stream);
+
+            // Act
+            let mut available = Vec::new();
+            for (stream_id, quarter_duration) in [(1, 100), (2, 75), (3, 75)] {
+                for quarter in 1..=3 {
+                    set_now(100 + quarter * quarter_duration);
+                    available.push(contract.available_balance(stream_id).unwrap());
+                }
+            }
+
+            // Assert
+            assert_eq!(&available[0..3], &[250, 500, 750]);
+            // 1001 * 75 / 300 = 250.25, 1001 * 150 / 300 = 500.5 and 1001 * 225 / 300 = 750.75, rounded down.
+            assert_eq!(&available[3..6], &[250, 500, 750]);
+            // The same amounts rounded to the nearest integer, halves up.
+            assert_eq!(&available[6..9], &[250, 501, 751]);
+        }
+
+        #[ink::test]
+        fn available_balance_after_mid_stream_withdrawal_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 100, 500));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+
+            // 25% of the stream elapsed
+            set_now(200);
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Act
+            // 50% of the stream elapsed
+            set_now(300);
+            let halfway = contract.available_balance(1);
+            // 75% of the stream elapsed
+            set_now(400);
+            let three_quarters = contract.available_balance(1);
+
+            // Assert
+            assert_eq!(halfway, Ok(250));
+            assert_eq!(three_quarters, Ok(500));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 750);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_all_emits_stream_drained_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 2);
+            assert!(matches!(events[0], Event::Withdrawn(_)));
+            assert!(matches!(
+                events[1],
+                Event::StreamDrained(StreamDrained { stream_id: 1 })
+            ));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_partial_amount_does_not_emit_stream_drained_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            contract.recipient_withdraw(1, Some(funds / 2)).unwrap();
+
+            // Assert
+            assert!(!get_recorded_events()
+                .iter()
+                .any(|event| matches!(event, Event::StreamDrained(_))));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_large_balance_does_not_overflow() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = u128::MAX / 2;
+            let duration = 10 * SECONDS_PER_YEAR;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, duration));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // 150 seconds elapsed
+            set_now(150);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            let duration = duration as u128;
+            assert_eq!(
+                amount_withdrawn,
+                funds / duration * 150 + funds % duration * 150 / duration
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_emits_withdrawn_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            contract.recipient_withdraw(1, Some(funds / 2)).unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::Withdrawn(Withdrawn { stream_id: 1, recipient: event_recipient, amount, fee: 0 })
+                    if event_recipient == recipient && amount == funds / 2
+            ));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_under_collateralized_emits_solvency_warning_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            for (stream_id, recipient) in [(1, accounts.bob), (2, accounts.charlie)] {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.django, recipient, funds, 0, 300),
+                );
+            }
+            contract.total_locked = 2 * funds;
+            set_balance(get_contract_id(), 1200);
+            set_sender(accounts.bob);
+
+            // 50% of the streams elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(500));
+            assert!(get_recorded_events().iter().any(|event| matches!(
+                event,
+                Event::SolvencyWarning(warning)
+                    if warning.held == 700 && warning.outstanding == 1500
+            )));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_fully_collateralized_does_not_emit_solvency_warning_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.django, accounts.bob, funds, 0, 300),
+            );
+            contract.total_locked = funds;
+            set_balance(get_contract_id(), funds);
+            set_sender(accounts.bob);
+
+            // 50% of the stream elapsed
+            set_now(150);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert!(!get_recorded_events()
+                .iter()
+                .any(|event| matches!(event, Event::SolvencyWarning(_))));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_specific_amount_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 1500000000;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract
+                .recipient_withdraw(1, Some(expected_withdrawal_amount))
+                .unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, expected_withdrawal_amount);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_failed_transfer_keeps_stream_balance() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(funds);
+            contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), 0);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.current_balance, funds);
+            assert_eq!(stream.last_withdraw_at, None);
+            assert_eq!(contract.total_locked, funds);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_underfunded_contract_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(funds);
+            contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds - 1);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            assert_eq!(get_balance(get_contract_id()), funds - 1);
+            assert_eq!(get_balance(recipient), 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_leaving_existential_deposit_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_mock_minimum_balance(100);
+            set_balance(get_contract_id(), 1100);
+            let recipient_balance = get_balance(recipient);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(get_contract_id()), 100);
+            assert_eq!(get_balance(recipient), recipient_balance + 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_below_contract_existential_deposit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let stream = Stream::new(accounts.charlie, recipient, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_mock_minimum_balance(100);
+            set_balance(get_contract_id(), 1099);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.total_locked, 1000);
+            assert_eq!(get_balance(get_contract_id()), 1099);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_exceeding_contract_balance_keeps_stream_state() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 400);
+            set_sender(recipient);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(
+                stream,
+                Stream::new(accounts.charlie, recipient, 1000, 0, 300)
+            );
+            assert_eq!(contract.total_locked, 1000);
+            assert_eq!(get_balance(get_contract_id()), 400);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_while_withdrawing_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // A re-entrant call sees the lock held by the outer withdrawal
+            contract.reentrancy_lock.insert((), &true);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Reentrancy));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_releases_reentrancy_lock() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract
+                .streams
+                .insert(2, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+            set_balance(get_contract_id(), 0);
+            let failed_result = contract.recipient_withdraw(2, None);
+
+            // Assert
+            assert_eq!(
+                failed_result,
+                Err(ContractError::InsufficientContractBalance)
+            );
+            assert_eq!(contract.reentrancy_lock.get(()), None);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_split_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let secondary = accounts.django;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let recipient_balance = get_balance(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw_split(stream_id, None, secondary, 3000);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(secondary), 300);
+            assert_eq!(get_balance(recipient), recipient_balance + 700);
+            assert_eq!(
+                contract
+                    .get_stream_by_id(stream_id)
+                    .unwrap()
+                    .current_balance,
+                0
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_split_with_invalid_bps_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            set_sender(recipient);
+
+            // Act
+            let result = contract.recipient_withdraw_split(1, None, accounts.django, 10001);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidSplit));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_split_by_agent_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.eve;
+            let secondary = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            let recipient_balance = get_balance(recipient);
+            set_now(300);
+
+            // Act
+            set_sender(agent);
+            let result = contract.recipient_withdraw_split(1, None, secondary, 3000);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(secondary), 300);
+            assert_eq!(get_balance(recipient), recipient_balance + 700);
+            assert_eq!(get_balance(agent), 0);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_split_below_existential_deposit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw_split(1, Some(50), accounts.django, 3000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::AmountBelowExistential));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_split_with_underfunded_contract_keeps_stream_balance() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let secondary = accounts.django;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            // Only enough funds for the secondary share
+            set_balance(get_contract_id(), 300);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            set_sender(recipient);
+            let result = contract.recipient_withdraw_split(stream_id, None, secondary, 3000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InsufficientContractBalance));
+            assert_eq!(get_balance(secondary), 0);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.current_balance, 1000);
+            assert_eq!(contract.total_locked, 1000);
+            assert_eq!(contract.available_balance(stream_id), Ok(1000));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 1500000000;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(accounts.charlie);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_from_non_existent_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 1500000000;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw(999, Some(expected_withdrawal_amount));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_expected_amount_greater_than_available_balance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 3000000000;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(150);
+
+            // Act
+            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+
+            // Assert
+            assert_eq!(
+                result,
+                Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance)
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_expected_amount_equal_to_zero_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 0;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(150);
+
+            // Act
+            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+
+            // Assert
+            assert_eq!(
+                result,
+                Err(ContractError::WithdrawalAmountShouldBeGreaterThanZero)
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_with_available_balance_equal_to_zero_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let expected_withdrawal_amount = 100;
+            let recipient = accounts.alice;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+            );
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Act
+            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAvailableBalanceIsZero));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_by_authorized_agent_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            set_now(100);
+            set_sender(agent);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(recipient), 2000);
+            assert_eq!(get_balance(agent), 0);
+            assert_eq!(contract.get_agent(1), Some(agent));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_by_agent_follows_payout_address() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.django;
+            let payout_address = accounts.eve;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            contract.set_payout_address(1, payout_address).unwrap();
+            contract.authorize_agent(1, agent).unwrap();
+            set_now(100);
+            set_sender(agent);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(payout_address), 1000);
+            assert_eq!(get_balance(agent), 0);
+            assert_eq!(get_balance(recipient), 1000);
+        }
+
+        #[ink::test]
+        fn agent_cannot_redirect_withdrawals() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            contract.set_payout_address(1, accounts.eve).unwrap();
+            contract.authorize_agent(1, agent).unwrap();
+            set_now(100);
+            set_sender(agent);
+
+            // Act
+            let payout_result = contract.set_payout_address(1, agent);
+            let split_result = contract.recipient_withdraw_split(1, None, agent, 10_000);
+
+            // Assert
+            assert_eq!(payout_result, Err(ContractError::Unauthorized));
+            assert_eq!(split_result, Err(ContractError::Unauthorized));
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap().payout_address,
+                Some(accounts.eve)
+            );
+            assert_eq!(get_balance(agent), 0);
+            assert_eq!(contract.available_balance(1), Ok(1000));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_by_revoked_agent_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            contract.revoke_agent(1).unwrap();
+            set_now(100);
+            set_sender(agent);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_agent(1), None);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_by_unauthorized_agent_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(recipient);
+            contract.authorize_agent(1, accounts.django).unwrap();
+            set_now(100);
+            set_sender(accounts.eve);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+            let authorize_result = contract.authorize_agent(1, accounts.eve);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(authorize_result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_agent(1), Some(accounts.django));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_on_cooldown_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(accounts.charlie);
+            contract.set_withdraw_cooldown(1, 60).unwrap();
+            set_sender(recipient);
+            set_now(100);
+            let first_withdrawal = contract.recipient_withdraw(1, None);
+            set_now(110);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(first_withdrawal, Ok(1000));
+            assert_eq!(result, Err(ContractError::WithdrawOnCooldown));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 2000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_after_cooldown_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_sender(accounts.charlie);
+            contract.set_withdraw_cooldown(1, 60).unwrap();
+            set_sender(recipient);
+            set_now(100);
+            contract.recipient_withdraw(1, None).unwrap();
+            set_now(160);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(600));
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap().last_withdraw_at,
+                Some(160)
+            );
+        }
+
+        #[ink::test]
+        fn set_withdraw_cooldown_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 3000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_withdraw_cooldown(1, 60);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().withdraw_cooldown, 0);
+        }
+
+        #[ink::test]
+        fn set_withdraw_cooldown_longer_than_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 3000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.set_withdraw_cooldown(1, u64::MAX);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::WithdrawCooldownTooLong));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().withdraw_cooldown, 0);
+            assert_eq!(contract.set_withdraw_cooldown(1, 300), Ok(()));
+        }
+
+        #[ink::test]
+        fn enable_half_up_rounding_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 1001, 0, 300),
+            );
+            set_sender(accounts.charlie);
+            set_now(150);
+            let rounded_down = contract.available_balance(1);
+
+            // Act
+            let result = contract.enable_half_up_rounding(1);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+            // 1001 * 150 / 300 = 500.5
+            assert_eq!(rounded_down, Ok(500));
+            assert_eq!(contract.available_balance(1), Ok(501));
+            set_now(300);
+            assert_eq!(contract.available_balance(1), Ok(1001));
+        }
+
+        #[ink::test]
+        fn enable_half_up_rounding_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 1001, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.enable_half_up_rounding(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap().rounding,
+                Rounding::Down
+            );
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_from_finished_and_emptied_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            set_now(300);
+            let first_withdrawal = contract.recipient_withdraw(1, None);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(first_withdrawal, Ok(1000));
+            assert_eq!(result, Err(ContractError::StreamFullyWithdrawn));
+            assert_eq!(contract.recipient_withdraw_lenient(1, None), Ok(0));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_lenient_with_available_balance_equal_to_zero_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Act
+            let lenient_result = contract.recipient_withdraw_lenient(1, None);
+            let strict_result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(lenient_result, Ok(0));
+            assert_eq!(
+                strict_result,
+                Err(ContractError::StreamAvailableBalanceIsZero)
+            );
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.current_balance, funds);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_lenient_with_available_balance_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract.recipient_withdraw_lenient(1, None).unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, funds);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_capped_below_available_balance_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let max_amount = 1000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract
+                .recipient_withdraw_capped(1, Some(max_amount))
+                .unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, max_amount);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.current_balance, funds - max_amount);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_capped_above_available_balance_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let max_amount = 5000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            let amount_withdrawn = contract
+                .recipient_withdraw_capped(1, Some(max_amount))
+                .unwrap();
+
+            // Assert
+            assert_eq!(amount_withdrawn, funds);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_capped_by_agent_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.eve;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            let recipient_balance = get_balance(recipient);
+            set_now(150);
+
+            // Act
+            set_sender(agent);
+            let result = contract.recipient_withdraw_capped(1, Some(200));
+
+            // Assert
+            assert_eq!(result, Ok(200));
+            assert_eq!(get_balance(recipient), recipient_balance + 200);
+            assert_eq!(get_balance(agent), 0);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_capped_below_existential_deposit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            set_now(150);
+
+            // Act
+            let result = contract.recipient_withdraw_capped(1, Some(50));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::AmountBelowExistential));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_capped_from_emptied_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let mut stream = Stream::new(accounts.charlie, recipient, 1000, 0, 300);
+            stream.current_balance = 0;
+            contract.streams.insert(1, &stream);
+            set_sender(recipient);
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw_capped(1, None);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamFullyWithdrawn));
+        }
+
+        #[ink::test]
+        fn top_up_stream_halfway_vests_over_remaining_duration() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, funds, 0, 300));
+            set_sender(payer);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            set_value_transferred(funds);
+            let current_balance = contract.top_up_stream(1).unwrap();
+
+            // Assert
+            assert_eq!(current_balance, 2 * funds);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.original_balance, 2 * funds);
+            assert_eq!(contract.available_balance(1), Ok(funds / 2));
+
+            // Three quarters of the stream elapsed
+            for _ in 0..12500 {
+                advance_block();
+            }
+
+            // Half of the vested balance plus half of the remaining balance
+            assert_eq!(contract.available_balance(1), Ok(funds / 2 + 3 * funds / 4));
+        }
+
+        #[ink::test]
+        fn top_up_stream_emits_stream_topped_up_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            set_value_transferred(400);
+            contract.top_up_stream(1).unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::StreamToppedUp(StreamToppedUp {
+                    stream_id: 1,
+                    added: 400,
+                    new_balance: 1400,
+                })
+            ));
+        }
+
+        #[ink::test]
+        fn top_up_stream_overflowing_total_locked_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let stream = Stream::new(payer, accounts.charlie, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = u128::MAX;
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            set_value_transferred(400);
+            let result = contract.top_up_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.total_locked, u128::MAX);
+        }
+
+        #[ink::test]
+        fn store_new_stream_overflowing_total_locked_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.total_locked = u128::MAX;
+
+            // Act
+            let result = contract.store_new_stream(&Stream::new(
+                accounts.bob,
+                accounts.charlie,
+                1000,
+                0,
+                300,
+            ));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(
+                contract.get_stream_by_id(1),
+                Err(ContractError::StreamDoesNotExist)
+            );
+            assert!(contract.get_streams_for_payer(accounts.bob).is_empty());
+        }
+
+        #[ink::test]
+        fn extend_stream_halfway_keeps_vested_balance() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, funds, 0, 300));
+            set_sender(payer);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            contract.extend_stream(1, 450).unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.end_date, 450);
+            assert_eq!(stream.original_balance, funds);
+            assert_eq!(contract.available_balance(1), Ok(funds / 2));
+
+            // Half of the remaining duration elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Half of the vested balance plus half of the remaining balance
+            assert_eq!(contract.available_balance(1), Ok(funds / 2 + funds / 4));
+        }
+
+        #[ink::test]
+        fn extend_stream_earlier_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            set_sender(payer);
+
+            // Act
+            let result = contract.extend_stream(1, 299);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::CannotShortenStream));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().end_date, 300);
+        }
+
+        #[ink::test]
+        fn extend_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.extend_stream(1, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn extend_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let mut stream = Stream::new(payer, accounts.alice, 1000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            set_sender(payer);
+
+            // Act
+            let result = contract.extend_stream(1, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+        }
+
+        #[ink::test]
+        fn reduce_stream_halfway_keeps_vested_balance() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.bob, 1000, 0, 300));
+            contract.total_locked = 1000;
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let refunded = contract.reduce_stream(1, 300);
+
+            // Assert
+            assert_eq!(refunded, Ok(300));
+            assert_eq!(get_balance(payer), payer_balance + 300);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.original_balance, 700);
+            assert_eq!(stream.current_balance, 700);
+            assert_eq!(contract.total_locked, 700);
+            assert_eq!(contract.available_balance(1), Ok(500));
+
+            // Stream finished
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            assert_eq!(contract.available_balance(1), Ok(700));
+        }
+
+        #[ink::test]
+        fn reduce_stream_below_vested_balance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.bob, 1000, 0, 300));
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.reduce_stream(1, 501);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ReductionExceedsUnvestedBalance));
+            assert_eq!(get_balance(payer), payer_balance);
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap(),
+                Stream::new(payer, accounts.bob, 1000, 0, 300)
+            );
+        }
+
+        #[ink::test]
+        fn fork_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            contract
+                .store_new_stream(&Stream::new(payer, accounts.bob, 3000, 0, 300))
+                .unwrap();
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+            set_now(100);
+
+            // Act
+            let result = contract.fork_stream(1, accounts.django, 1000, 300);
+
+            // Assert
+            assert_eq!(result, Ok(2));
+            let source = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(source.original_balance, 2000);
+            assert_eq!(source.current_balance, 2000);
+            assert_eq!(contract.available_balance(1), Ok(1000));
+            let forked = contract.get_stream_by_id(2).unwrap();
+            assert_eq!(forked.payer, payer);
+            assert_eq!(forked.recipient, accounts.django);
+            assert_eq!(forked.original_balance, 1000);
+            assert_eq!(forked.start_date, 100);
+            assert_eq!(forked.end_date, 400);
+            assert_eq!(contract.total_locked, 3000);
+            assert_eq!(get_balance(payer), payer_balance);
+            assert_eq!(contract.get_streams_for_payer(payer), vec![1, 2]);
+
+            // Both streams vest independently
+            set_now(300);
+            assert_eq!(contract.available_balance(1), Ok(2000));
+            assert_eq!(contract.available_balance(2), Ok(666));
+        }
+
+        #[ink::test]
+        fn fork_stream_above_unvested_balance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            contract
+                .store_new_stream(&Stream::new(payer, accounts.bob, 3000, 0, 300))
+                .unwrap();
+            set_sender(payer);
+            set_now(100);
+
+            // Act
+            let result = contract.fork_stream(1, accounts.django, 2001, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ReductionExceedsUnvestedBalance));
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap(),
+                Stream::new(payer, accounts.bob, 3000, 0, 300)
+            );
+            assert_eq!(contract.total_streams(), 1);
+            assert_eq!(contract.total_locked, 3000);
+        }
+
+        #[ink::test]
+        fn fork_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .store_new_stream(&Stream::new(accounts.charlie, accounts.bob, 3000, 0, 300))
+                .unwrap();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.fork_stream(1, accounts.django, 1000, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.total_streams(), 1);
+        }
+
+        #[ink::test]
+        fn release_now_halfway_keeps_vesting_schedule() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            let recipient_balance = get_balance(recipient);
+            set_sender(accounts.charlie);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.release_now(1, 300);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+            assert_eq!(get_balance(recipient), recipient_balance + 300);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.original_balance, 1000);
+            assert_eq!(stream.current_balance, 700);
+            assert_eq!(contract.total_locked, 700);
+            assert_eq!(contract.available_balance(1), Ok(500));
+
+            // 75% of the stream elapsed, the remaining 200 unvested vest until the end date
+            for _ in 0..12500 {
+                advance_block();
+            }
+
+            assert_eq!(contract.available_balance(1), Ok(600));
+
+            // Stream finished
+            for _ in 0..12500 {
+                advance_block();
+            }
+
+            assert_eq!(contract.available_balance(1), Ok(700));
+        }
+
+        #[ink::test]
+        fn release_now_exceeding_current_balance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            let recipient_balance = get_balance(recipient);
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.release_now(1, 1001);
+
+            // Assert
+            assert_eq!(
+                result,
+                Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance)
+            );
+            assert_eq!(get_balance(recipient), recipient_balance);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn release_now_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.release_now(1, 100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn reduce_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reduce_stream(1, 100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn top_up_finished_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            set_sender(payer);
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            // Act
+            set_value_transferred(1000);
+            let result = contract.top_up_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAlreadyFinished));
+        }
+
+        #[ink::test]
+        fn top_up_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.alice);
+
+            // Act
+            set_value_transferred(1000);
+            let result = contract.top_up_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn top_up_stream_by_co_payer_records_contribution() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let co_payer = accounts.django;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            contract.total_locked = 1000;
+            set_sender(co_payer);
+            set_now(100);
+
+            // Act
+            set_value_transferred(500);
+            contract.top_up_stream(1).unwrap();
+            set_value_transferred(250);
+            let current_balance = contract.top_up_stream(1).unwrap();
+
+            // Assert
+            assert_eq!(current_balance, 1750);
+            assert_eq!(contract.get_contribution(1, co_payer), 750);
+            assert_eq!(contract.get_contribution(1, accounts.bob), 0);
+            assert_eq!(contract.get_contributors(1), vec![co_payer]);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().payer, accounts.bob);
+        }
+
+        #[ink::test]
+        fn top_up_stream_by_payer_records_no_contribution() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+            set_now(100);
+
+            // Act
+            set_value_transferred(500);
+            contract.top_up_stream(1).unwrap();
+
+            // Assert
+            assert!(contract.get_contributors(1).is_empty());
+            assert_eq!(contract.get_contribution(1, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn top_up_stream_with_too_many_contributors_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_now(100);
+            for co_payer in get_distinct_recipients(MAX_CONTRIBUTORS) {
+                set_sender(co_payer);
+                set_value_transferred(10);
+                contract.top_up_stream(1).unwrap();
+            }
+            let stream = contract.get_stream_by_id(1).unwrap();
+            set_sender(accounts.django);
+
+            // Act
+            set_value_transferred(10);
+            let result = contract.top_up_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::TooManyContributors));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.get_contributors(1).len(), MAX_CONTRIBUTORS);
+        }
+
+        #[ink::test]
+        fn cancel_stream_with_co_payer_refunds_proportionally() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let recipient = accounts.charlie;
+            let co_payer = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 4000);
+
+            // Half of the stream elapsed
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(3000);
+            contract.top_up_stream(1).unwrap();
+            let payer_balance = get_balance(payer);
+            let recipient_balance = get_balance(recipient);
+            let co_payer_balance = get_balance(co_payer);
+
+            // Three quarters of the stream elapsed
+            set_now(225);
+            set_sender(payer);
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            // Half of the first funds plus half of the remaining balance, and the payer share of the other 1750
+            assert_eq!(result, Ok((2250, 438)));
+            assert_eq!(get_balance(recipient), recipient_balance + 2250);
+            // 1750 * 3000 / 4000, rounding down
+            assert_eq!(get_balance(co_payer), co_payer_balance + 1312);
+            assert_eq!(get_balance(payer), payer_balance + 438);
+            let events = get_recorded_events();
+            assert!(matches!(
+                events[1],
+                Event::ContributionRefunded(ContributionRefunded {
+                    stream_id: 1,
+                    contributor,
+                    amount: 1312,
+                }) if contributor == co_payer
+            ));
+            assert!(matches!(
+                events[2],
+                Event::StreamCancelled(StreamCancelled {
+                    stream_id: 1,
+                    recipient_paid: 2250,
+                    payer_refunded: 438,
+                })
+            ));
+            assert_eq!(contract.total_locked, 0);
+            assert!(contract.get_contributors(1).is_empty());
+            assert_eq!(contract.get_contribution(1, co_payer), 0);
+        }
+
+        #[ink::test]
+        fn renounce_stream_with_co_payer_refunds_proportionally() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let recipient = accounts.charlie;
+            let co_payer = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 2000);
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(1000);
+            contract.top_up_stream(1).unwrap();
+            let payer_balance = get_balance(payer);
+            let co_payer_balance = get_balance(co_payer);
+            set_now(225);
+            set_sender(recipient);
+
+            // Act
+            contract.renounce_stream(1).unwrap();
+
+            // Assert
+            assert_eq!(get_balance(co_payer), co_payer_balance + 375);
+            assert_eq!(get_balance(payer), payer_balance + 375);
+            assert!(contract.get_contributors(1).is_empty());
+        }
+
+        #[ink::test]
+        fn reduce_stream_with_co_payer_refunds_proportionally() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let co_payer = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 2000);
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(1000);
+            contract.top_up_stream(1).unwrap();
+            let payer_balance = get_balance(payer);
+            let co_payer_balance = get_balance(co_payer);
+            set_sender(payer);
+
+            // Act
+            let result = contract.reduce_stream(1, 1000);
+
+            // Assert
+            // 1000 * 1000 / 2000
+            assert_eq!(result, Ok(500));
+            assert_eq!(get_balance(co_payer), co_payer_balance + 500);
+            assert_eq!(get_balance(payer), payer_balance + 500);
+            assert_eq!(contract.get_contribution(1, co_payer), 500);
+            assert_eq!(contract.get_stream_by_id(1).unwrap().original_balance, 1000);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn fork_stream_with_co_payer_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let co_payer = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(1000);
+            contract.top_up_stream(1).unwrap();
+            let stream = contract.get_stream_by_id(1).unwrap();
+            set_sender(payer);
+
+            // Act
+            let result = contract.fork_stream(1, accounts.eve, 1000, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamHasContributors));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.get_contribution(1, co_payer), 1000);
+            assert_eq!(contract.total_locked, 2000);
+        }
+
+        #[ink::test]
+        fn replace_stream_with_co_payer_refunds_proportionally() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let co_payer = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 2000);
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(1000);
+            contract.top_up_stream(1).unwrap();
+            let co_payer_balance = get_balance(co_payer);
+            set_sender(payer);
+            set_value_transferred(0);
+
+            // Act
+            let new_stream_id = contract.replace_stream(1, accounts.eve, 600).unwrap();
+
+            // Assert
+            // 1500 unvested * 1000 / 2000
+            assert_eq!(get_balance(co_payer), co_payer_balance + 750);
+            let new_stream = contract.get_stream_by_id(new_stream_id).unwrap();
+            assert_eq!(new_stream.original_balance, 750);
+            assert!(contract.get_contributors(1).is_empty());
+            assert_eq!(contract.get_contribution(1, co_payer), 0);
+            assert_eq!(contract.total_locked, 750);
+        }
+
+        #[ink::test]
+        fn reclaim_expired_with_co_payer_refunds_proportionally() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let co_payer = accounts.django;
+            contract.grace_period = 300;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 4000);
+            set_now(150);
+            set_sender(co_payer);
+            set_value_transferred(3000);
+            contract.top_up_stream(1).unwrap();
+            let payer_balance = get_balance(payer);
+            let co_payer_balance = get_balance(co_payer);
+            set_sender(payer);
+
+            // Grace period ended
+            set_now(601);
+
+            // Act
+            let result = contract.reclaim_expired(1);
+
+            // Assert
+            // 4000 * 3000 / 4000
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(co_payer), co_payer_balance + 3000);
+            assert_eq!(get_balance(payer), payer_balance + 1000);
+            assert_eq!(contract.total_locked, 0);
+        }
+
+        #[ink::test]
+        fn fulfill_commitment_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, funds, 0, 300));
+            set_sender(payer);
+            contract.add_commitment(1, 150, funds).unwrap();
+            contract.add_commitment(1, 250, funds).unwrap();
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            let current_time = get_current_time_in_seconds(&contract);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.get_available_balance(current_time), Ok(funds / 2));
+
+            // Act
+            set_value_transferred(funds);
+            let current_balance = contract.fulfill_commitment(1).unwrap();
+
+            // Assert
+            assert_eq!(current_balance, 2 * funds);
+            assert_eq!(contract.get_commitments(1), vec![(250, funds)]);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.original_balance, 2 * funds);
+            // The new funds don't vest retroactively.
+            assert_eq!(stream.get_available_balance(current_time), Ok(funds / 2));
+            assert_eq!(stream.get_available_balance(225), Ok(funds + funds / 4));
+        }
+
+        #[ink::test]
+        fn fulfill_commitment_before_due_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            set_sender(payer);
+            contract.add_commitment(1, 150, 1000).unwrap();
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::NoCommitmentDue));
+        }
+
+        #[ink::test]
+        fn fulfill_commitment_with_wrong_amount_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            set_sender(payer);
+            contract.add_commitment(1, 0, 1000).unwrap();
+            set_value_transferred(999);
+
+            // Act
+            let result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::CommitmentAmountMismatch));
+            assert_eq!(contract.get_commitments(1), vec![(0, 1000)]);
+        }
+
+        #[ink::test]
+        fn add_commitment_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.add_commitment(1, 150, 1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn add_commitment_above_limit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            contract
+                .commitments
+                .insert(1, &vec![(150, 1); MAX_COMMITMENTS]);
+            set_sender(payer);
+
+            // Act
+            let result = contract.add_commitment(1, 150, 1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::TooManyCommitments));
+            assert_eq!(contract.get_commitments(1).len(), MAX_COMMITMENTS);
+        }
+
+        #[ink::test]
+        fn add_commitment_after_end_date_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            set_sender(payer);
+
+            // Act
+            let result = contract.add_commitment(1, 301, 1000);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidCommitment));
+            assert_eq!(
+                contract.add_commitment(1, 300, 1000),
+                Err(ContractError::InvalidCommitment)
+            );
+        }
+
+        #[ink::test]
+        fn fulfill_commitment_of_finished_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(payer);
+            contract.add_commitment(1, 150, 1000).unwrap();
+            set_now(300);
+            set_value_transferred(1000);
+
+            // Act
+            let result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAlreadyFinished));
+            assert_eq!(contract.get_commitments(1), vec![(150, 1000)]);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn fulfill_commitments_overflowing_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.alice, 1000, 0, 300));
+            contract
+                .commitments
+                .insert(1, &vec![(0, u128::MAX), (0, 1)]);
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            let result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+        }
+
+        #[ink::test]
+        fn fulfill_commitment_overflowing_total_locked_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let stream = Stream::new(payer, accounts.alice, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.commitments.insert(1, &vec![(100, 500)]);
+            contract.total_locked = u128::MAX;
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            set_value_transferred(500);
+            let result = contract.fulfill_commitment(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.get_commitments(1), vec![(100, 500)]);
+        }
+
+        #[ink::test]
+        fn create_stream_with_commitments_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            set_now(0);
+            set_sender(payer);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream_with_commitments(accounts.charlie, 300, vec![(150, 1000)])
+                .unwrap();
+
+            // Assert
+            assert_eq!(contract.get_commitments(stream_id), vec![(150, 1000)]);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(stream.original_balance, 1000);
+            assert_eq!(contract.total_locked, 1000);
+            set_now(150);
+            assert_eq!(contract.available_balance(stream_id), Ok(500));
+            set_value_transferred(1000);
+            assert_eq!(contract.fulfill_commitment(stream_id), Ok(2000));
+        }
+
+        #[ink::test]
+        fn create_stream_with_invalid_commitment_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_now(0);
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+
+            // Act
+            let late_result =
+                contract.create_stream_with_commitments(accounts.charlie, 300, vec![(300, 1000)]);
+            let empty_result =
+                contract.create_stream_with_commitments(accounts.charlie, 300, vec![(150, 0)]);
+            let too_many_result = contract.create_stream_with_commitments(
+                accounts.charlie,
+                300,
+                vec![(150, 1); MAX_COMMITMENTS + 1],
+            );
+
+            // Assert
+            assert_eq!(late_result, Err(ContractError::InvalidCommitment));
+            assert_eq!(empty_result, Err(ContractError::EmptyFunds));
+            assert_eq!(too_many_result, Err(ContractError::TooManyCommitments));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn replace_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let additional_funds = 1000;
+            let payer = accounts.bob;
+            let old_recipient = accounts.charlie;
+            let new_recipient = accounts.django;
+            set_sender(payer);
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(old_recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds + additional_funds);
+            let old_recipient_balance = get_balance(old_recipient);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let current_time = get_current_time_in_seconds(&contract);
+            set_value_transferred(additional_funds);
+            let new_stream_id = contract
+                .replace_stream(stream_id, new_recipient, current_time + 600)
+                .unwrap();
+
+            // Assert
+            assert_eq!(
+                get_balance(old_recipient),
+                old_recipient_balance + funds / 2
+            );
+            let old_stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert_eq!(old_stream.current_balance, 0);
+            assert_eq!(old_stream.end_date, current_time);
+            let new_stream = contract.get_stream_by_id(new_stream_id).unwrap();
+            assert_eq!(new_stream.payer, payer);
+            assert_eq!(new_stream.recipient, new_recipient);
+            assert_eq!(new_stream.original_balance, funds / 2 + additional_funds);
+            assert_eq!(new_stream.start_date, current_time);
+            assert_eq!(new_stream.end_date, current_time + 600);
+            assert_eq!(contract.total_locked, funds / 2 + additional_funds);
+        }
+
+        #[ink::test]
+        fn replace_stream_cancels_old_stream() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let old_recipient = accounts.charlie;
+            let new_recipient = accounts.django;
+            contract.max_streams_per_payer = 1;
+            contract
+                .store_new_stream(&Stream::new(payer, old_recipient, 3000000000, 0, 300))
+                .unwrap();
+            set_sender(payer);
+            contract.add_commitment(1, 150, 1000).unwrap();
+            set_sender(old_recipient);
+            contract.authorize_agent(1, accounts.eve).unwrap();
+            set_sender(payer);
+
+            // Act
+            let new_stream_id = contract.replace_stream(1, new_recipient, 600).unwrap();
+
+            // Assert
+            assert!(contract.get_stream_by_id(1).unwrap().cancelled);
+            assert_eq!(contract.get_streams_for_payer(payer), vec![new_stream_id]);
+            assert!(contract.get_streams_for_recipient(old_recipient).is_empty());
+            assert_eq!(
+                contract.get_streams_for_recipient(new_recipient),
+                vec![new_stream_id]
+            );
+            assert!(contract.get_commitments(1).is_empty());
+            assert_eq!(contract.withdraw_agents.get(1), None);
+            assert_eq!(
+                contract.replace_stream(1, new_recipient, 600),
+                Err(ContractError::StreamAlreadyCancelled)
+            );
+        }
+
+        #[ink::test]
+        fn replace_stream_emits_cancellation_events() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let new_recipient = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 3000000000, 0, 300));
+            contract.total_locked = 3000000000;
+            set_balance(get_contract_id(), 3000000000);
+            set_sender(payer);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let new_stream_id = contract.replace_stream(1, new_recipient, 600).unwrap();
+
+            // Assert
+            let events = get_recorded_events();
+            assert_eq!(events.len(), 3);
+            assert!(matches!(
+                events[0],
+                Event::StreamCancelled(StreamCancelled {
+                    stream_id: 1,
+                    recipient_paid: 1500000000,
+                    payer_refunded: 0,
+                })
+            ));
+            assert!(matches!(
+                events[1],
+                Event::StreamDrained(StreamDrained { stream_id: 1 })
+            ));
+            assert!(matches!(
+                events[2],
+                Event::StreamCreated(StreamCreated {
+                    stream_id,
+                    recipient,
+                    amount: 1500000000,
+                    ..
+                }) if stream_id == new_stream_id && recipient == new_recipient
+            ));
+        }
+
+        #[ink::test]
+        fn replace_stream_overflowing_new_stream_funds_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 3000000000;
+            set_sender(accounts.bob);
+            set_now(150);
+
+            // Act
+            set_value_transferred(u128::MAX);
+            let result = contract.replace_stream(1, accounts.django, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn replace_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.replace_stream(1, accounts.django, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn replace_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.replace_stream(1, accounts.django, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+        }
+
+        #[ink::test]
+        fn replace_stream_while_paused_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.stream_count = 1;
+            contract.total_locked = 3000000000;
+            set_sender(accounts.alice);
+            contract.set_paused(true).unwrap();
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.replace_stream(1, accounts.django, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ContractPaused));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.total_streams(), 1);
+        }
+
+        #[ink::test]
+        fn cancel_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_of_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap().recipient,
+                accounts.charlie
+            );
+        }
+
+        #[ink::test]
+        fn reduce_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reduce_stream(1, 100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn fork_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.immutable = true;
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.fork_stream(1, accounts.django, 100, 300);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamImmutable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn last_withdrawal_time_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_balance(get_contract_id(), funds);
+            set_sender(recipient);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.last_withdrawal_time(1),
+                Ok(Some(get_current_time_in_seconds(&contract)))
+            );
+        }
+
+        #[ink::test]
+        fn last_withdrawal_time_without_withdrawals_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
+            );
+
+            // Act
+            let result = contract.last_withdrawal_time(1);
+
+            // Assert
+            assert_eq!(result, Ok(None));
+        }
+
+        #[ink::test]
+        fn is_worth_withdrawing_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, funds, 0, 300));
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act & Assert
+            assert_eq!(contract.is_worth_withdrawing(1, funds / 4), Ok(true));
+            assert_eq!(contract.is_worth_withdrawing(1, funds / 2), Ok(true));
+            assert_eq!(contract.is_worth_withdrawing(1, funds / 2 + 1), Ok(false));
+        }
+
+        #[ink::test]
+        fn is_worth_withdrawing_without_available_balance_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
+            );
+
+            // Act
+            let result = contract.is_worth_withdrawing(1, 1);
+
+            // Assert
+            assert_eq!(result, Ok(false));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_wrapped_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.recipient_withdraw_wrapped(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_wrapped_with_available_balance_equal_to_zero_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, 3000000000, 0, 300));
+            set_sender(recipient);
+
+            // Act
+            let result = contract.recipient_withdraw_wrapped(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAvailableBalanceIsZero));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_wrapped_from_emptied_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let mut stream = Stream::new(accounts.charlie, recipient, 1000, 0, 300);
+            stream.current_balance = 0;
+            contract.streams.insert(1, &stream);
+            set_sender(recipient);
+            set_now(300);
+
+            // Act
+            let result = contract.recipient_withdraw_wrapped(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamFullyWithdrawn));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_wrapped_by_agent_below_existential_deposit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let agent = accounts.eve;
+            contract.set_existential_deposit(100).unwrap();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_sender(recipient);
+            contract.authorize_agent(1, agent).unwrap();
+            set_now(15);
+
+            // Act
+            set_sender(agent);
+            let result = contract.recipient_withdraw_wrapped(1, accounts.django);
+
+            // Assert
+            // Rejected before the wrapper is called, so the stream keeps its balance.
+            assert_eq!(result, Err(ContractError::AmountBelowExistential));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 1000);
+        }
+
+        #[ink::test]
+        fn stream_audit_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            let commitments: Vec<(u64, u128)> = (0..MAX_AUDIT_COMMITMENTS as u64 + 1)
+                .map(|due_date| (due_date, 1))
+                .collect();
+            contract.commitments.insert(1, &commitments);
+
+            // Act
+            let audit = contract.stream_audit(1).unwrap();
+
+            // Assert
+            assert_eq!(audit.stream, stream);
+            assert_eq!(audit.created_at, 0);
+            assert_eq!(audit.last_withdraw_at, None);
+            assert_eq!(audit.withdrawn_balance, 0);
+            assert_eq!(audit.commitments.len(), MAX_AUDIT_COMMITMENTS);
+            assert!(audit.commitments_truncated);
+            assert!(!audit.is_finished);
+        }
+
+        #[ink::test]
+        fn stream_audit_with_nonexistent_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.stream_audit(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn get_stream_details_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            let created_details = contract.get_stream_details(stream_id).unwrap();
+
+            // Half of the stream elapsed and the recipient withdraws part of it
+            for _ in 0..25000 {
+                advance_block();
+            }
+            set_sender(recipient);
+            contract.recipient_withdraw(stream_id, Some(200)).unwrap();
+            let halfway_details = contract.get_stream_details(stream_id).unwrap();
+
+            // Stream finished
+            for _ in 0..25000 {
+                advance_block();
+            }
+            let finished_details = contract.get_stream_details(stream_id).unwrap();
+
+            // Assert
+            assert_eq!(created_details.available_balance, 0);
+            assert_eq!(created_details.withdrawn_amount, 0);
+            assert!(!created_details.is_finished);
+
+            assert_eq!(halfway_details.stream.current_balance, 800);
+            assert_eq!(halfway_details.available_balance, 300);
+            assert_eq!(halfway_details.withdrawn_amount, 200);
+            assert!(!halfway_details.is_finished);
+
+            assert_eq!(finished_details.available_balance, 800);
+            assert_eq!(finished_details.withdrawn_amount, 200);
+            assert!(finished_details.is_finished);
+        }
+
+        #[ink::test]
+        fn get_stream_details_with_nonexistent_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.get_stream_details(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn get_streams_details_with_nonexistent_streams_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            for stream_id in [1, 2] {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+                );
+            }
+
+            // Act
+            let results = contract.get_streams_details(vec![1, 7, 2]);
+
+            // Assert
+            assert_eq!(results.len(), 3);
+            assert_eq!(results[0], contract.get_stream_details(1));
+            assert_eq!(results[1], Err(ContractError::StreamDoesNotExist));
+            assert_eq!(results[2], contract.get_stream_details(2));
+        }
+
+        #[ink::test]
+        fn get_streams_details_is_capped() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let results = contract.get_streams_details((1..=150).collect());
+
+            // Assert
+            assert_eq!(results.len(), MAX_PAGE_SIZE as usize);
+        }
+
+        #[ink::test]
+        fn cancel_stream_halfway_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let payer = accounts.bob;
+            let recipient = accounts.charlie;
+            set_sender(payer);
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_balance(get_contract_id(), funds);
+            let payer_balance = get_balance(payer);
+            let recipient_balance = get_balance(recipient);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let (recipient_payout, payer_refund) = contract.cancel_stream(stream_id).unwrap();
+
+            // Assert
+            assert_eq!(recipient_payout, funds / 2);
+            assert_eq!(payer_refund, funds / 2);
+            assert_eq!(get_balance(recipient), recipient_balance + funds / 2);
+            assert_eq!(get_balance(payer), payer_balance + funds / 2);
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert!(stream.cancelled);
+            assert_eq!(stream.current_balance, 0);
+            assert_eq!(contract.total_locked, 0);
+
+            set_sender(recipient);
+            assert_eq!(
+                contract.recipient_withdraw(stream_id, None),
+                Err(ContractError::StreamAlreadyCancelled)
+            );
+        }
+
+        #[ink::test]
+        fn cancel_stream_emits_stream_cancelled_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let recipient = accounts.django;
+            set_sender(payer);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let payer_balance = get_balance(payer);
+
+            // 40% of the stream elapsed
+            for _ in 0..20000 {
+                advance_block();
+            }
+
+            // Act
+            contract.cancel_stream(stream_id).unwrap();
+
+            // Assert
+            let recipient_paid = get_balance(recipient);
+            let payer_refunded = get_balance(payer) - payer_balance;
+            assert_eq!(recipient_paid, 400);
+            assert_eq!(payer_refunded, 600);
+            assert!(get_recorded_events().iter().any(|event| matches!(
+                event,
+                Event::StreamCancelled(cancelled)
+                    if cancelled.stream_id == stream_id
+                        && cancelled.recipient_paid == recipient_paid
+                        && cancelled.payer_refunded == payer_refunded
+            )));
+        }
+
+        #[ink::test]
+        fn cancel_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.alice);
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::OnlyPayerCanCancel));
+        }
+
+        fn assert_renounce_split(blocks: u32, expected_recipient_paid: u128) {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let recipient = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, 1000, 0, 300));
+            let payer_balance = get_balance(payer);
+            for _ in 0..blocks {
+                advance_block();
+            }
+            set_sender(recipient);
+
+            // Act
+            let result = contract.renounce_stream(1);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+            let recipient_paid = get_balance(recipient);
+            let payer_refunded = get_balance(payer) - payer_balance;
+            assert_eq!(recipient_paid, expected_recipient_paid);
+            assert_eq!(payer_refunded, 1000 - expected_recipient_paid);
+            assert!(contract.streams.get(1).unwrap().cancelled);
+            assert!(get_recorded_events().iter().any(|event| matches!(
+                event,
+                Event::StreamRenounced(renounced)
+                    if renounced.stream_id == 1
+                        && renounced.recipient_paid == recipient_paid
+                        && renounced.payer_refunded == payer_refunded
+            )));
+        }
+
+        #[ink::test]
+        fn renounce_stream_before_start_refunds_everything_to_payer() {
+            assert_renounce_split(0, 0);
+        }
+
+        #[ink::test]
+        fn renounce_stream_halfway_splits_balance() {
+            // 50% of the stream elapsed
+            assert_renounce_split(25000, 500);
+        }
+
+        #[ink::test]
+        fn renounce_stream_after_end_pays_everything_to_recipient() {
+            assert_renounce_split(50000, 1000);
+        }
+
+        #[ink::test]
+        fn renounce_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.django, 1000, 0, 300));
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.renounce_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn cancel_stream_already_cancelled_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+            set_sender(accounts.bob);
+            contract.cancel_stream(1).unwrap();
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAlreadyCancelled));
+        }
+
+        #[ink::test]
+        fn cancel_non_cancellable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            set_sender(payer);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(
+                    accounts.charlie,
+                    None,
+                    Some(300),
+                    StreamOptions {
+                        cancellable: false,
+                        ..StreamOptions::default()
+                    },
+                )
+                .unwrap();
+
+            // Act
+            let cancel_result = contract.cancel_stream(stream_id);
+            let reduce_result = contract.reduce_stream(stream_id, 100);
+
+            // Assert
+            assert_eq!(cancel_result, Err(ContractError::StreamNotCancellable));
+            assert_eq!(reduce_result, Err(ContractError::StreamNotCancellable));
+            let stream = contract.get_stream_by_id(stream_id).unwrap();
+            assert!(!stream.cancellable);
+            assert!(!stream.cancelled);
+            assert_eq!(stream.current_balance, 1000);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_of_non_cancellable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.cancellable = false;
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_sender(accounts.bob);
+            set_now(150);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamNotCancellable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn set_withdraw_cooldown_of_non_cancellable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.cancellable = false;
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_withdraw_cooldown(1, 60);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamNotCancellable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().withdraw_cooldown, 0);
+        }
+
+        #[ink::test]
+        fn extend_non_cancellable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            stream.cancellable = false;
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.extend_stream(1, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamNotCancellable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().end_date, 300);
+        }
+
+        #[ink::test]
+        fn create_stream_is_cancellable_by_default() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.bob);
+            set_value_transferred(1000);
+
+            // Act
+            let stream_id = contract
+                .create_stream_units(accounts.charlie, 5, TimeUnit::Minutes)
+                .unwrap();
+
+            // Assert
+            assert!(contract.get_stream_by_id(stream_id).unwrap().cancellable);
+        }
+
+        #[ink::test]
+        fn close_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            set_sender(recipient);
+            contract.recipient_withdraw(stream_id, None).unwrap();
+
+            // Act
+            contract.close_stream(stream_id).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_stream_by_id(stream_id),
+                Err(ContractError::StreamDoesNotExist)
+            );
+            assert!(contract.get_streams_for_recipient(recipient).is_empty());
+            assert!(contract.withdrawal_history(stream_id).is_empty());
+        }
+
+        #[ink::test]
+        fn close_stream_with_balance_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.close_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamNotEmpty));
+            assert!(contract.get_stream_by_id(1).is_ok());
+        }
+
+        #[ink::test]
+        fn close_stream_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.charlie, 0, 0, 300));
+            set_sender(accounts.django);
+
+            // Act
+            let result = contract.close_stream(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let old_recipient = accounts.bob;
+            let new_recipient = accounts.charlie;
+            set_sender(accounts.alice);
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(old_recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            contract
+                .reassign_recipient(stream_id, new_recipient)
+                .unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_stream_by_id(stream_id).unwrap().recipient,
+                new_recipient
+            );
+            assert!(contract.get_streams_for_recipient(old_recipient).is_empty());
+            assert_eq!(
+                contract.get_streams_for_recipient(new_recipient),
+                vec![stream_id]
+            );
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            set_sender(old_recipient);
+            assert_eq!(
+                contract.recipient_withdraw(stream_id, None),
+                Err(ContractError::Unauthorized)
+            );
+            set_sender(new_recipient);
+            assert_eq!(contract.recipient_withdraw(stream_id, None), Ok(funds));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_pays_vested_balance_to_old_recipient() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let old_recipient = accounts.bob;
+            let new_recipient = accounts.django;
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, old_recipient, 1000, 0, 300),
+            );
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            let old_recipient_balance = get_balance(old_recipient);
+            set_sender(accounts.charlie);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            contract.reassign_recipient(1, new_recipient).unwrap();
+
+            // Assert
+            assert_eq!(get_balance(old_recipient), old_recipient_balance + 500);
+            assert_eq!(contract.available_balance(1), Ok(0));
+            assert_eq!(contract.total_locked, 500);
+
+            // Stream finished
+            set_now(300);
+            set_sender(new_recipient);
+            assert_eq!(contract.recipient_withdraw(1, None), Ok(500));
+            assert_eq!(get_balance(new_recipient), 500);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_ignores_withdraw_cooldown() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let old_recipient = accounts.bob;
+            let new_recipient = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, old_recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_balance(get_contract_id(), 3000);
+            set_sender(payer);
+            contract.set_withdraw_cooldown(1, 60).unwrap();
+            set_sender(old_recipient);
+            set_now(100);
+            contract.recipient_withdraw(1, None).unwrap();
+            let old_recipient_balance = get_balance(old_recipient);
+            set_now(150);
+            set_sender(payer);
+
+            // Act
+            let result = contract.reassign_recipient(1, new_recipient);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+            assert_eq!(get_balance(old_recipient), old_recipient_balance + 500);
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.recipient, new_recipient);
+            assert_eq!(stream.last_withdraw_at, Some(150));
+            assert_eq!(contract.total_locked, 1500);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_while_withdrawing_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            set_sender(accounts.charlie);
+            set_now(150);
+
+            // A re-entrant call sees the lock held by the outer withdrawal
+            contract.reentrancy_lock.insert((), &true);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Reentrancy));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_under_collateralized_emits_solvency_warning_event() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            for (stream_id, recipient) in [(1, accounts.bob), (2, accounts.charlie)] {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.django, recipient, funds, 0, 300),
+                );
+            }
+            contract.total_locked = 2 * funds;
+            set_balance(get_contract_id(), 1200);
+            set_sender(accounts.django);
+
+            // 50% of the streams elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.eve);
+
+            // Assert
+            assert_eq!(result, Ok(()));
+            assert!(get_recorded_events().iter().any(|event| matches!(
+                event,
+                Event::SolvencyWarning(warning)
+                    if warning.held == 700 && warning.outstanding == 1500
+            )));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_while_paused_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 1000;
+            set_sender(accounts.alice);
+            contract.set_paused(true).unwrap();
+            set_sender(accounts.charlie);
+            set_now(150);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ContractPaused));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_to_payer_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.bob);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientCannotBePayer));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_to_zero_account_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reassign_recipient(1, AccountId::from([0u8; 32]));
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidRecipient));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_to_contract_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            contract.streams.insert(1, &stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.reassign_recipient(1, get_contract_id());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidRecipient));
+            assert_eq!(contract.get_stream_by_id(1).unwrap(), stream);
+        }
+
+        #[ink::test]
+        fn reassign_recipient_charges_fee_and_records_payout() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let owner = accounts.django;
+            let old_recipient = accounts.bob;
+            contract.owner = owner;
+            set_sender(owner);
+            contract.set_fee_bps(250).unwrap();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.charlie, old_recipient, 1000, 0, 300),
+            );
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            let owner_balance = get_balance(owner);
+            let old_recipient_balance = get_balance(old_recipient);
+            set_sender(accounts.charlie);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            contract.reassign_recipient(1, accounts.eve).unwrap();
+
+            // Assert
+            assert_eq!(get_balance(old_recipient), old_recipient_balance + 488);
+            assert_eq!(get_balance(owner), owner_balance + 12);
+            assert_eq!(contract.withdrawal_history(1), vec![(150, 500)]);
+            assert_eq!(contract.total_withdrawn_volume(), 500);
+            assert_eq!(
+                contract.get_stream_by_id(1).unwrap().last_withdraw_at,
+                Some(150)
+            );
+            let events = get_recorded_events();
+            assert!(matches!(
+                events.last(),
+                Some(Event::Withdrawn(Withdrawn {
+                    stream_id: 1,
+                    recipient,
+                    amount: 488,
+                    fee: 12,
+                })) if *recipient == old_recipient
+            ));
+        }
+
+        #[ink::test]
+        fn reassign_recipient_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.reassign_recipient(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn transfer_payer_role_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let old_payer = accounts.alice;
+            let new_payer = accounts.charlie;
+            set_sender(old_payer);
+            set_value_transferred(funds);
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            contract.transfer_payer_role(stream_id, new_payer).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_stream_by_id(stream_id).unwrap().payer,
+                new_payer
+            );
+            assert!(contract.get_streams_for_payer(old_payer).is_empty());
+            assert_eq!(contract.get_streams_for_payer(new_payer), vec![stream_id]);
+
+            set_sender(old_payer);
+            assert_eq!(
+                contract.top_up_stream(stream_id),
+                Err(ContractError::Unauthorized)
+            );
+            assert_eq!(
+                contract.cancel_stream(stream_id),
+                Err(ContractError::OnlyPayerCanCancel)
+            );
+            set_sender(new_payer);
+            assert_eq!(contract.top_up_stream(stream_id), Ok(2 * funds));
+            assert_eq!(contract.cancel_stream(stream_id), Ok((0, 2 * funds)));
+        }
+
+        #[ink::test]
+        fn transfer_payer_role_to_recipient_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.transfer_payer_role(1, accounts.charlie);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::RecipientCannotBePayer));
+        }
+
+        #[ink::test]
+        fn transfer_payer_role_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.transfer_payer_role(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn set_payout_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            contract.set_payout_address(1, accounts.django).unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.payout_address, Some(accounts.django));
+        }
+
+        #[ink::test]
+        fn set_payout_address_to_recipient_clears_it() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+            contract.set_payout_address(1, accounts.django).unwrap();
+
+            // Act
+            contract.set_payout_address(1, accounts.charlie).unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.payout_address, None);
+        }
+
+        #[ink::test]
+        fn set_payout_address_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            let result = contract.set_payout_address(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+        }
+
+        #[ink::test]
+        fn set_refund_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+
+            // Act
+            contract.set_refund_address(1, accounts.django).unwrap();
+            let refund_to = contract.get_stream_by_id(1).unwrap().refund_to;
+            contract.set_refund_address(1, accounts.bob).unwrap();
+
+            // Assert
+            assert_eq!(refund_to, Some(accounts.django));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().refund_to, None);
+        }
+
+        #[ink::test]
+        fn set_refund_address_with_unauthorized_wallet_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.set_refund_address(1, accounts.django);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().refund_to, None);
+        }
+
+        #[ink::test]
+        fn set_refund_address_of_cancelled_or_immutable_stream_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut cancelled_stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            cancelled_stream.cancelled = true;
+            contract.streams.insert(1, &cancelled_stream);
+            let mut immutable_stream = Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300);
+            immutable_stream.immutable = true;
+            contract.streams.insert(2, &immutable_stream);
+            set_sender(accounts.bob);
+
+            // Act
+            let cancelled_result = contract.set_refund_address(1, accounts.django);
+            let immutable_result = contract.set_refund_address(2, accounts.django);
+
+            // Assert
+            assert_eq!(cancelled_result, Err(ContractError::StreamAlreadyCancelled));
+            assert_eq!(immutable_result, Err(ContractError::StreamImmutable));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().refund_to, None);
+            assert_eq!(contract.get_stream_by_id(2).unwrap().refund_to, None);
+        }
+
+        #[ink::test]
+        fn replace_stream_keeps_refund_address() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300);
+            stream.refund_to = Some(accounts.eve);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 3000000000;
+            set_sender(accounts.bob);
+
+            // Act
+            let new_stream_id = contract.replace_stream(1, accounts.django, 600).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_stream_by_id(new_stream_id).unwrap().refund_to,
+                Some(accounts.eve)
+            );
+        }
+
+        #[ink::test]
+        fn replace_stream_keeps_stream_settings() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 3000000000, 0, 300);
+            stream.memo = Some([7; 32]);
+            stream.token_decimals = 18;
+            stream.withdraw_cooldown = 60;
+            stream.rounding = Rounding::HalfUp;
+            stream.cliff_date = 100;
+            stream.completion_bonus = 1000000000;
+            stream.payout_address = Some(accounts.eve);
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 3000000000;
+            set_sender(accounts.bob);
+
+            // Act
+            let new_stream_id = contract.replace_stream(1, accounts.django, 600).unwrap();
+
+            // Assert
+            let new_stream = contract.get_stream_by_id(new_stream_id).unwrap();
+            assert_eq!(new_stream.memo, Some([7; 32]));
+            assert_eq!(new_stream.token_decimals, 18);
+            assert_eq!(new_stream.withdraw_cooldown, 60);
+            assert_eq!(new_stream.rounding, Rounding::HalfUp);
+            assert!(!new_stream.immutable);
+            assert!(new_stream.cancellable);
+            assert_eq!(new_stream.cliff_date, new_stream.start_date);
+            assert_eq!(new_stream.completion_bonus, 0);
+            assert_eq!(new_stream.vesting, VestingKind::Linear);
+            assert_eq!(new_stream.payout_address, None);
+            assert_eq!(new_stream.original_balance, 3000000000);
+        }
+
+        #[ink::test]
+        fn replace_stream_above_stream_limit_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            for _ in 0..2 {
+                contract
+                    .store_new_stream(&Stream::new(payer, accounts.charlie, 3000000000, 0, 300))
+                    .unwrap();
+            }
+            contract.max_streams_per_payer = 1;
+            set_sender(payer);
+
+            // Act
+            let result = contract.replace_stream(1, accounts.django, 600);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamLimitReached));
+            assert!(!contract.get_stream_by_id(1).unwrap().cancelled);
+            assert_eq!(contract.get_streams_for_payer(payer), vec![1, 2]);
+        }
+
+        #[ink::test]
+        fn cancel_stream_with_refund_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let recipient = accounts.charlie;
+            let treasury = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            set_sender(payer);
+            contract.set_refund_address(1, treasury).unwrap();
+            let payer_balance = get_balance(payer);
+            let recipient_balance = get_balance(recipient);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            assert_eq!(result, Ok((500, 500)));
+            assert_eq!(get_balance(treasury), 500);
+            assert_eq!(get_balance(payer), payer_balance);
+            assert_eq!(get_balance(recipient), recipient_balance + 500);
+        }
+
+        #[ink::test]
+        fn reduce_stream_with_refund_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let treasury = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            set_sender(payer);
+            contract.set_refund_address(1, treasury).unwrap();
+            let payer_balance = get_balance(payer);
+            set_now(150);
+
+            // Act
+            let result = contract.reduce_stream(1, 200);
+
+            // Assert
+            assert_eq!(result, Ok(200));
+            assert_eq!(get_balance(treasury), 200);
+            assert_eq!(get_balance(payer), payer_balance);
+        }
+
+        #[ink::test]
+        fn reclaim_expired_with_refund_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let treasury = accounts.django;
+            contract.grace_period = 0;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            contract.total_locked = 1000;
+            set_balance(get_contract_id(), 1000);
+            set_sender(payer);
+            contract.set_refund_address(1, treasury).unwrap();
+            let payer_balance = get_balance(payer);
+            set_now(301);
+
+            // Act
+            let result = contract.reclaim_expired(1);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(get_balance(treasury), 1000);
+            assert_eq!(get_balance(payer), payer_balance);
+        }
+
+        #[ink::test]
+        fn transfer_payer_role_clears_refund_address() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.bob);
+            contract.set_refund_address(1, accounts.django).unwrap();
+
+            // Act
+            contract.transfer_payer_role(1, accounts.eve).unwrap();
+
+            // Assert
+            let stream = contract.get_stream_by_id(1).unwrap();
+            assert_eq!(stream.refund_to, None);
+            assert_eq!(stream.refund_destination(), accounts.eve);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_to_payout_address_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 1000;
+            let recipient = accounts.charlie;
+            let payout_address = accounts.django;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, funds, 0, 300));
+            set_sender(recipient);
+            contract.set_payout_address(1, payout_address).unwrap();
+            let recipient_balance = get_balance(recipient);
+            let payout_address_balance = get_balance(payout_address);
+
+            // Stream finished
+            set_now(300);
+
+            // Act
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Assert
+            assert_eq!(get_balance(payout_address), payout_address_balance + funds);
+            assert_eq!(get_balance(recipient), recipient_balance);
+        }
+
+        #[ink::test]
+        fn available_balance_of_new_stream_is_zero() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Act
+            let available_balance = contract.available_balance(1).unwrap();
+
+            // Assert
+            assert_eq!(available_balance, 0);
+        }
+
+        #[ink::test]
+        fn available_balance_halfway_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let available_balance = contract.available_balance(1).unwrap();
+
+            // Assert
+            assert_eq!(available_balance, 500);
+        }
+
+        #[ink::test]
+        fn available_balance_of_finished_stream_is_current_balance() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.alice, 1000, 0, 300);
+            stream.withdraw(200).unwrap();
+            contract.streams.insert(1, &stream);
+
+            // Stream finished
+            for _ in 0..50001 {
+                advance_block();
+            }
+
+            // Act
+            let available_balance = contract.available_balance(1).unwrap();
+
+            // Assert
+            assert_eq!(available_balance, 800);
+        }
+
+        #[ink::test]
+        fn can_withdraw_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            let before_start = contract.can_withdraw(1, accounts.charlie);
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let recipient = contract.can_withdraw(1, accounts.charlie);
+            let payer = contract.can_withdraw(1, accounts.bob);
+            let non_existent_stream = contract.can_withdraw(2, accounts.charlie);
+
+            // Assert
+            assert!(!before_start);
+            assert!(recipient);
+            assert!(!payer);
+            assert!(!non_existent_stream);
+        }
+
+        #[ink::test]
+        fn can_withdraw_while_paused_is_false() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+            set_sender(accounts.alice);
+            contract.set_paused(true).unwrap();
+
+            // Half of the stream elapsed
+            set_now(150);
+
+            // Act
+            let result = contract.can_withdraw(1, accounts.charlie);
+
+            // Assert
+            assert!(!result);
+        }
+
+        #[ink::test]
+        fn can_withdraw_on_cooldown_is_false() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.charlie;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, recipient, 3000, 0, 300));
+            contract.total_locked = 3000;
+            set_balance(get_contract_id(), 3000);
+            set_sender(accounts.bob);
+            contract.set_withdraw_cooldown(1, 60).unwrap();
+            set_sender(recipient);
+            set_now(100);
+            contract.recipient_withdraw(1, None).unwrap();
+            set_now(150);
+            let on_cooldown = contract.can_withdraw(1, recipient);
+
+            // Act
+            set_now(160);
+            let after_cooldown = contract.can_withdraw(1, recipient);
+
+            // Assert
+            assert!(!on_cooldown);
+            assert!(after_cooldown);
+        }
+
+        #[ink::test]
+        fn vested_at_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 1000, 100, 400),
+            );
+
+            // Act
+            let at_start = contract.vested_at(1, 100);
+            let at_midpoint = contract.vested_at(1, 250);
+            let past_end = contract.vested_at(1, 1000);
+
+            // Assert
+            assert_eq!(at_start, Ok(0));
+            assert_eq!(at_midpoint, Ok(500));
+            assert_eq!(past_end, Ok(1000));
+        }
+
+        #[ink::test]
+        fn vested_at_of_unexisting_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.vested_at(1, 100);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn is_stream_finished_of_active_stream_is_false() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.is_stream_finished(1);
+
+            // Assert
+            assert_eq!(result, Ok(false));
+        }
+
+        #[ink::test]
+        fn is_stream_finished_of_finished_stream_is_true() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.is_stream_finished(1);
+
+            // Assert
+            assert_eq!(result, Ok(true));
+        }
+
+        #[ink::test]
+        fn is_stream_finished_of_unexisting_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.is_stream_finished(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn stream_status_of_pending_stream_is_pending() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 1000, 300, 600),
+            );
+
+            // Act
+            let result = contract.stream_status(1);
+
+            // Assert
+            assert_eq!(result, Ok(StreamStatus::Pending));
+        }
+
+        #[ink::test]
+        fn stream_status_of_active_stream_is_active() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Half of the stream elapsed
+            for _ in 0..25000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.stream_status(1);
+
+            // Assert
+            assert_eq!(result, Ok(StreamStatus::Active));
+        }
+
+        #[ink::test]
+        fn stream_status_of_finished_stream_is_finished() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+
+            // Act
+            let result = contract.stream_status(1);
+
+            // Assert
+            assert_eq!(result, Ok(StreamStatus::Finished));
+        }
+
+        #[ink::test]
+        fn stream_status_of_fully_withdrawn_stream_is_fully_withdrawn() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.alice, accounts.bob, 1000, 0, 300));
+
+            // Stream finished
+            set_now(300);
+            set_sender(accounts.bob);
+            contract.recipient_withdraw(1, None).unwrap();
+
+            // Act
+            let result = contract.stream_status(1);
+
+            // Assert
+            assert_eq!(result, Ok(StreamStatus::FullyWithdrawn));
+        }
+
+        #[ink::test]
+        fn stream_status_of_cancelled_stream_is_cancelled() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract.cancel_stream(stream_id).unwrap();
+
+            // Act
+            let result = contract.stream_status(stream_id);
+
+            // Assert
+            assert_eq!(result, Ok(StreamStatus::Cancelled));
+        }
+
+        #[ink::test]
+        fn withdrawn_amount_of_new_stream_is_zero() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
+
+            // Act
+            let withdrawn_amount = contract.withdrawn_amount(1);
+
+            // Assert
+            assert_eq!(withdrawn_amount, Ok(0));
+        }
+
+        #[ink::test]
+        fn withdrawn_amount_after_partial_withdrawal_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Half of the stream elapsed
+            set_now(150);
+            set_sender(recipient);
+            contract.recipient_withdraw(stream_id, Some(300)).unwrap();
+
+            // Act
+            let withdrawn_amount = contract.withdrawn_amount(stream_id);
+
+            // Assert
+            assert_eq!(withdrawn_amount, Ok(300));
+        }
+
+        #[ink::test]
+        fn withdrawn_amount_after_full_withdrawal_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            set_sender(accounts.charlie);
+            set_value_transferred(1000);
+            let stream_id = contract
+                .create_stream(recipient, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Stream finished
+            set_now(300);
+            set_sender(recipient);
+            contract.recipient_withdraw(stream_id, None).unwrap();
+
+            // Act
+            let withdrawn_amount = contract.withdrawn_amount(stream_id);
+
+            // Assert
+            assert_eq!(withdrawn_amount, Ok(1000));
+        }
+
+        #[ink::test]
+        fn withdrawn_amount_of_unexisting_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.withdrawn_amount(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn total_streams_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            assert_eq!(contract.total_streams(), 0);
+            assert_eq!(contract.peek_next_stream_id(), 1);
+
+            // Act
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            assert_eq!(contract.total_streams(), 1);
+            assert_eq!(contract.peek_next_stream_id(), 2);
+
+            for _ in 0..3 {
+                contract
+                    .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                    .unwrap();
+            }
+
+            // Assert
+            assert_eq!(contract.total_streams(), 4);
+            assert_eq!(contract.peek_next_stream_id(), 5);
+        }
+
+        #[ink::test]
+        fn get_streams_for_recipient_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+
+            // Act
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let second_stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let third_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_streams_for_recipient(accounts.bob),
+                vec![first_stream_id, third_stream_id]
+            );
+            assert_eq!(
+                contract.get_streams_for_recipient(accounts.charlie),
+                vec![second_stream_id]
+            );
+            assert!(contract
+                .get_streams_for_recipient(accounts.django)
+                .is_empty());
+        }
+
+        #[ink::test]
+        fn list_streams_skips_closed_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            for stream_id in 1..=3 {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.alice, accounts.bob, 1000, 0, 300),
+                );
+            }
+
+            // Stream finished
+            for _ in 0..50000 {
+                advance_block();
+            }
+            set_sender(accounts.bob);
+            contract.recipient_withdraw(2, None).unwrap();
+            contract.close_stream(2).unwrap();
+
+            // Act
+            let stream_ids = contract.list_streams(1, 10);
+
+            // Assert
+            assert_eq!(stream_ids, vec![1, 3]);
+            assert_eq!(contract.list_streams(2, 1), Vec::<u64>::new());
+        }
+
+        #[ink::test]
+        fn list_streams_caps_limit() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            for stream_id in 1..=150 {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.alice, accounts.bob, 1000, 0, 300),
+                );
+            }
+
+            // Act
+            let stream_ids = contract.list_streams(1, u64::MAX);
+
+            // Assert
+            assert_eq!(stream_ids.len(), MAX_PAGE_SIZE as usize);
+            assert_eq!(stream_ids.last(), Some(&100));
+        }
+
+        #[ink::test]
+        fn total_available_for_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .store_new_stream(&Stream::new(accounts.charlie, recipient, 1000, 0, 100))
+                .unwrap();
+            contract
+                .store_new_stream(&Stream::new(accounts.charlie, recipient, 3000, 0, 300))
+                .unwrap();
+            contract
+                .store_new_stream(&Stream::new(accounts.charlie, recipient, 1000, 200, 500))
+                .unwrap();
+            contract
+                .store_new_stream(&Stream::new(
+                    accounts.charlie,
+                    accounts.django,
+                    1000,
+                    0,
+                    100,
+                ))
+                .unwrap();
+            set_now(150);
+
+            // Act
+            let result = contract.total_available_for(recipient);
+
+            // Assert
+            assert_eq!(result, 2500);
+            assert_eq!(contract.total_available_for(accounts.eve), 0);
+        }
+
+        #[ink::test]
+        fn get_streams_for_payer_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let second_stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_sender(accounts.bob);
+            let other_payer_stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+            set_sender(accounts.alice);
+            let third_stream_id = contract
+                .create_stream(accounts.django, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            let payer_streams = contract.get_streams_for_payer(accounts.alice);
+
+            // Assert
+            assert_eq!(
+                payer_streams,
+                vec![first_stream_id, second_stream_id, third_stream_id]
+            );
+            assert_eq!(
+                contract.get_streams_for_payer(accounts.bob),
+                vec![other_payer_stream_id]
+            );
+            assert!(contract.get_streams_for_payer(accounts.django).is_empty());
+        }
+
+        #[ink::test]
+        fn streams_involving_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let who = accounts.bob;
+            for stream in [
+                Stream::new(who, accounts.charlie, 1000, 0, 300),
+                Stream::new(accounts.charlie, who, 1000, 0, 300),
+                Stream::new(accounts.charlie, accounts.django, 1000, 0, 300),
+                Stream::new(who, accounts.django, 1000, 0, 300),
+                Stream::new(who, who, 1000, 0, 300),
+            ] {
+                contract.store_new_stream(&stream).unwrap();
+            }
+
+            // Act
+            let stream_ids = contract.streams_involving(who);
+
+            // Assert
+            assert_eq!(stream_ids, vec![1, 2, 4, 5]);
+            assert!(contract.streams_involving(accounts.eve).is_empty());
+        }
+
+        #[ink::test]
+        fn streams_involving_by_role_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let who = accounts.bob;
+            for stream in [
+                Stream::new(who, accounts.charlie, 1000, 0, 300),
+                Stream::new(accounts.charlie, who, 1000, 0, 300),
+                Stream::new(accounts.charlie, accounts.django, 1000, 0, 300),
+                Stream::new(who, accounts.django, 1000, 0, 300),
+            ] {
+                contract.store_new_stream(&stream).unwrap();
+            }
+
+            // Act
+            let (as_payer, as_recipient) = contract.streams_involving_by_role(who);
+
+            // Assert
+            assert_eq!(as_payer, vec![1, 4]);
+            assert_eq!(as_recipient, vec![2]);
+            assert_eq!(
+                contract.streams_involving_by_role(accounts.eve),
+                (vec![], vec![])
+            );
+        }
+
+        #[ink::test]
+        fn streams_involving_by_role_keeps_latest_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let who = accounts.bob;
+            for _ in 0..MAX_PAGE_SIZE + 2 {
+                contract
+                    .store_new_stream(&Stream::new(who, accounts.charlie, 1000, 0, 300))
+                    .unwrap();
+            }
+
+            // Act
+            let (as_payer, as_recipient) = contract.streams_involving_by_role(who);
+
+            // Assert
+            assert_eq!(as_payer.len(), MAX_PAGE_SIZE as usize);
+            assert_eq!(as_payer[0], 3);
+            assert_eq!(as_payer.last(), Some(&(MAX_PAGE_SIZE + 2)));
+            assert!(as_recipient.is_empty());
+        }
+
+        #[ink::test]
+        fn get_streams_for_payer_excludes_cancelled_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let second_stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            contract.cancel_stream(first_stream_id).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_streams_for_payer(accounts.alice),
+                vec![second_stream_id]
+            );
+        }
+
+        #[ink::test]
+        fn create_stream_over_payer_limit_fails() {
+            // Arrange
+            let mut contract = StreamsContract::new_with_max_streams_per_payer(2);
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            for _ in 0..2 {
+                contract
+                    .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                    .unwrap();
+            }
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamLimitReached));
+            assert_eq!(contract.get_streams_for_payer(accounts.alice).len(), 2);
+        }
+
+        #[ink::test]
+        fn create_streams_batch_over_payer_limit_fails() {
+            // Arrange
+            let mut contract = StreamsContract::new_with_max_streams_per_payer(2);
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            set_value_transferred(300);
+
+            // Act
+            let result = contract.create_streams_batch(
+                vec![
+                    (accounts.bob, None, Some(300)),
+                    (accounts.charlie, None, Some(300)),
+                    (accounts.django, None, Some(300)),
+                ],
+                vec![100, 100, 100],
+            );
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamLimitReached));
+            assert_eq!(contract.total_streams(), 0);
+        }
+
+        #[ink::test]
+        fn cancel_all_streams_skips_finished_and_immutable_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.charlie;
+            let mut immutable_stream = Stream::new(payer, accounts.bob, 1000, 0, 300);
+            immutable_stream.immutable = true;
+            for stream in [
+                Stream::new(payer, accounts.bob, 1000, 0, 300),
+                Stream::new(payer, accounts.bob, 1000, 0, 100),
+                Stream::new(payer, accounts.django, 600, 0, 300),
+                immutable_stream,
+                Stream::new(accounts.bob, accounts.django, 1000, 0, 300),
+            ] {
+                contract.store_new_stream(&stream).unwrap();
+            }
+            set_balance(get_contract_id(), 4600);
+            let recipient_balance = get_balance(accounts.django);
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            let result = contract.cancel_all_streams();
+
+            // Assert
+            assert_eq!(result, Ok(vec![(1, 500), (3, 300)]));
+            assert!(contract.get_stream_by_id(1).unwrap().cancelled);
+            assert!(contract.get_stream_by_id(3).unwrap().cancelled);
+            assert!(!contract.get_stream_by_id(2).unwrap().cancelled);
+            assert!(!contract.get_stream_by_id(4).unwrap().cancelled);
+            assert!(!contract.get_stream_by_id(5).unwrap().cancelled);
+            assert_eq!(get_balance(accounts.django), recipient_balance + 300);
+            assert_eq!(contract.get_streams_for_payer(payer), vec![2, 4]);
+            assert_eq!(contract.total_locked, 3000);
+        }
+
+        #[ink::test]
+        fn cancel_all_streams_without_streams_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .store_new_stream(&Stream::new(accounts.bob, accounts.django, 1000, 0, 300))
+                .unwrap();
+            set_sender(accounts.charlie);
+
+            // Act
+            let result = contract.cancel_all_streams();
+
+            // Assert
+            assert_eq!(result, Ok(vec![]));
+            assert_eq!(contract.total_locked, 1000);
+        }
+
+        #[ink::test]
+        fn indexes_stay_consistent_across_stream_lifecycle() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let all_accounts = [
+                accounts.bob,
+                accounts.charlie,
+                accounts.django,
+                accounts.eve,
+                accounts.frank,
+            ];
+            for stream in [
+                Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+                Stream::new(accounts.bob, accounts.django, 1000, 0, 300),
+                Stream::new(accounts.bob, accounts.eve, 1000, 0, 100),
+                Stream::new(accounts.charlie, accounts.bob, 1000, 0, 300),
+            ] {
+                contract.store_new_stream(&stream).unwrap();
+            }
+            set_balance(get_contract_id(), 4000);
+            assert_indexes_consistent(&contract, &all_accounts);
+            set_now(150);
+
+            // Act
+            set_sender(accounts.bob);
+            contract.reassign_recipient(1, accounts.frank).unwrap();
+            assert_indexes_consistent(&contract, &all_accounts);
+            contract.transfer_payer_role(2, accounts.charlie).unwrap();
+            assert_indexes_consistent(&contract, &all_accounts);
+
+            set_sender(accounts.charlie);
+            contract.cancel_stream(2).unwrap();
+            assert_indexes_consistent(&contract, &all_accounts);
+            contract.cancel_stream(4).unwrap();
+            assert_indexes_consistent(&contract, &all_accounts);
+
+            set_sender(accounts.eve);
+            contract.recipient_withdraw(3, None).unwrap();
+            contract.close_stream(3).unwrap();
+
+            // Assert
+            assert_indexes_consistent(&contract, &all_accounts);
+            assert_eq!(contract.get_streams_for_payer(accounts.bob), vec![1]);
+            assert_eq!(contract.get_streams_for_recipient(accounts.frank), vec![1]);
+            assert!(contract.get_streams_for_payer(accounts.charlie).is_empty());
+            assert!(contract.get_streams_for_recipient(accounts.eve).is_empty());
+        }
+
+        #[ink::test]
+        fn cancel_stream_frees_payer_slot() {
+            // Arrange
+            let mut contract = StreamsContract::new_with_max_streams_per_payer(2);
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract.cancel_stream(first_stream_id).unwrap();
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn close_stream_frees_payer_slot() {
+            // Arrange
+            let mut contract = StreamsContract::new_with_max_streams_per_payer(2);
+            let accounts = get_default_accounts();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            for _ in 0..50000 {
+                advance_block();
+            }
+            set_sender(accounts.bob);
+            contract.recipient_withdraw(first_stream_id, None).unwrap();
+            set_sender(accounts.alice);
+            contract.close_stream(first_stream_id).unwrap();
+
+            // Act
+            let result =
+                contract.create_stream(accounts.bob, None, Some(300), StreamOptions::default());
+
+            // Assert
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn get_streams_for_recipient_excludes_cancelled_streams() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            set_sender(accounts.alice);
+            set_value_transferred(1000);
+            let first_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+            let second_stream_id = contract
+                .create_stream(accounts.bob, None, Some(300), StreamOptions::default())
+                .unwrap();
+
+            // Act
+            contract.cancel_stream(first_stream_id).unwrap();
+
+            // Assert
+            assert_eq!(
+                contract.get_streams_for_recipient(accounts.bob),
+                vec![second_stream_id]
+            );
+        }
+
+        #[ink::test]
+        fn get_available_balance_before_stream_start_fails() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let stream = Stream::new(accounts.bob, accounts.alice, 1000, 1000, 1300);
+
+            // Act
+            let result = stream.get_available_balance(500);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamAvailableBalanceIsZero));
+        }
+
+        #[ink::test]
+        fn get_available_balance_at_end_date_works() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let mut stream = Stream::new(accounts.bob, accounts.alice, 1000, 0, 300);
+            stream.withdraw(200).unwrap();
+
+            // Act
+            let result = stream.get_available_balance(300);
+
+            // Assert
+            assert!(stream.is_finished(300));
+            assert!(!stream.is_finished(299));
+            assert_eq!(result, Ok(800));
+        }
+
+        #[ink::test]
+        fn get_available_balance_of_zero_duration_stream_works() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let stream = Stream::new(accounts.bob, accounts.alice, 1000, 1000, 1000);
+
+            // Act
+            let result = stream.get_available_balance(1001);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+            assert_eq!(stream.vested_balance(1000), Ok(1000));
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_from_zero_duration_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.charlie, recipient, 1000, 0, 0));
+            set_sender(recipient);
+
+            // Act
+            let result_at_start = contract.recipient_withdraw(1, None);
+            set_now(6);
+            let result_after_start = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(
+                result_at_start,
+                Err(ContractError::StreamAvailableBalanceIsZero)
+            );
+            assert_eq!(result_after_start, Ok(1000));
+            assert_eq!(contract.get_stream_by_id(1).unwrap().current_balance, 0);
+        }
+
+        #[ink::test]
+        fn recipient_withdraw_from_inverted_dates_stream_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let recipient = accounts.bob;
+            let payer = accounts.charlie;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, 1000, 1, 0));
+            set_sender(recipient);
+
+            // Stream started
+            set_now(6);
+
+            // Act
+            let result = contract.recipient_withdraw(1, None);
+
+            // Assert
+            assert_eq!(result, Ok(1000));
+        }
+
+        #[ink::test]
+        fn get_stream_by_id_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            let payer = accounts.alice;
+            let recipient = accounts.bob;
+            let start_date = 0;
+            let end_date = 300;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, recipient, funds, 0, 300));
+
+            // Act
+            let stream = contract.get_stream_by_id(1).unwrap();
+
+            // Assert
+            assert_eq!(stream.payer, payer);
+            assert_eq!(stream.recipient, recipient);
+            assert_eq!(stream.original_balance, funds);
+            assert_eq!(stream.current_balance, funds);
+            assert_eq!(stream.start_date, start_date);
+            assert_eq!(stream.end_date, end_date);
+        }
+
+        #[ink::test]
+        fn get_stream_by_id_with_invalid_parameters_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.get_stream_by_id(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn stream_exists_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+
+            // Act
+            let existing = contract.stream_exists(1);
+            let nonexistent = contract.stream_exists(2);
+
+            // Assert
+            assert!(existing);
+            assert!(!nonexistent);
+        }
+
+        #[ink::test]
+        fn parties_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 1000, 0, 300),
+            );
+
+            // Act
+            let parties = contract.parties(1);
+
+            // Assert
+            assert_eq!(parties, Ok((accounts.bob, accounts.charlie)));
+        }
+
+        #[ink::test]
+        fn stream_field_getters_work() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.charlie, 1000, 100, 400);
+            stream.current_balance = 600;
+            contract.streams.insert(1, &stream);
+
+            // Act
+            let original_balance = contract.original_balance_of(1);
+            let current_balance = contract.current_balance_of(1);
+            let start_date = contract.start_date_of(1);
+            let end_date = contract.end_date_of(1);
+
+            // Assert
+            assert_eq!(original_balance, Ok(1000));
+            assert_eq!(current_balance, Ok(600));
+            assert_eq!(start_date, Ok(100));
+            assert_eq!(end_date, Ok(400));
+            assert_eq!(
+                contract.end_date_of(2),
+                Err(ContractError::StreamDoesNotExist)
+            );
+        }
+
+        #[ink::test]
+        fn parties_of_nonexistent_stream_fails() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let result = contract.parties(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+        }
+
+        #[ink::test]
+        fn simulate_vesting_works() {
+            // Arrange
+            let (contract, _) = init();
+            let balance = 3000000000;
+
+            // Act & Assert
+            assert_eq!(contract.simulate_vesting(balance, 100, 400, 50), 0);
+            assert_eq!(contract.simulate_vesting(balance, 100, 400, 100), 0);
+            assert_eq!(
+                contract.simulate_vesting(balance, 100, 400, 250),
+                balance / 2
+            );
+            assert_eq!(contract.simulate_vesting(balance, 100, 400, 400), balance);
+            assert_eq!(contract.simulate_vesting(balance, 100, 400, 1000), balance);
+        }
+
+        #[ink::test]
+        fn simulate_vesting_with_zero_duration_works() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let vested = contract.simulate_vesting(1000, 100, 100, 100);
+
+            // Assert
+            assert_eq!(vested, 1000);
+        }
+
+        #[ink::test]
+        fn simulate_vesting_with_large_balance_works() {
+            // Arrange
+            let (contract, _) = init();
+
+            // Act
+            let vested = contract.simulate_vesting(u128::MAX, 0, 1 << 63, 1 << 62);
+
+            // Assert
+            assert_eq!(vested, u128::MAX / 2);
+        }
+
+        #[ink::test]
+        fn flow_rate_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 3000, 0, 300));
+
+            // Act
+            let flow_rate = contract.flow_rate(1).unwrap();
+
+            // Assert
+            assert_eq!(flow_rate, 10);
+        }
+
+        #[ink::test]
+        fn flow_rate_rounds_down() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 3299, 0, 300));
+
+            // Act
+            let flow_rate = contract.flow_rate(1).unwrap();
+
+            // Assert
+            assert_eq!(flow_rate, 10);
+        }
+
+        #[ink::test]
+        fn flow_rate_with_zero_duration_fails() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            let end_date = 1910126705;
-            set_sender(sender);
-            set_value_transferred(funds);
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 3000, 300, 300),
+            );
 
             // Act
-            let current_time = get_current_time_in_seconds(&contract);
-            let stream_id = contract
-                .create_stream(recipient, Some(end_date), None)
-                .unwrap();
+            let result = contract.flow_rate(1);
 
             // Assert
-            assert_eq!(stream_id, contract.next_stream_id - 1);
-            let stream = contract.get_stream_by_id(stream_id).unwrap();
-            assert_eq!(stream.payer, sender);
-            assert_eq!(stream.recipient, recipient);
-            assert_eq!(stream.original_balance, funds);
-            assert_eq!(stream.current_balance, funds);
-            assert_eq!(stream.start_date, current_time);
-            assert_eq!(stream.end_date, end_date);
+            assert_eq!(result, Err(ContractError::InvalidStreamDuration));
         }
 
         #[ink::test]
-        fn create_stream_without_funds_fails() {
+        fn current_flow_rate_after_top_up_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            set_sender(sender);
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 1000, 0, 300));
+            set_sender(payer);
+            set_now(150);
+            set_value_transferred(1500);
+            contract.top_up_stream(1).unwrap();
 
             // Act
-            let result = contract.create_stream(recipient, None, None);
+            let current_flow_rate = contract.current_flow_rate(1).unwrap();
 
             // Assert
-            assert_eq!(result, Err(ContractError::EmptyFunds));
+            // 500 unvested plus 1500 added, over the remaining 150 seconds
+            assert_eq!(current_flow_rate, 13);
+            assert_eq!(contract.flow_rate(1).unwrap(), 8);
         }
 
         #[ink::test]
-        fn create_stream_without_end_date_and_duration_fails() {
+        fn current_flow_rate_after_extension_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            set_sender(sender);
-            set_value_transferred(funds);
+            let payer = accounts.bob;
+            contract
+                .streams
+                .insert(1, &Stream::new(payer, accounts.charlie, 3000, 0, 300));
+            set_sender(payer);
+            set_now(200);
+            contract.extend_stream(1, 400).unwrap();
 
             // Act
-            let result = contract.create_stream(recipient, None, None);
+            let current_flow_rate = contract.current_flow_rate(1).unwrap();
 
             // Assert
-            assert_eq!(result, Err(ContractError::EndDateAndDurationAreEmpty));
+            // 1000 unvested over the remaining 200 seconds
+            assert_eq!(current_flow_rate, 5);
+            assert_eq!(contract.flow_rate(1).unwrap(), 7);
         }
 
         #[ink::test]
-        fn create_stream_with_same_payer_and_recipient_fails() {
+        fn current_flow_rate_of_finished_stream_is_zero() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.bob;
-            let recipient = accounts.bob;
-            set_sender(sender);
-            set_value_transferred(funds);
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.charlie, 3000, 0, 300),
+            );
+            set_now(300);
 
             // Act
-            let result = contract.create_stream(recipient, None, None);
+            let current_flow_rate = contract.current_flow_rate(1);
 
             // Assert
-            assert_eq!(result, Err(ContractError::RecipientCannotBePayer));
+            assert_eq!(current_flow_rate, Ok(0));
         }
 
         #[ink::test]
-        fn create_stream_with_short_duration_fails() {
+        fn schedule_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            let duration = 100;
-            set_sender(sender);
-            set_value_transferred(funds);
+            set_now(100);
+            set_sender(accounts.bob);
+            set_value_transferred(3000);
+            let stream_id = contract
+                .create_stream(accounts.charlie, None, Some(300), StreamOptions::default())
+                .unwrap();
 
             // Act
-            let result = contract.create_stream(recipient, None, Some(duration));
+            let result = contract.schedule(stream_id);
 
             // Assert
-            assert_eq!(result, Err(ContractError::StreamDurationShouldBeGreater));
+            assert_eq!(result, Ok((100, 400, 3000, 10)));
         }
 
         #[ink::test]
-        fn create_stream_with_short_end_date_fails() {
+        fn remaining_duration_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 1;
-            let sender = accounts.alice;
-            let recipient = accounts.bob;
-            let end_date = 100;
-            set_sender(sender);
-            set_value_transferred(funds);
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
 
             // Act
-            let result = contract.create_stream(recipient, Some(end_date), None);
+            let remaining_at_start = contract.remaining_duration(1).unwrap();
+            for _ in 0..25000 {
+                advance_block();
+            }
+            let remaining_halfway = contract.remaining_duration(1).unwrap();
+            for _ in 0..50000 {
+                advance_block();
+            }
+            let remaining_after_end = contract.remaining_duration(1).unwrap();
 
             // Assert
-            assert_eq!(result, Err(ContractError::StreamEndDateShouldBeLater));
+            assert_eq!(remaining_at_start, 300);
+            assert_eq!(remaining_halfway, 150);
+            assert_eq!(remaining_after_end, 0);
         }
 
         #[ink::test]
-        fn recipient_withdraw_all_works() {
+        fn progress_bps_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let recipient = accounts.alice;
-            contract.streams.insert(
-                1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
-            );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, 1000, 0, 300));
 
-            // Stream finished
+            // Act
+            let progress_at_start = contract.progress_bps(1).unwrap();
+            for _ in 0..25000 {
+                advance_block();
+            }
+            let progress_halfway = contract.progress_bps(1).unwrap();
             for _ in 0..50000 {
                 advance_block();
             }
-
-            // Act
-            let amount_withdrawn = contract.recipient_withdraw(1, None).unwrap();
+            let progress_after_end = contract.progress_bps(1).unwrap();
 
             // Assert
-            assert_eq!(amount_withdrawn, funds);
+            assert_eq!(progress_at_start, 0);
+            assert_eq!(progress_halfway, 5000);
+            assert_eq!(progress_after_end, 10000);
         }
 
         #[ink::test]
-        fn recipient_withdraw_specific_amount_works() {
+        fn progress_bps_before_start_is_zero() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let expected_withdrawal_amount = 1500000000;
-            let recipient = accounts.alice;
             contract.streams.insert(
                 1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                &Stream::new(accounts.bob, accounts.alice, 1000, 300, 600),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
 
-            // Stream finished
-            for _ in 0..50000 {
-                advance_block();
-            }
+            // Act
+            let progress = contract.progress_bps(1);
+
+            // Assert
+            assert_eq!(progress, Ok(0));
+        }
+
+        #[ink::test]
+        fn progress_bps_with_zero_duration_works() {
+            // Arrange
+            let accounts = get_default_accounts();
+            let stream = Stream::new(accounts.bob, accounts.alice, 1000, 300, 300);
 
             // Act
-            let amount_withdrawn = contract
-                .recipient_withdraw(1, Some(expected_withdrawal_amount))
-                .unwrap();
+            let progress_before_end = stream.progress_bps(299);
+            let progress_at_end = stream.progress_bps(300);
 
             // Assert
-            assert_eq!(amount_withdrawn, expected_withdrawal_amount);
+            assert_eq!(progress_before_end, 0);
+            assert_eq!(progress_at_end, 10000);
         }
 
         #[ink::test]
-        fn recipient_withdraw_with_unauthorized_wallet_fails() {
+        fn annualized_rate_works() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let expected_withdrawal_amount = 1500000000;
-            let recipient = accounts.alice;
             contract.streams.insert(
                 1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(accounts.charlie);
-
-            // Stream finished
-            for _ in 0..50000 {
-                advance_block();
-            }
 
             // Act
-            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+            let annualized_rate = contract.annualized_rate(1).unwrap();
 
             // Assert
-            assert_eq!(result, Err(ContractError::Unauthorized));
+            assert_eq!(annualized_rate, 3000000000 / 300 * SECONDS_PER_YEAR as u128);
         }
 
         #[ink::test]
-        fn recipient_withdraw_from_non_existent_stream_fails() {
+        fn annualized_rate_with_zero_duration_fails() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let expected_withdrawal_amount = 1500000000;
-            let recipient = accounts.alice;
             contract.streams.insert(
                 1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 300, 300),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
 
-            // Stream finished
-            for _ in 0..50000 {
-                advance_block();
-            }
+            // Act
+            let result = contract.annualized_rate(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::InvalidStreamDuration));
+        }
+
+        #[ink::test]
+        fn annualized_rate_with_large_balance_and_minimum_duration_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = u128::MAX / SECONDS_PER_YEAR as u128;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, funds, 0, 300));
 
             // Act
-            let result = contract.recipient_withdraw(999, Some(expected_withdrawal_amount));
+            let annualized_rate = contract.annualized_rate(1).unwrap();
 
             // Assert
-            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+            assert_eq!(
+                annualized_rate,
+                funds / 300 * SECONDS_PER_YEAR as u128
+                    + funds % 300 * SECONDS_PER_YEAR as u128 / 300
+            );
         }
 
         #[ink::test]
-        fn recipient_withdraw_with_expected_amount_greater_than_available_balance_fails() {
+        fn annualized_rate_overflowing_fails() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let expected_withdrawal_amount = 3000000000;
-            let recipient = accounts.alice;
             contract.streams.insert(
                 1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                &Stream::new(accounts.bob, accounts.alice, u128::MAX, 0, 300),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
 
-            // Stream finished
+            // Act
+            let result = contract.annualized_rate(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+        }
+
+        #[ink::test]
+        fn preview_cancellation_works() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let funds = 3000000000;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, funds, 0, 300));
+
+            // Half of the stream elapsed
             for _ in 0..25000 {
                 advance_block();
             }
 
             // Act
-            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+            let (recipient_payout, payer_refund) = contract.preview_cancellation(1).unwrap();
 
             // Assert
-            assert_eq!(
-                result,
-                Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance)
-            );
+            assert_eq!(recipient_payout, funds / 2);
+            assert_eq!(payer_refund, funds / 2);
         }
 
         #[ink::test]
-        fn recipient_withdraw_with_expected_amount_equal_to_zero_fails() {
+        fn preview_cancellation_on_arithmetic_failure_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let mut stream = Stream::new(accounts.bob, accounts.alice, 1000, 0, 300);
+            // More withdrawn than vested
+            stream.current_balance = 100;
+            contract.streams.insert(1, &stream);
+            set_now(150);
+
+            // Act
+            let result = contract.preview_cancellation(1);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+        }
+
+        #[ink::test]
+        fn cancel_stream_on_arithmetic_failure_fails() {
+            // Arrange
+            let (mut contract, accounts) = init();
+            let payer = accounts.bob;
+            let mut stream = Stream::new(payer, accounts.charlie, 1000, 0, 300);
+            // More withdrawn than vested
+            stream.current_balance = 100;
+            contract.streams.insert(1, &stream);
+            contract.total_locked = 100;
+            let payer_balance = get_balance(payer);
+            set_sender(payer);
+            set_now(150);
+
+            // Act
+            let result = contract.cancel_stream(1);
+
+            // Assert
+            // The whole balance is not refunded to the payer.
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+            assert_eq!(get_balance(payer), payer_balance);
+        }
+
+        #[ink::test]
+        fn preview_cancel_batch_works() {
             // Arrange
             let (mut contract, accounts) = init();
             let funds = 3000000000;
-            let expected_withdrawal_amount = 0;
-            let recipient = accounts.alice;
+            contract
+                .streams
+                .insert(1, &Stream::new(accounts.bob, accounts.alice, funds, 0, 300));
             contract.streams.insert(
-                1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                2,
+                &Stream::new(accounts.bob, accounts.charlie, funds, 0, 600),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
 
-            // Stream finished
+            // Half of the first stream elapsed
             for _ in 0..25000 {
                 advance_block();
             }
 
             // Act
-            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+            let (recipient_payout, payer_refund) =
+                contract.preview_cancel_batch(vec![1, 2]).unwrap();
 
             // Assert
-            assert_eq!(
-                result,
-                Err(ContractError::WithdrawalAmountShouldBeGreaterThanZero)
-            );
+            assert_eq!(recipient_payout, funds / 2 + funds / 4);
+            assert_eq!(payer_refund, funds / 2 + funds * 3 / 4);
         }
 
         #[ink::test]
-        fn recipient_withdraw_with_available_balance_equal_to_zero_fails() {
+        fn preview_cancel_batch_with_non_existent_stream_fails() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let expected_withdrawal_amount = 100;
-            let recipient = accounts.alice;
             contract.streams.insert(
                 1,
-                &Stream::new(accounts.bob, recipient, funds.clone().into(), 0, 300),
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
             );
-            set_balance(get_contract_id(), funds);
-            set_sender(recipient);
 
             // Act
-            let result = contract.recipient_withdraw(1, Some(expected_withdrawal_amount));
+            let result = contract.preview_cancel_batch(vec![1, 999]);
 
             // Assert
-            assert_eq!(result, Err(ContractError::StreamAvailableBalanceIsZero));
+            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
         }
 
         #[ink::test]
-        fn get_stream_by_id_works() {
+        fn preview_cancel_batch_with_too_many_streams_fails() {
+            // Arrange
+            let (contract, _) = init();
+            let stream_ids = (1..=MAX_BATCH_SIZE as u64 + 1).collect();
+
+            // Act
+            let result = contract.preview_cancel_batch(stream_ids);
+
+            // Assert
+            assert_eq!(result, Err(ContractError::BatchTooLarge));
+        }
+
+        #[ink::test]
+        fn preview_cancel_batch_with_duplicate_streams_fails() {
             // Arrange
             let (mut contract, accounts) = init();
-            let funds = 3000000000;
-            let payer = accounts.alice;
-            let recipient = accounts.bob;
-            let start_date = 0;
-            let end_date = 300;
-            contract
-                .streams
-                .insert(1, &Stream::new(payer, recipient, funds, 0, 300));
+            contract.streams.insert(
+                1,
+                &Stream::new(accounts.bob, accounts.alice, 3000000000, 0, 300),
+            );
 
             // Act
-            let stream = contract.get_stream_by_id(1).unwrap();
+            let result = contract.preview_cancel_batch(vec![1; 50]);
 
             // Assert
-            assert_eq!(stream.payer, payer);
-            assert_eq!(stream.recipient, recipient);
-            assert_eq!(stream.original_balance, funds);
-            assert_eq!(stream.current_balance, funds);
-            assert_eq!(stream.start_date, start_date);
-            assert_eq!(stream.end_date, end_date);
+            assert_eq!(result, Err(ContractError::DuplicateStreamId));
         }
 
         #[ink::test]
-        fn get_stream_by_id_with_invalid_parameters_fails() {
+        fn preview_cancel_batch_overflowing_total_fails() {
             // Arrange
-            let (contract, _) = init();
+            let (mut contract, accounts) = init();
+            for stream_id in [1, 2] {
+                contract.streams.insert(
+                    stream_id,
+                    &Stream::new(accounts.bob, accounts.alice, u128::MAX, 100, 400),
+                );
+            }
+
+            // Before the streams start
+            set_now(50);
 
             // Act
-            let result = contract.get_stream_by_id(1);
+            let result = contract.preview_cancel_batch(vec![1, 2]);
 
             // Assert
-            assert_eq!(result, Err(ContractError::StreamDoesNotExist));
+            assert_eq!(result, Err(ContractError::ArithmeticOverflow));
         }
     }
 }
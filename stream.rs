@@ -1,12 +1,117 @@
 use ink_env::AccountId;
+use ink_prelude::vec::Vec;
+use ink_primitives::KeyPtr;
 use ink_storage::traits::{PackedLayout, SpreadAllocate, SpreadLayout, StorageLayout};
 
-use crate::errors::ContractError;
+use crate::{
+    errors::ContractError,
+    utils::{mul_div, vested_amount, vested_amount_half_up},
+};
 
 /// Minimum duration that a stream can have.
 pub const STREAM_MINIMUM_DURATION: u64 = 300;
 
+/// Number of seconds in a year of 365 days.
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
+/// Maximum number of streams that can be processed in a single batch call.
+pub const MAX_BATCH_SIZE: usize = 50;
+
+/// Denominator of the amounts expressed in basis points.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Maximum protocol fee on withdrawals, in basis points.
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Maximum number of pending commitments included in a stream audit.
+pub const MAX_AUDIT_COMMITMENTS: usize = 20;
+
+/// Maximum number of pending commitments a stream can have.
+pub const MAX_COMMITMENTS: usize = 50;
+
+/// Maximum number of co-payers contributing to a stream besides its payer.
+pub const MAX_CONTRIBUTORS: usize = 20;
+
+/// Maximum number of withdrawals kept in the history of a stream.
+pub const MAX_WITHDRAWAL_HISTORY: usize = 20;
+
+/// Time after the end date of a stream during which only the recipient can claim its balance. 30 days.
+pub const GRACE_PERIOD: u64 = 2_592_000;
+
+/// Default maximum number of active streams a payer can have.
+pub const MAX_STREAMS_PER_PAYER: u32 = 1_000;
+
+/// Decimals of the native token, used by default to format stream amounts.
+pub const NATIVE_TOKEN_DECIMALS: u8 = 12;
+
+/// Maximum number of stream IDs scanned by a paginated query.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Version of the contract, bumped whenever its storage layout or behavior changes.
+pub const CONTRACT_VERSION: u32 = 11;
+
+/// How the stream balance unlocks between its start date and its end date.
+#[derive(
+    PartialEq,
+    Debug,
+    Default,
+    Eq,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum VestingKind {
+    /// The balance unlocks proportionally to the elapsed time.
+    #[default]
+    Linear,
+    /// The balance unlocks in equal steps at the end of each `interval`, measured in seconds.
+    Stepped { interval: u64 },
+}
+
+impl SpreadAllocate for VestingKind {
+    fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+        ptr.advance_by(<Self as SpreadLayout>::FOOTPRINT);
+        VestingKind::default()
+    }
+}
+
+/// How the vested balance is rounded while the stream is running.
+#[derive(
+    PartialEq,
+    Debug,
+    Default,
+    Eq,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum Rounding {
+    /// The vested balance is rounded down, in favor of the payer.
+    #[default]
+    Down,
+    /// The vested balance is rounded to the nearest token, halves up.
+    HalfUp,
+}
+
+impl SpreadAllocate for Rounding {
+    fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+        ptr.advance_by(<Self as SpreadLayout>::FOOTPRINT);
+        Rounding::default()
+    }
+}
+
 /// Struct for storing streams
+///
+/// The SCALE encoding follows the field declaration order and is part of the contract ABI.
+/// New fields should only be appended, so off-chain tooling can keep decoding stored streams.
 #[derive(
     PartialEq,
     Debug,
@@ -33,6 +138,195 @@ pub struct Stream {
     pub start_date: u64,
     /// Date when the stream will end. Measured in seconds.
     pub end_date: u64,
+    /// If `true`, the payer can't modify the stream parameters.
+    pub immutable: bool,
+    /// Date of the last withdrawal. Measured in seconds.
+    pub last_withdraw_at: Option<u64>,
+    /// If `true`, the stream was cancelled by the payer and can't be used anymore.
+    pub cancelled: bool,
+    /// Date from which the unvested balance streams linearly until `end_date`. Measured in seconds.
+    pub checkpoint_date: u64,
+    /// Balance already vested at `checkpoint_date`, including the withdrawn balance.
+    pub checkpoint_vested: u128,
+    /// AccountId receiving the withdrawn funds on behalf of the recipient. If not specified, funds go to the recipient.
+    pub payout_address: Option<AccountId>,
+    /// Date when the stream was created. Measured in seconds.
+    pub created_at: u64,
+    /// Date before which nothing can be withdrawn. Measured in seconds.
+    pub cliff_date: u64,
+    /// Short reference attached by the payer, such as an invoice hash.
+    pub memo: Option<[u8; 32]>,
+    /// How the stream balance unlocks over time.
+    pub vesting: VestingKind,
+    /// Decimals used to format the stream amounts. Informational only.
+    pub token_decimals: u8,
+    /// Minimum interval between two withdrawals. Measured in seconds. Zero disables it.
+    pub withdraw_cooldown: u64,
+    /// If `false`, the payer can't cancel or reduce the stream.
+    pub cancellable: bool,
+    /// Part of the stream balance the recipient only gets once the stream is finished. Refunded to the payer on cancellation.
+    pub completion_bonus: u128,
+    /// How the vested balance is rounded while the stream is running.
+    pub rounding: Rounding,
+    /// AccountId receiving the refunded funds on behalf of the payer. If not specified, refunds go to the payer.
+    pub refund_to: Option<AccountId>,
+}
+
+/// Lifecycle status of a stream at a given time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum StreamStatus {
+    /// The stream didn't start yet.
+    Pending,
+    /// The stream is vesting.
+    Active,
+    /// The stream reached its end date but its balance wasn't fully withdrawn.
+    Finished,
+    /// The whole stream balance was withdrawn.
+    FullyWithdrawn,
+    /// The stream was cancelled by the payer.
+    Cancelled,
+}
+
+/// Unit of a stream duration, converted to seconds when the stream is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum TimeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+}
+
+impl TimeUnit {
+    /// Converts a number of units to seconds.
+    ///
+    /// Parameters:
+    /// - `count`: The number of units.
+    ///
+    /// Returns:
+    /// - `count` measured in seconds.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn to_seconds(self, count: u64) -> Result<u64, ContractError> {
+        let seconds_per_unit = match self {
+            TimeUnit::Seconds => 1,
+            TimeUnit::Minutes => 60,
+            TimeUnit::Hours => 3_600,
+            TimeUnit::Days => 86_400,
+        };
+
+        count
+            .checked_mul(seconds_per_unit)
+            .ok_or(ContractError::ArithmeticOverflow)
+    }
+}
+
+/// Struct describing a stream of a template, used to recreate the same streams periodically.
+#[derive(
+    PartialEq, Debug, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct StreamTemplate {
+    /// AccountId of the recipient.
+    pub recipient: AccountId,
+    /// Funds of the stream.
+    pub amount: u128,
+    /// Duration of the stream. Measured in seconds.
+    pub duration: u64,
+}
+
+/// Optional settings of a new stream.
+#[derive(PartialEq, Debug, Eq, Clone, Copy, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct StreamOptions {
+    /// If `true`, the payer won't be able to modify the stream once it is created.
+    pub immutable: bool,
+    /// The date when the stream starts vesting, measured in seconds. If not specified, the stream starts immediately.
+    pub start_date: Option<u64>,
+    /// Seconds after the creation date when the stream starts vesting. Can't be combined with `start_date`.
+    pub start_offset: Option<u64>,
+    /// Seconds after the start date before which nothing can be withdrawn. If not specified, the stream has no cliff.
+    pub cliff: Option<u64>,
+    /// Short reference attached to the stream, such as an invoice hash.
+    pub memo: Option<[u8; 32]>,
+    /// How the stream balance unlocks over time. If not specified, the stream vests linearly.
+    pub vesting: Option<VestingKind>,
+    /// If `true` and the recipient is a contract, it will be notified about the new stream.
+    pub notify: bool,
+    /// Decimals used to format the stream amounts. Informational only. If not specified, the native token decimals are used.
+    pub token_decimals: Option<u8>,
+    /// If `false`, the payer won't be able to cancel, reduce, replace, extend or reassign the stream, nor change its withdraw cooldown.
+    pub cancellable: bool,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            immutable: false,
+            start_date: None,
+            start_offset: None,
+            cliff: None,
+            memo: None,
+            vesting: None,
+            notify: false,
+            token_decimals: None,
+            cancellable: true,
+        }
+    }
+}
+
+/// Struct bundling everything known about a stream, for auditing purposes.
+#[derive(PartialEq, Debug, Eq, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct StreamAudit {
+    /// The stream itself.
+    pub stream: Stream,
+    /// Date when the stream was created. Measured in seconds.
+    pub created_at: u64,
+    /// Date of the last withdrawal. Measured in seconds.
+    pub last_withdraw_at: Option<u64>,
+    /// Amount already withdrawn by the recipient.
+    pub withdrawn_balance: u128,
+    /// Amount that the recipient can withdraw right now.
+    pub available_balance: u128,
+    /// Pending commitments as `(due_date, amount)` tuples, capped to `MAX_AUDIT_COMMITMENTS`.
+    pub commitments: Vec<(u64, u128)>,
+    /// `true` if there are more pending commitments than the ones included.
+    pub commitments_truncated: bool,
+    /// `true` if the stream end date has already passed.
+    pub is_finished: bool,
+}
+
+/// Struct wrapping a stream with its balances computed at a given time.
+#[derive(PartialEq, Debug, Eq, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct StreamDetails {
+    /// The stream itself.
+    pub stream: Stream,
+    /// Amount that the recipient can withdraw right now.
+    pub available_balance: u128,
+    /// Amount already withdrawn by the recipient.
+    pub withdrawn_amount: u128,
+    /// `true` if the stream end date has already passed.
+    pub is_finished: bool,
+}
+
+/// Struct bundling the contract version and settings, so integrators can detect the supported features.
+#[derive(PartialEq, Debug, Eq, Clone, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ContractConfig {
+    /// Version of the contract, see `CONTRACT_VERSION`.
+    pub version: u32,
+    /// Minimum duration that a stream can have. Measured in seconds.
+    pub min_duration: u64,
+    /// Protocol fee on withdrawals, in basis points.
+    pub fee_bps: u16,
+    /// `true` if stream creation and withdrawals are paused.
+    pub paused: bool,
+    /// Owner of the contract.
+    pub owner: AccountId,
 }
 
 impl Stream {
@@ -50,9 +344,36 @@ impl Stream {
             current_balance: stream_funds,
             start_date,
             end_date,
+            immutable: false,
+            last_withdraw_at: None,
+            cancelled: false,
+            checkpoint_date: start_date,
+            checkpoint_vested: 0,
+            payout_address: None,
+            created_at: start_date,
+            cliff_date: start_date,
+            memo: None,
+            vesting: VestingKind::Linear,
+            token_decimals: NATIVE_TOKEN_DECIMALS,
+            withdraw_cooldown: 0,
+            cancellable: true,
+            completion_bonus: 0,
+            rounding: Rounding::Down,
+            refund_to: None,
         }
     }
 
+    /// Decodes a SCALE encoded stream, such as the one returned by `get_stream_by_id`.
+    ///
+    /// Parameters:
+    /// - `bytes`: The encoded stream.
+    ///
+    /// Errors:
+    /// - The SCALE error if the bytes are not a valid stream or have trailing data.
+    pub fn decode_from_bytes(mut bytes: &[u8]) -> Result<Self, scale::Error> {
+        <Self as scale::DecodeAll>::decode_all(&mut bytes)
+    }
+
     /// Reduces the current stream balance by the specified amount.
     ///
     /// Parameters:
@@ -73,35 +394,590 @@ impl Stream {
         Ok(())
     }
 
+    /// Adds funds to the stream.
+    ///
+    /// Parameters:
+    /// - `amount`: Amount to increase the stream balance by.
+    ///
+    /// Behavior:
+    /// - Both `original_balance` and `current_balance` will be increased by `amount`.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn add_funds(&mut self, amount: u128) -> Result<(), ContractError> {
+        self.original_balance = self
+            .original_balance
+            .checked_add(amount)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+        self.current_balance = self
+            .current_balance
+            .checked_add(amount)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    /// Tops up the stream, vesting the new funds over the remaining duration.
+    ///
+    /// Parameters:
+    /// - `amount`: Amount to increase the stream balance by.
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Behavior:
+    /// - The balance vested until `current_time` is kept, so the top-up doesn't vest retroactively.
+    /// - The unvested balance, including `amount`, streams linearly from `current_time` until `end_date`.
+    /// - Both `original_balance` and `current_balance` will be increased by `amount`.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn top_up(&mut self, amount: u128, current_time: u64) -> Result<(), ContractError> {
+        self.checkpoint(current_time)?;
+        self.add_funds(amount)
+    }
+
+    /// Extends the stream, vesting the unvested balance until the new end date.
+    ///
+    /// Parameters:
+    /// - `new_end_date`: New end date of the stream, measured in seconds.
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Behavior:
+    /// - The balance vested until `current_time` is kept, so the extension doesn't change what the recipient already earned.
+    /// - The unvested balance streams linearly from `current_time` until `new_end_date`.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn extend(&mut self, new_end_date: u64, current_time: u64) -> Result<(), ContractError> {
+        self.checkpoint(current_time)?;
+        self.end_date = new_end_date;
+
+        Ok(())
+    }
+
+    /// Reduces the stream principal, vesting the rest of the unvested balance over the remaining duration.
+    ///
+    /// Parameters:
+    /// - `amount`: Amount to reduce the stream balance by.
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Validations:
+    /// - `amount` should not exceed the balance unvested at `current_time`, excluding the completion bonus.
+    ///
+    /// Behavior:
+    /// - The balance vested until `current_time` is kept, so the recipient doesn't lose what it already earned.
+    /// - Both `original_balance` and `current_balance` will be decreased by `amount`.
+    ///
+    /// Errors:
+    /// - ReductionExceedsUnvestedBalance
+    /// - ArithmeticOverflow
+    pub fn reduce(&mut self, amount: u128, current_time: u64) -> Result<(), ContractError> {
+        self.checkpoint(current_time)?;
+
+        let unvested_balance = self
+            .streamed_balance()
+            .checked_sub(self.streamed_vested_balance(current_time)?)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+
+        if amount > unvested_balance || amount > self.current_balance {
+            return Err(ContractError::ReductionExceedsUnvestedBalance);
+        }
+
+        self.original_balance -= amount;
+        self.current_balance -= amount;
+
+        Ok(())
+    }
+
+    /// Releases part of the stream balance to the recipient immediately, vesting it ahead of schedule.
+    ///
+    /// Parameters:
+    /// - `amount`: Amount to be released.
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Validations:
+    /// - `amount` should not exceed `current_balance` minus the completion bonus.
+    ///
+    /// Behavior:
+    /// - `amount` is vested immediately and withdrawn, so the available balance doesn't change.
+    /// - If `amount` exceeds the unvested balance, the excess is taken from the available balance.
+    /// - The rest of the unvested balance streams linearly from `current_time` until `end_date`.
+    ///
+    /// Errors:
+    /// - ExpectedWithdrawalAmountExceedsStreamAvailableBalance
+    /// - ArithmeticOverflow
+    pub fn release(&mut self, amount: u128, current_time: u64) -> Result<(), ContractError> {
+        if amount > self.current_balance.saturating_sub(self.completion_bonus) {
+            return Err(ContractError::ExpectedWithdrawalAmountExceedsStreamAvailableBalance);
+        }
+
+        self.withdraw(amount)?;
+        self.checkpoint(current_time)?;
+
+        let unvested_balance = self
+            .streamed_balance()
+            .checked_sub(self.streamed_vested_balance(current_time)?)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+        self.checkpoint_vested += amount.min(unvested_balance);
+
+        Ok(())
+    }
+
+    /// Records the balance vested until a given time, so later changes only affect the unvested balance.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn checkpoint(&mut self, current_time: u64) -> Result<(), ContractError> {
+        if current_time > self.checkpoint_date {
+            self.checkpoint_vested = self.streamed_vested_balance(current_time)?;
+            self.checkpoint_date = current_time;
+        }
+
+        Ok(())
+    }
+
+    /// Calculates the balance vested until a given time, including the withdrawn balance.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - The balance vested at the last checkpoint plus the unvested balance streamed since then.
+    /// - The completion bonus is included only once the stream is finished.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn vested_balance(&self, current_time: u64) -> Result<u128, ContractError> {
+        let completion_bonus = if self.is_finished(current_time) {
+            self.completion_bonus
+        } else {
+            0
+        };
+
+        self.streamed_vested_balance(current_time)?
+            .checked_add(completion_bonus)
+            .ok_or(ContractError::ArithmeticOverflow)
+    }
+
+    /// Calculates the balance vested until a given time, excluding the completion bonus.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    fn streamed_vested_balance(&self, current_time: u64) -> Result<u128, ContractError> {
+        let unvested_balance = self
+            .streamed_balance()
+            .checked_sub(self.checkpoint_vested)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+
+        self.checkpoint_vested
+            .checked_add(Stream::compute_unlocked(
+                unvested_balance,
+                self.checkpoint_date,
+                self.end_date,
+                self.vesting_time(current_time),
+                self.rounding,
+            ))
+            .ok_or(ContractError::ArithmeticOverflow)
+    }
+
+    /// Calculates the part of the original balance streamed over time, that is, without the completion bonus.
+    fn streamed_balance(&self) -> u128 {
+        self.original_balance.saturating_sub(self.completion_bonus)
+    }
+
+    /// Calculates the time up to which the stream balance is unlocked, according to its vesting kind.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - `current_time` for linear streams.
+    /// - The end of the last completed interval for stepped streams.
+    fn vesting_time(&self, current_time: u64) -> u64 {
+        match self.vesting {
+            VestingKind::Stepped { interval } if interval > 0 && current_time > self.start_date => {
+                let completed_intervals = (current_time - self.start_date) / interval;
+                self.start_date
+                    .saturating_add(completed_intervals.saturating_mul(interval))
+            }
+            _ => current_time,
+        }
+    }
+
     /// Calculates the stream availabe balance based on the elapsed time.
     ///
     /// Parameters:
     /// - `current_time`: Current time in seconds.
     ///
     /// Behavior:
+    /// - Nothing is available until the stream starts and its cliff date is reached.
     /// - The stream available balance will be calculated based on the elapsed time and the withdrawn balance.
+    /// - The vested balance is rounded down unless the stream `rounding` is `HalfUp`, so the recipient gets the exact share at the end date.
+    /// - The stream available balance never exceeds the stream current balance.
     ///
     /// Returns:
     /// - The stream available balance.
+    ///
+    /// Errors:
+    /// - StreamAvailableBalanceIsZero
+    /// - ArithmeticOverflow
     pub fn get_available_balance(&self, current_time: u64) -> Result<u128, ContractError> {
-        let balance_withdrawn: u128 = self.original_balance - self.current_balance;
+        let available_balance = self.calculate_available_balance(current_time)?;
 
-        let available_balance = if self.is_finished(current_time) {
-            self.original_balance - balance_withdrawn
-        } else {
-            let elapsed_time = current_time - self.start_date;
+        if available_balance == 0 {
+            return Err(ContractError::StreamAvailableBalanceIsZero);
+        }
 
-            let unlocked_balance =
-                self.original_balance * (elapsed_time as u128) / (self.total_duration() as u128);
+        Ok(available_balance)
+    }
 
-            unlocked_balance - balance_withdrawn
+    /// Calculates the stream available balance like `get_available_balance`, but returns zero instead of an error when nothing is available.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - The stream available balance, zero if nothing has vested yet, everything vested was already withdrawn or the calculation overflows.
+    pub fn available_balance_or_zero(&self, current_time: u64) -> u128 {
+        self.calculate_available_balance(current_time).unwrap_or(0)
+    }
+
+    /// Calculates the stream available balance, which may be zero.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    fn calculate_available_balance(&self, current_time: u64) -> Result<u128, ContractError> {
+        if current_time <= self.start_date || current_time < self.cliff_date {
+            return Ok(0);
+        }
+
+        if self.is_finished(current_time) {
+            return Ok(self.current_balance);
+        }
+
+        Ok(self
+            .vested_balance(current_time)?
+            .checked_sub(self.withdrawn_balance())
+            .ok_or(ContractError::ArithmeticOverflow)?
+            .min(self.current_balance))
+    }
+
+    /// Calculates the unlocked amount of a balance streamed linearly between two dates.
+    ///
+    /// Parameters:
+    /// - `balance`: Balance streamed between `start_date` and `end_date`.
+    /// - `start_date`: Date when the streaming starts. Measured in seconds.
+    /// - `end_date`: Date when the streaming ends. Measured in seconds.
+    /// - `current_time`: Time to calculate the unlocked amount at. Measured in seconds.
+    /// - `rounding`: How the unlocked amount is rounded in between both dates.
+    ///
+    /// Behavior:
+    /// - Nothing is unlocked before `start_date`.
+    /// - The whole balance is unlocked from `end_date`, or immediately if both dates are the same.
+    /// - In between, the balance is unlocked proportionally to the elapsed time, see `vested_amount` and `vested_amount_half_up`.
+    ///
+    /// Returns:
+    /// - The unlocked amount.
+    ///
+    /// NOTES
+    /// -----
+    /// - A balance lower than the duration in seconds still unlocks progressively, one token every `duration / balance` seconds.
+    pub fn compute_unlocked(
+        balance: u128,
+        start_date: u64,
+        end_date: u64,
+        current_time: u64,
+        rounding: Rounding,
+    ) -> u128 {
+        if current_time < start_date {
+            return 0;
+        }
+
+        let elapsed = current_time - start_date;
+        let duration = end_date.saturating_sub(start_date);
+
+        match rounding {
+            Rounding::Down => vested_amount(balance, elapsed, duration),
+            Rounding::HalfUp => vested_amount_half_up(balance, elapsed, duration),
+        }
+    }
+
+    /// Calculates how much of the stream duration has elapsed, in basis points.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - Zero until the stream starts, `BPS_DENOMINATOR` once it finishes, and the elapsed proportion rounding down in between.
+    pub fn progress_bps(&self, current_time: u64) -> u16 {
+        // It can't exceed `BPS_DENOMINATOR`, so it fits in a `u16`.
+        Stream::compute_unlocked(
+            BPS_DENOMINATOR as u128,
+            self.start_date,
+            self.end_date,
+            current_time,
+            Rounding::Down,
+        ) as u16
+    }
+
+    /// Calculates the time left until the stream finishes.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - The seconds between `current_time` and `stream.end_date`, or zero if the stream is finished.
+    pub fn remaining_duration(&self, current_time: u64) -> u64 {
+        self.end_date.saturating_sub(current_time)
+    }
+
+    /// Calculates the amount streamed per second.
+    ///
+    /// Validations:
+    /// - The stream duration should be greater than zero.
+    ///
+    /// Returns:
+    /// - The streamed balance, without the completion bonus, divided by `total_duration`, rounding down.
+    ///
+    /// Errors:
+    /// - InvalidStreamDuration
+    pub fn flow_rate(&self) -> Result<u128, ContractError> {
+        let total_duration = self.total_duration();
+
+        if total_duration == 0 {
+            return Err(ContractError::InvalidStreamDuration);
+        }
+
+        Ok(self.streamed_balance() / total_duration as u128)
+    }
+
+    /// Calculates the amount streamed per second from a given time on.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - The unvested balance divided by the time left until `end_date`, rounding down.
+    /// - Zero if the stream is finished, since nothing is streamed anymore.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    ///
+    /// NOTES
+    /// -----
+    /// - Unlike `flow_rate`, it reflects top-ups and extensions made while the stream is running.
+    pub fn current_flow_rate(&self, current_time: u64) -> Result<u128, ContractError> {
+        let remaining_duration = self
+            .end_date
+            .saturating_sub(current_time.max(self.start_date));
+
+        if remaining_duration == 0 {
+            return Ok(0);
+        }
+
+        let unvested_balance = self
+            .streamed_balance()
+            .checked_sub(self.streamed_vested_balance(current_time)?)
+            .ok_or(ContractError::ArithmeticOverflow)?;
+
+        Ok(unvested_balance / remaining_duration as u128)
+    }
+
+    /// Calculates the amount that would be streamed in a year at the stream rate.
+    ///
+    /// Validations:
+    /// - The stream duration should be greater than zero.
+    ///
+    /// Returns:
+    /// - The streamed balance, without the completion bonus, times `SECONDS_PER_YEAR / total_duration`.
+    ///
+    /// Errors:
+    /// - InvalidStreamDuration
+    /// - ArithmeticOverflow
+    pub fn annualized_rate(&self) -> Result<u128, ContractError> {
+        let total_duration = self.total_duration();
+
+        if total_duration == 0 {
+            return Err(ContractError::InvalidStreamDuration);
+        }
+
+        mul_div(self.streamed_balance(), SECONDS_PER_YEAR, total_duration)
+            .ok_or(ContractError::ArithmeticOverflow)
+    }
+
+    /// Calculates how the stream balance would be split if the stream was cancelled.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Behavior:
+    /// - The recipient keeps the vested balance that has not been withdrawn yet.
+    /// - The payer gets back the rest of the current balance.
+    ///
+    /// Returns:
+    /// - A tuple with the recipient payout and the payer refund.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn cancellation_split(&self, current_time: u64) -> Result<(u128, u128), ContractError> {
+        let recipient_payout = match self.get_available_balance(current_time) {
+            Err(ContractError::StreamAvailableBalanceIsZero) => 0,
+            result => result?,
         };
+        // It can't underflow because the available balance is part of the current balance.
+        let payer_refund = self.current_balance.saturating_sub(recipient_payout);
 
-        if available_balance == 0 {
-            return Err(ContractError::StreamAvailableBalanceIsZero);
+        Ok((recipient_payout, payer_refund))
+    }
+
+    /// Closes the stream keeping only the vested balance in its history.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Behavior:
+    /// - `original_balance` will be reduced to the vested balance.
+    /// - `current_balance` will be zero.
+    /// - `end_date` will be the current time if the stream was not finished yet.
+    /// - `completion_bonus` will be zero, since it was either paid out or refunded.
+    ///
+    /// Returns:
+    /// - A tuple with the recipient payout and the payer refund, as calculated by `cancellation_split`.
+    ///
+    /// Errors:
+    /// - ArithmeticOverflow
+    pub fn settle(&mut self, current_time: u64) -> Result<(u128, u128), ContractError> {
+        let (recipient_payout, payer_refund) = self.cancellation_split(current_time)?;
+
+        self.original_balance = self.original_balance.saturating_sub(payer_refund);
+        self.current_balance = 0;
+        self.end_date = self.end_date.min(current_time);
+        self.completion_bonus = 0;
+
+        Ok((recipient_payout, payer_refund))
+    }
+
+    /// Check if the stream is still active.
+    ///
+    /// Validations:
+    /// - The stream should not be cancelled.
+    ///
+    /// Errors:
+    /// - StreamAlreadyCancelled
+    pub fn ensure_active(&self) -> Result<(), ContractError> {
+        if self.cancelled {
+            return Err(ContractError::StreamAlreadyCancelled);
         }
 
-        Ok(available_balance)
+        Ok(())
+    }
+
+    /// Check if the withdrawal cooldown of the stream elapsed since the last withdrawal.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Errors:
+    /// - WithdrawOnCooldown
+    pub fn ensure_withdraw_cooldown_elapsed(&self, current_time: u64) -> Result<(), ContractError> {
+        if let Some(last_withdraw_at) = self.last_withdraw_at {
+            if current_time.saturating_sub(last_withdraw_at) < self.withdraw_cooldown {
+                return Err(ContractError::WithdrawOnCooldown);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the stream parameters can be modified by the payer.
+    ///
+    /// Validations:
+    /// - The stream should not be cancelled.
+    /// - The stream should not be immutable.
+    ///
+    /// Errors:
+    /// - StreamAlreadyCancelled
+    /// - StreamImmutable
+    pub fn can_be_modified(&self) -> Result<(), ContractError> {
+        self.ensure_active()?;
+
+        if self.immutable {
+            return Err(ContractError::StreamImmutable);
+        }
+
+        Ok(())
+    }
+
+    /// Check if a commitment can be pledged to the stream.
+    ///
+    /// Parameters:
+    /// - `due_date`: The date when the funds should be added, measured in seconds.
+    /// - `amount`: The amount of funds pledged.
+    ///
+    /// Validations:
+    /// - `amount` should be greater than zero.
+    /// - `due_date` should be earlier than the stream end date, so the commitment can be fulfilled before the stream finishes.
+    ///
+    /// Errors:
+    /// - EmptyFunds
+    /// - InvalidCommitment
+    pub fn ensure_valid_commitment(
+        &self,
+        due_date: u64,
+        amount: u128,
+    ) -> Result<(), ContractError> {
+        if amount == 0 {
+            return Err(ContractError::EmptyFunds);
+        }
+
+        if due_date >= self.end_date {
+            return Err(ContractError::InvalidCommitment);
+        }
+
+        Ok(())
+    }
+
+    /// Check if the stream is consistent, such as a stream received from outside the contract.
+    ///
+    /// Validations:
+    /// - The stream should not be cancelled.
+    /// - `end_date` should be later than `start_date`.
+    /// - `current_balance` should not exceed `original_balance`.
+    /// - `checkpoint_vested` and `completion_bonus` should not exceed `original_balance`.
+    ///
+    /// Errors:
+    /// - ValidationError
+    pub fn ensure_consistent(&self) -> Result<(), ContractError> {
+        if self.cancelled
+            || self.end_date <= self.start_date
+            || self.current_balance > self.original_balance
+            || self.checkpoint_vested > self.original_balance
+            || self.completion_bonus > self.original_balance
+        {
+            return Err(ContractError::ValidationError);
+        }
+
+        Ok(())
+    }
+
+    /// Check if the stream can be cancelled by the payer.
+    ///
+    /// Errors:
+    /// - StreamNotCancellable
+    pub fn ensure_cancellable(&self) -> Result<(), ContractError> {
+        if !self.cancellable {
+            return Err(ContractError::StreamNotCancellable);
+        }
+
+        Ok(())
     }
 
     /// Check if the caller has permission to withdraw from the stream.
@@ -122,22 +998,351 @@ impl Stream {
         Ok(())
     }
 
+    /// Returns the AccountId that should receive the recipient funds.
+    ///
+    /// Returns:
+    /// - `stream.payout_address` if specified, `stream.recipient` otherwise.
+    pub fn payout_destination(&self) -> AccountId {
+        self.payout_address.unwrap_or(self.recipient)
+    }
+
+    /// Returns the AccountId that should receive the payer refunds.
+    ///
+    /// Returns:
+    /// - `stream.refund_to` if specified, `stream.payer` otherwise.
+    pub fn refund_destination(&self) -> AccountId {
+        self.refund_to.unwrap_or(self.payer)
+    }
+
     /// Check if the stream is finished.
     ///
     /// Parameters:
     /// - `current_time`: Current time in seconds.
     ///
     /// Returns:
-    /// - `true` if the stream has finished, `false` otherwise.
-    fn is_finished(&self, current_time: u64) -> bool {
-        current_time > self.end_date
+    /// - `true` if the stream has reached its end date, `false` otherwise.
+    pub fn is_finished(&self, current_time: u64) -> bool {
+        current_time >= self.end_date
+    }
+
+    /// Calculates the stream lifecycle status.
+    ///
+    /// Parameters:
+    /// - `current_time`: Current time in seconds.
+    ///
+    /// Returns:
+    /// - `Cancelled` if the stream was cancelled, regardless of its dates.
+    /// - `Pending` before `start_date`.
+    /// - `FullyWithdrawn` once the current balance is zero.
+    /// - `Finished` from `end_date`, `Active` otherwise.
+    pub fn status(&self, current_time: u64) -> StreamStatus {
+        if self.cancelled {
+            StreamStatus::Cancelled
+        } else if current_time < self.start_date {
+            StreamStatus::Pending
+        } else if self.current_balance == 0 {
+            StreamStatus::FullyWithdrawn
+        } else if self.is_finished(current_time) {
+            StreamStatus::Finished
+        } else {
+            StreamStatus::Active
+        }
+    }
+
+    /// Calculates the balance already withdrawn from the stream.
+    ///
+    /// Returns:
+    /// - The difference between `stream.original_balance` and `stream.current_balance`.
+    /// - Zero if `stream.current_balance` exceeds `stream.original_balance`, which shouldn't happen since funds are always added to both.
+    pub fn withdrawn_balance(&self) -> u128 {
+        self.original_balance.saturating_sub(self.current_balance)
     }
 
     /// Calculates the total duration of the stream.
     ///
     /// Returns:
-    /// - The difference between `stream.end_date` and `stream.start_date`.
-    fn total_duration(&self) -> u64 {
-        self.end_date - self.start_date
+    /// - The difference between `stream.end_date` and `stream.start_date`, or zero if `end_date` is earlier.
+    pub fn total_duration(&self) -> u64 {
+        self.end_date.saturating_sub(self.start_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scale::Encode;
+
+    fn populated_stream() -> Stream {
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+        stream.current_balance = 600;
+        stream.immutable = true;
+        stream.cancelled = true;
+        stream.last_withdraw_at = Some(250);
+        stream.checkpoint_date = 200;
+        stream.checkpoint_vested = 300;
+        stream.payout_address = Some(AccountId::from([3u8; 32]));
+        stream.created_at = 50;
+        stream.cliff_date = 150;
+        stream.memo = Some([4u8; 32]);
+        stream.vesting = VestingKind::Stepped { interval: 100 };
+        stream.token_decimals = 18;
+        stream.withdraw_cooldown = 60;
+        stream.cancellable = false;
+        stream.completion_bonus = 100;
+        stream.rounding = Rounding::HalfUp;
+        stream.refund_to = Some(AccountId::from([5u8; 32]));
+        stream
+    }
+
+    #[test]
+    fn stream_scale_round_trip_works() {
+        // Arrange
+        let stream = populated_stream();
+
+        // Act
+        let decoded = Stream::decode_from_bytes(&stream.encode());
+
+        // Assert
+        assert_eq!(decoded.ok(), Some(stream));
+    }
+
+    #[test]
+    fn decode_stream_with_trailing_bytes_fails() {
+        // Arrange
+        let mut bytes = populated_stream().encode();
+        bytes.push(0);
+
+        // Act
+        let decoded = Stream::decode_from_bytes(&bytes);
+
+        // Assert
+        assert!(decoded.is_err());
+    }
+
+    #[test]
+    fn ensure_consistent_works() {
+        // Arrange
+        let stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            0,
+            300,
+        );
+        let mut overvested_stream = stream;
+        overvested_stream.checkpoint_vested = 2000;
+        let mut oversized_bonus_stream = stream;
+        oversized_bonus_stream.completion_bonus = 2000;
+
+        // Act
+        let valid = stream.ensure_consistent();
+        let cancelled = populated_stream().ensure_consistent();
+        let overvested = overvested_stream.ensure_consistent();
+        let oversized_bonus = oversized_bonus_stream.ensure_consistent();
+
+        // Assert
+        assert_eq!(valid, Ok(()));
+        assert_eq!(cancelled, Err(ContractError::ValidationError));
+        assert_eq!(overvested, Err(ContractError::ValidationError));
+        assert_eq!(oversized_bonus, Err(ContractError::ValidationError));
+    }
+
+    #[test]
+    fn get_available_balance_with_current_balance_above_original_works() {
+        // Arrange
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            0,
+            300,
+        );
+        stream.current_balance = 2000;
+
+        // Act
+        let halfway = stream.get_available_balance(150);
+        let finished = stream.get_available_balance(300);
+
+        // Assert
+        assert_eq!(stream.withdrawn_balance(), 0);
+        assert_eq!(halfway, Ok(500));
+        assert_eq!(finished, Ok(2000));
+    }
+
+    #[test]
+    fn available_balance_or_zero_returns_zero_when_nothing_is_available() {
+        // Arrange
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+        stream.cliff_date = 200;
+
+        // Act
+        let before_start = stream.available_balance_or_zero(50);
+        let at_start = stream.available_balance_or_zero(100);
+        let before_cliff = stream.available_balance_or_zero(150);
+        stream.current_balance = 500;
+        let already_withdrawn = stream.available_balance_or_zero(250);
+
+        // Assert
+        assert_eq!(before_start, 0);
+        assert_eq!(at_start, 0);
+        assert_eq!(before_cliff, 0);
+        assert_eq!(already_withdrawn, 0);
+        assert_eq!(
+            stream.get_available_balance(150),
+            Err(ContractError::StreamAvailableBalanceIsZero)
+        );
+    }
+
+    #[test]
+    fn available_balance_or_zero_returns_vested_balance() {
+        // Arrange
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+        stream.cliff_date = 200;
+
+        // Act
+        let at_cliff = stream.available_balance_or_zero(250);
+        stream.current_balance = 700;
+        let after_withdrawal = stream.available_balance_or_zero(250);
+        let finished = stream.available_balance_or_zero(400);
+
+        // Assert
+        assert_eq!(at_cliff, 500);
+        assert_eq!(after_withdrawal, 200);
+        assert_eq!(finished, 700);
+        assert_eq!(stream.get_available_balance(250), Ok(after_withdrawal));
+    }
+
+    #[test]
+    fn available_balance_or_zero_returns_zero_on_arithmetic_failure() {
+        // Arrange
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+        // More withdrawn than vested
+        stream.current_balance = 100;
+
+        // Act
+        let result = stream.available_balance_or_zero(250);
+
+        // Assert
+        assert_eq!(result, 0);
+        assert_eq!(
+            stream.get_available_balance(250),
+            Err(ContractError::ArithmeticOverflow)
+        );
+    }
+
+    #[test]
+    fn time_unit_to_seconds_works() {
+        // Act
+        let seconds = TimeUnit::Seconds.to_seconds(90);
+        let minutes = TimeUnit::Minutes.to_seconds(90);
+        let hours = TimeUnit::Hours.to_seconds(2);
+        let days = TimeUnit::Days.to_seconds(30);
+
+        // Assert
+        assert_eq!(seconds, Ok(90));
+        assert_eq!(minutes, Ok(5_400));
+        assert_eq!(hours, Ok(7_200));
+        assert_eq!(days, Ok(2_592_000));
+    }
+
+    #[test]
+    fn time_unit_to_seconds_overflow_fails() {
+        // Act
+        let result = TimeUnit::Days.to_seconds(u64::MAX);
+
+        // Assert
+        assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn get_available_balance_with_funds_below_duration_works() {
+        // Arrange
+        let stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            100,
+            0,
+            300,
+        );
+
+        // Act
+        let before_first_token = stream.get_available_balance(2);
+        let first_token = stream.get_available_balance(3);
+        let halfway = stream.get_available_balance(150);
+        let before_end = stream.get_available_balance(299);
+        let finished = stream.get_available_balance(300);
+
+        // Assert
+        assert_eq!(
+            before_first_token,
+            Err(ContractError::StreamAvailableBalanceIsZero)
+        );
+        assert_eq!(first_token, Ok(1));
+        assert_eq!(halfway, Ok(50));
+        assert_eq!(before_end, Ok(99));
+        assert_eq!(finished, Ok(100));
+    }
+
+    #[test]
+    fn cancellation_split_without_available_balance_works() {
+        // Arrange
+        let stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+
+        // Act
+        let result = stream.cancellation_split(50);
+
+        // Assert
+        assert_eq!(result, Ok((0, 1000)));
+    }
+
+    #[test]
+    fn cancellation_split_on_arithmetic_failure_fails() {
+        // Arrange
+        let mut stream = Stream::new(
+            AccountId::from([1u8; 32]),
+            AccountId::from([2u8; 32]),
+            1000,
+            100,
+            400,
+        );
+        // More withdrawn than vested
+        stream.current_balance = 100;
+
+        // Act
+        let result = stream.settle(250);
+
+        // Assert
+        assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+        assert_eq!(stream.current_balance, 100);
     }
 }
@@ -1,84 +1,211 @@
 use ink_env::AccountId;
 use ink_lang::codegen::Env;
+use ink_prelude::vec::Vec;
 
-use crate::{
-    errors::ContractError, stream::STREAM_MINIMUM_DURATION, streams_contract::StreamsContract,
-};
+use crate::{errors::ContractError, stream::VestingKind, streams_contract::StreamsContract};
 
 /// Validates and generate the stream end date based on the date parameters of the `create_stream` message.
 ///
 /// Parameters:
 /// - `end_date`: Date measured in seconds received as parameter in the `create_stream` message.
 /// - `duration`: Duration measured in seconds received as parameter in the `create_stream` message.
-/// - `start_date`: Stream creation date measured in seconds.
+/// - `start_date`: Stream start date measured in seconds.
+/// - `min_duration`: The stream **minimum duration** measured in seconds.
+/// - `max_duration`: The stream **maximum duration** measured in seconds. Zero disables the check.
 ///
 /// Validations:
 /// - `end_date` and `duration` cannot be both empty.
-/// - `end_date` should be greater than the current date.
-/// - The stream duration should be greater than the **minimum duration**.
+/// - If both `end_date` and `duration` are specified, they should agree (`start_date + duration == end_date`).
+/// - `end_date` should be greater than `start_date`.
+/// - The stream duration, given or computed from `end_date`, should be greater than the **minimum duration**.
+/// - The stream duration should not be greater than the **maximum duration**, if configured.
 ///
 /// Returns:
 /// - The stream end date in seconds.
 ///
 /// Errors:
 /// - EndDateAndDurationAreEmpty
-/// - StreamEndDateShouldBeLater
+/// - EndDateDurationConflict
+/// - StreamEndDateInPast
 /// - StreamDurationShouldBeGreater
+/// - StreamDurationTooLong
+/// - ArithmeticOverflow
 ///
 /// NOTES
 /// -----
-/// - The current stream **minimum duration** is 5 minutes.
+/// - The **minimum duration** is configured when the contract is instantiated. It is 5 minutes by default.
+/// - The **maximum duration** is configured by the contract owner. It is disabled by default.
 pub fn validate_and_generate_stream_end_date(
     end_date: Option<u64>,
     duration: Option<u64>,
     start_date: u64,
+    min_duration: u64,
+    max_duration: u64,
 ) -> Result<u64, ContractError> {
-    if end_date == None && duration == None {
-        return Err(ContractError::EndDateAndDurationAreEmpty);
-    }
+    // Both modes are validated through the same effective duration.
+    let stream_duration = match (end_date, duration) {
+        (None, None) => return Err(ContractError::EndDateAndDurationAreEmpty),
+        (Some(end_date), Some(duration)) if start_date.checked_add(duration) != Some(end_date) => {
+            return Err(ContractError::EndDateDurationConflict)
+        }
+        (Some(end_date), _) if end_date <= start_date => {
+            return Err(ContractError::StreamEndDateInPast)
+        }
+        (Some(end_date), _) => end_date - start_date,
+        (None, Some(duration)) => duration,
+    };
 
-    if end_date != None {
-        let end_date = end_date.unwrap();
-        validate_stream_end_date(start_date, end_date)?;
-        return Ok(end_date);
+    validate_stream_duration(stream_duration, min_duration)?;
+    validate_stream_max_duration(stream_duration, max_duration)?;
+
+    match end_date {
+        Some(end_date) => Ok(end_date),
+        None => checked_end_date(start_date, stream_duration),
     }
+}
 
-    if duration != None {
-        let duration = duration.unwrap();
-        validate_stream_duration(duration)?;
-        return Ok(start_date + duration);
-    };
+/// Calculates the end date of a stream from its start date and duration.
+///
+/// Parameters:
+/// - `start_date`: Stream start date measured in seconds.
+/// - `duration`: Stream duration measured in seconds.
+///
+/// Returns:
+/// - `start_date + duration`.
+///
+/// Errors:
+/// - ArithmeticOverflow
+fn checked_end_date(start_date: u64, duration: u64) -> Result<u64, ContractError> {
+    start_date
+        .checked_add(duration)
+        .ok_or(ContractError::ArithmeticOverflow)
+}
 
-    Err(ContractError::Unexpected)
+/// Generates the stream start date from the `start_offset` parameter of the `create_stream` message.
+///
+/// Parameters:
+/// - `start_date`: Date measured in seconds received as parameter in the `create_stream` message.
+/// - `start_offset`: Offset from `current_time` measured in seconds received as parameter in the `create_stream` message.
+/// - `current_time`: Stream creation date measured in seconds.
+///
+/// Validations:
+/// - `start_date` and `start_offset` cannot be both specified.
+///
+/// Returns:
+/// - `current_time + start_offset` if `start_offset` is specified, `start_date` otherwise.
+///
+/// Errors:
+/// - StartDateOffsetConflict
+/// - ArithmeticOverflow
+pub fn generate_stream_start_date_from_offset(
+    start_date: Option<u64>,
+    start_offset: Option<u64>,
+    current_time: u64,
+) -> Result<Option<u64>, ContractError> {
+    match (start_date, start_offset) {
+        (Some(_), Some(_)) => Err(ContractError::StartDateOffsetConflict),
+        (None, Some(start_offset)) => current_time
+            .checked_add(start_offset)
+            .map(Some)
+            .ok_or(ContractError::ArithmeticOverflow),
+        (start_date, None) => Ok(start_date),
+    }
 }
 
-/// Validates the stream end date based on the `end_date` parameter of the `create_stream` message.
+/// Validates and generate the stream start date based on the `start_date` parameter of the `create_stream` message.
 ///
 /// Parameters:
-/// - `start_date`: Stream creation date measured in seconds.
-/// - `end_date`: Date measured in seconds received as parameter in the `create_stream` message.
+/// - `start_date`: Date measured in seconds received as parameter in the `create_stream` message.
+/// - `current_time`: Stream creation date measured in seconds.
 ///
 /// Validations:
-/// - `end_date` should be after the minimum end date according to the `start_date`.
+/// - If specified, `start_date` should not be earlier than `current_time`.
+///
+/// Returns:
+/// - `start_date` if specified, `current_time` otherwise.
 ///
 /// Errors:
-/// - StreamEndDateShouldBeLater
+/// - StreamStartDateShouldBeLater
+pub fn validate_and_generate_stream_start_date(
+    start_date: Option<u64>,
+    current_time: u64,
+) -> Result<u64, ContractError> {
+    match start_date {
+        Some(start_date) if start_date < current_time => {
+            Err(ContractError::StreamStartDateShouldBeLater)
+        }
+        Some(start_date) => Ok(start_date),
+        None => Ok(current_time),
+    }
+}
+
+/// Validates and generate the stream cliff date based on the `cliff` parameter of the `create_stream` message.
 ///
-/// NOTES
-/// -----
-/// - The current stream **minimum duration** is 5 minutes.
-fn validate_stream_end_date(start_date: u64, end_date: u64) -> Result<(), ContractError> {
-    if end_date < start_date + STREAM_MINIMUM_DURATION {
-        return Err(ContractError::StreamEndDateShouldBeLater);
+/// Parameters:
+/// - `cliff`: Cliff duration measured in seconds received as parameter in the `create_stream` message.
+/// - `start_date`: Stream start date measured in seconds.
+/// - `end_date`: Stream end date measured in seconds.
+///
+/// Validations:
+/// - If specified, the cliff date (`start_date + cliff`) should not be later than `end_date`.
+///
+/// Returns:
+/// - `start_date + cliff` if specified, `start_date` otherwise.
+///
+/// Errors:
+/// - StreamCliffShouldBeEarlier
+pub fn validate_and_generate_stream_cliff_date(
+    cliff: Option<u64>,
+    start_date: u64,
+    end_date: u64,
+) -> Result<u64, ContractError> {
+    match cliff {
+        Some(cliff) => match start_date.checked_add(cliff) {
+            Some(cliff_date) if cliff_date <= end_date => Ok(cliff_date),
+            _ => Err(ContractError::StreamCliffShouldBeEarlier),
+        },
+        None => Ok(start_date),
     }
+}
 
-    Ok(())
+/// Validates and generate the stream vesting kind based on the `vesting` parameter of the `create_stream` message.
+///
+/// Parameters:
+/// - `vesting`: Vesting kind received as parameter in the `create_stream` message.
+/// - `start_date`: Stream start date measured in seconds.
+/// - `end_date`: Stream end date measured in seconds.
+///
+/// Validations:
+/// - For stepped streams, `interval` should be greater than zero and divide the stream duration evenly.
+///
+/// Returns:
+/// - `vesting` if specified, `VestingKind::Linear` otherwise.
+///
+/// Errors:
+/// - InvalidVestingInterval
+/// - ArithmeticOverflow
+pub fn validate_and_generate_vesting_kind(
+    vesting: Option<VestingKind>,
+    start_date: u64,
+    end_date: u64,
+) -> Result<VestingKind, ContractError> {
+    let duration = end_date
+        .checked_sub(start_date)
+        .ok_or(ContractError::ArithmeticOverflow)?;
+
+    match vesting.unwrap_or_default() {
+        VestingKind::Stepped { interval } if interval == 0 || duration % interval != 0 => {
+            Err(ContractError::InvalidVestingInterval)
+        }
+        vesting => Ok(vesting),
+    }
 }
 
 /// Validates the stream duration based on the `duration` parameter of the `create_stream` message.
 ///
 /// Parameters:
 /// - `duration`: Duration measured in seconds received as parameter in the `create_stream` message.
+/// - `min_duration`: The stream **minimum duration** measured in seconds.
 ///
 /// Validations:
 /// - `duration` should be greater than the stream minimum duration.
@@ -88,34 +215,69 @@ fn validate_stream_end_date(start_date: u64, end_date: u64) -> Result<(), Contra
 ///
 /// NOTES
 /// -----
-/// - The current stream **minimum duration** is 5 minutes.
-fn validate_stream_duration(duration: u64) -> Result<(), ContractError> {
-    if duration < STREAM_MINIMUM_DURATION {
+/// - The **minimum duration** is configured when the contract is instantiated. It is 5 minutes by default.
+pub fn validate_stream_duration(duration: u64, min_duration: u64) -> Result<(), ContractError> {
+    if duration < min_duration {
         return Err(ContractError::StreamDurationShouldBeGreater);
     }
 
     Ok(())
 }
 
+/// Validates that a stream doesn't last longer than the configured maximum.
+///
+/// Parameters:
+/// - `duration`: The stream duration measured in seconds.
+/// - `max_duration`: The stream **maximum duration** measured in seconds. Zero disables the check.
+///
+/// Validations:
+/// - `duration` should not be greater than the stream maximum duration.
+///
+/// Errors:
+/// - StreamDurationTooLong
+fn validate_stream_max_duration(duration: u64, max_duration: u64) -> Result<(), ContractError> {
+    if max_duration > 0 && duration > max_duration {
+        return Err(ContractError::StreamDurationTooLong);
+    }
+
+    Ok(())
+}
+
 /// Validates the `create_stream` message parameters.
 ///
 /// Parameters:
 /// - `payer`: Sender of the `create_stream` message.
 /// - `recipient`: Recipient AccountId received as parameter in the `create_stream` message.
+/// - `contract`: AccountId of the streams contract.
 /// - `funds`: Funds received in the `create_stream` message.
+/// - `min_amount`: Minimum funds of a stream. Zero disables the check.
+/// - `max_amount`: Maximum funds of a stream. Zero disables the check.
 ///
 /// Validations:
+/// - `recipient` can't be the zero account.
+/// - `recipient` can't be the streams contract.
 /// - `payer` should be different than `recipient`.
 /// - `funds` should be greater than 0.
+/// - `funds` should be within the configured amount bounds.
 ///
 /// Errors:
+/// - InvalidRecipient
 /// - RecipientCannotBePayer
 /// - EmptyFunds
+/// - AmountTooSmall
+/// - AmountTooLarge
 pub fn validate_stream_creation_parameters(
     payer: AccountId,
     recipient: AccountId,
+    contract: AccountId,
     funds: u128,
+    min_amount: u128,
+    max_amount: u128,
 ) -> Result<(), ContractError> {
+    if recipient == AccountId::from([0u8; 32]) || recipient == contract {
+        return Err(ContractError::InvalidRecipient);
+    }
+
     if payer == recipient {
         return Err(ContractError::RecipientCannotBePayer);
     }
@@ -124,6 +286,40 @@ pub fn validate_stream_creation_parameters(
         return Err(ContractError::EmptyFunds);
     }
 
+    if min_amount > 0 && funds < min_amount {
+        return Err(ContractError::AmountTooSmall);
+    }
+
+    if max_amount > 0 && funds > max_amount {
+        return Err(ContractError::AmountTooLarge);
+    }
+
+    Ok(())
+}
+
+/// Validates that new funds don't exceed the total value locked cap of the contract.
+///
+/// Parameters:
+/// - `total_locked`: Funds currently locked in streams.
+/// - `new_funds`: Funds to be added to the contract.
+/// - `tvl_cap`: Maximum total value locked. Can be empty.
+///
+/// Validations:
+/// - If `tvl_cap` has value, `total_locked + new_funds` should be lower or equal than `tvl_cap`.
+///
+/// Errors:
+/// - TvlCapExceeded
+pub fn validate_tvl_cap(
+    total_locked: u128,
+    new_funds: u128,
+    tvl_cap: Option<u128>,
+) -> Result<(), ContractError> {
+    if let Some(tvl_cap) = tvl_cap {
+        if total_locked.saturating_add(new_funds) > tvl_cap {
+            return Err(ContractError::TvlCapExceeded);
+        }
+    }
+
     Ok(())
 }
 
@@ -147,7 +343,456 @@ pub fn validate_recipient_withdrawal_amount(
     Ok(())
 }
 
+#[cfg(test)]
+thread_local! {
+    static MOCK_NOW: core::cell::Cell<Option<u64>> = core::cell::Cell::new(None);
+}
+
+/// Sets the time returned by `get_current_time_in_seconds` instead of the block timestamp. Test only.
+///
+/// Parameters:
+/// - `now`: Time measured in seconds, or `None` to go back to the block timestamp.
+#[cfg(test)]
+pub fn set_mock_now(now: Option<u64>) {
+    MOCK_NOW.with(|mock_now| mock_now.set(now));
+}
+
+#[cfg(test)]
+fn mock_now() -> Option<u64> {
+    MOCK_NOW.with(|mock_now| mock_now.get())
+}
+
+#[cfg(not(test))]
+fn mock_now() -> Option<u64> {
+    None
+}
+
 /// Get the time of the current block.
+///
+/// NOTES
+/// -----
+/// - In tests, the time set with `set_mock_now` takes precedence over the block timestamp.
 pub fn get_current_time_in_seconds(contract: &StreamsContract) -> u64 {
+    if let Some(now) = mock_now() {
+        return now;
+    }
+
     contract.env().block_timestamp() / 1000
 }
+
+#[cfg(test)]
+thread_local! {
+    static MOCK_MINIMUM_BALANCE: core::cell::Cell<u128> = core::cell::Cell::new(0);
+}
+
+/// Sets the balance returned by `get_minimum_balance` instead of the chain existential deposit. Test only.
+///
+/// Parameters:
+/// - `minimum_balance`: The existential deposit to be used in the test.
+#[cfg(test)]
+pub fn set_mock_minimum_balance(minimum_balance: u128) {
+    MOCK_MINIMUM_BALANCE.with(|mock_minimum_balance| mock_minimum_balance.set(minimum_balance));
+}
+
+/// Get the minimum balance that an account must keep, i.e. the existential deposit of the chain.
+///
+/// NOTES
+/// -----
+/// - In tests, the balance set with `set_mock_minimum_balance` is used instead, zero by default, so the test balances don't have to cover the existential deposit of the off-chain environment.
+#[cfg(not(test))]
+pub fn get_minimum_balance(contract: &StreamsContract) -> u128 {
+    contract.env().minimum_balance()
+}
+
+#[cfg(test)]
+pub fn get_minimum_balance(_contract: &StreamsContract) -> u128 {
+    MOCK_MINIMUM_BALANCE.with(|mock_minimum_balance| mock_minimum_balance.get())
+}
+
+/// Checks if an account is a contract.
+///
+/// NOTES
+/// -----
+/// - The off-chain test environment doesn't support contract accounts, so every account is a plain account in tests.
+/// - As a consequence, the unit tests never reach the recipient contract notifications, such as `notify_stream_created`.
+#[cfg(not(test))]
+pub fn is_contract_account(contract: &StreamsContract, account: &AccountId) -> bool {
+    contract.env().is_contract(account)
+}
+
+#[cfg(test)]
+pub fn is_contract_account(_contract: &StreamsContract, _account: &AccountId) -> bool {
+    false
+}
+
+/// Multiplies a balance by a ratio of two dates or durations without overflowing the intermediate product.
+///
+/// Parameters:
+/// - `value`: The balance to be multiplied.
+/// - `numerator`: Numerator of the ratio.
+/// - `denominator`: Denominator of the ratio.
+///
+/// Behavior:
+/// - `value` is split into quotient and remainder of `denominator`, so the product is computed in pieces that fit in `u128`.
+/// - The result is rounded down.
+///
+/// Returns:
+/// - `value * numerator / denominator`, or `None` if `denominator` is zero or the result doesn't fit in `u128`.
+pub fn mul_div(value: u128, numerator: u64, denominator: u64) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+
+    let numerator = numerator as u128;
+    let denominator = denominator as u128;
+
+    (value / denominator)
+        .checked_mul(numerator)?
+        .checked_add((value % denominator) * numerator / denominator)
+}
+
+/// Calculates the share of a balance proportional to a part of a `u128` total.
+///
+/// Parameters:
+/// - `value`: The balance to be split.
+/// - `part`: The part of the total the share corresponds to.
+/// - `total`: The total `part` belongs to.
+///
+/// Behavior:
+/// - `part` and `total` are shifted right until `total` fits in `u64`, which keeps the ratio within a relative error of `2^-63`.
+/// - `part` is capped at `total`, so the share never exceeds `value`.
+/// - The result is rounded down.
+///
+/// Returns:
+/// - `value * part / total`, or `None` if `total` is zero.
+pub fn proportional_share(value: u128, part: u128, total: u128) -> Option<u128> {
+    let shift = (128 - total.leading_zeros()).saturating_sub(64);
+
+    mul_div(
+        value,
+        (part.min(total) >> shift) as u64,
+        (total >> shift) as u64,
+    )
+}
+
+/// Hashes the secret presented to claim a claimable stream.
+///
+/// Parameters:
+/// - `preimage`: The secret shared by the payer with the future recipient.
+///
+/// Returns:
+/// - The BLAKE2b 256-bit hash of `preimage`.
+pub fn hash_claim_preimage(preimage: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    ink_env::hash_bytes::<ink_env::hash::Blake2x256>(preimage, &mut hash);
+    hash
+}
+
+/// Hashes the commitment made before claiming a claimable stream.
+///
+/// Parameters:
+/// - `preimage`: The secret shared by the payer with the future recipient.
+/// - `claimer`: The account that will claim the stream.
+///
+/// Returns:
+/// - The BLAKE2b 256-bit hash of `preimage` followed by the 32 bytes of `claimer`.
+pub fn hash_claim_commitment(preimage: &[u8], claimer: &AccountId) -> [u8; 32] {
+    let mut input = Vec::with_capacity(preimage.len() + 32);
+    input.extend_from_slice(preimage);
+    input.extend_from_slice(&scale::Encode::encode(claimer));
+
+    hash_claim_preimage(&input)
+}
+
+/// Calculates the amount of a balance vested linearly after some time, rounding down.
+///
+/// Parameters:
+/// - `original`: The balance vested over `duration`.
+/// - `elapsed`: Time elapsed since the vesting started. Measured in seconds.
+/// - `duration`: Total vesting duration. Measured in seconds.
+///
+/// Returns:
+/// - `original * elapsed / duration`, rounding down.
+/// - `original` once `elapsed` reaches `duration`, or if `duration` is zero.
+///
+/// NOTES
+/// -----
+/// - Rounding down favors the payer: the recipient gets at most one token less than the exact linear share until the end.
+pub fn vested_amount(original: u128, elapsed: u64, duration: u64) -> u128 {
+    if elapsed >= duration {
+        return original;
+    }
+
+    // It can't overflow because the elapsed time is lower than the duration.
+    mul_div(original, elapsed, duration).unwrap_or(original)
+}
+
+/// Calculates the amount of a balance vested linearly after some time, rounding half up.
+///
+/// Parameters:
+/// - `original`: The balance vested over `duration`.
+/// - `elapsed`: Time elapsed since the vesting started. Measured in seconds.
+/// - `duration`: Total vesting duration. Measured in seconds.
+///
+/// Returns:
+/// - `original * elapsed / duration`, rounding to the nearest integer and halves up.
+/// - `original` once `elapsed` reaches `duration`, or if `duration` is zero.
+///
+/// NOTES
+/// -----
+/// - The result never exceeds `original`, since the exact share is strictly lower before the end.
+pub fn vested_amount_half_up(original: u128, elapsed: u64, duration: u64) -> u128 {
+    if elapsed >= duration {
+        return original;
+    }
+
+    let rounded_down = vested_amount(original, elapsed, duration);
+    let duration = duration as u128;
+    // It can't overflow because both factors are lower than `u64::MAX`.
+    let remainder = (original % duration) * elapsed as u128 % duration;
+
+    if remainder * 2 >= duration {
+        rounded_down + 1
+    } else {
+        rounded_down
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vested_amount_rounds_down() {
+        for (original, elapsed, duration, expected) in [
+            (1000, 150, 300, 500),
+            (1000, 100, 300, 333),
+            (1000, 200, 300, 666),
+            (1001, 150, 300, 500),
+            (7, 1, 2, 3),
+            (1, 299, 300, 0),
+            (1000, 300, 300, 1000),
+            (1000, 400, 300, 1000),
+            (1000, 0, 0, 1000),
+            (u128::MAX, 1, 3, u128::MAX / 3),
+        ] {
+            assert_eq!(
+                vested_amount(original, elapsed, duration),
+                expected,
+                "{} * {} / {}",
+                original,
+                elapsed,
+                duration
+            );
+        }
+    }
+
+    #[test]
+    fn vested_amount_half_up_rounds_to_nearest() {
+        for (original, elapsed, duration, expected) in [
+            (1000, 150, 300, 500),
+            (1000, 100, 300, 333),
+            (1000, 200, 300, 667),
+            (1001, 150, 300, 501),
+            (7, 1, 2, 4),
+            (1, 149, 300, 0),
+            (1, 150, 300, 1),
+            (1, 299, 300, 1),
+            (1000, 300, 300, 1000),
+            (1000, 0, 0, 1000),
+            (u128::MAX, 2, 3, u128::MAX / 3 * 2),
+        ] {
+            assert_eq!(
+                vested_amount_half_up(original, elapsed, duration),
+                expected,
+                "{} * {} / {}",
+                original,
+                elapsed,
+                duration
+            );
+        }
+    }
+
+    #[test]
+    fn proportional_share_works() {
+        for (value, part, total, expected) in [
+            (1000, 1, 4, 250),
+            (1000, 1, 3, 333),
+            (1000, 4, 4, 1000),
+            (1000, 5, 4, 1000),
+            (1000, 0, 4, 0),
+            (u128::MAX, u128::MAX, u128::MAX, u128::MAX),
+            (
+                10u128.pow(24),
+                10u128.pow(24),
+                4 * 10u128.pow(24),
+                10u128.pow(24) / 4,
+            ),
+        ] {
+            assert_eq!(
+                proportional_share(value, part, total),
+                Some(expected),
+                "{} * {} / {}",
+                value,
+                part,
+                total
+            );
+        }
+        assert_eq!(proportional_share(1000, 0, 0), None);
+    }
+
+    #[test]
+    fn checked_end_date_works() {
+        // Act
+        let result = checked_end_date(100, 300);
+
+        // Assert
+        assert_eq!(result, Ok(400));
+    }
+
+    #[test]
+    fn checked_end_date_overflow_fails() {
+        // Act
+        let result = checked_end_date(1, u64::MAX);
+
+        // Assert
+        assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn generate_stream_start_date_from_offset_works() {
+        // Act
+        let from_offset = generate_stream_start_date_from_offset(None, Some(3600), 1000);
+        let from_start_date = generate_stream_start_date_from_offset(Some(2000), None, 1000);
+        let overflow = generate_stream_start_date_from_offset(None, Some(u64::MAX), 1000);
+
+        // Assert
+        assert_eq!(from_offset, Ok(Some(4600)));
+        assert_eq!(from_start_date, Ok(Some(2000)));
+        assert_eq!(overflow, Err(ContractError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_with_max_duration_fails() {
+        // Act
+        let result = validate_and_generate_stream_end_date(None, Some(u64::MAX), 1, 300, 0);
+
+        // Assert
+        assert_eq!(result, Err(ContractError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_within_max_duration_works() {
+        // Act
+        let from_duration = validate_and_generate_stream_end_date(None, Some(1000), 100, 300, 1000);
+        let from_end_date = validate_and_generate_stream_end_date(Some(1100), None, 100, 300, 1000);
+
+        // Assert
+        assert_eq!(from_duration, Ok(1100));
+        assert_eq!(from_end_date, Ok(1100));
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_over_max_duration_fails() {
+        // Act
+        let from_duration = validate_and_generate_stream_end_date(None, Some(1001), 100, 300, 1000);
+        let from_end_date = validate_and_generate_stream_end_date(Some(1101), None, 100, 300, 1000);
+
+        // Assert
+        assert_eq!(from_duration, Err(ContractError::StreamDurationTooLong));
+        assert_eq!(from_end_date, Err(ContractError::StreamDurationTooLong));
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_at_min_duration_works() {
+        // Act
+        let from_duration = validate_and_generate_stream_end_date(None, Some(300), 100, 300, 0);
+        let from_end_date = validate_and_generate_stream_end_date(Some(400), None, 100, 300, 0);
+
+        // Assert
+        assert_eq!(from_duration, Ok(400));
+        assert_eq!(from_end_date, Ok(400));
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_below_min_duration_fails() {
+        // Act
+        let from_duration = validate_and_generate_stream_end_date(None, Some(299), 100, 300, 0);
+        let from_end_date = validate_and_generate_stream_end_date(Some(399), None, 100, 300, 0);
+
+        // Assert
+        assert_eq!(
+            from_duration,
+            Err(ContractError::StreamDurationShouldBeGreater)
+        );
+        assert_eq!(
+            from_end_date,
+            Err(ContractError::StreamDurationShouldBeGreater)
+        );
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_in_past_fails() {
+        // Act
+        let before_start_date = validate_and_generate_stream_end_date(Some(99), None, 100, 300, 0);
+        let at_start_date = validate_and_generate_stream_end_date(Some(100), None, 100, 300, 0);
+        let just_after_start_date =
+            validate_and_generate_stream_end_date(Some(101), None, 100, 300, 0);
+
+        // Assert
+        assert_eq!(before_start_date, Err(ContractError::StreamEndDateInPast));
+        assert_eq!(at_start_date, Err(ContractError::StreamEndDateInPast));
+        assert_eq!(
+            just_after_start_date,
+            Err(ContractError::StreamDurationShouldBeGreater)
+        );
+    }
+
+    #[test]
+    fn validate_and_generate_stream_end_date_is_total() {
+        // Arrange
+        let dates = [
+            None,
+            Some(0),
+            Some(99),
+            Some(100),
+            Some(400),
+            Some(1100),
+            Some(u64::MAX),
+        ];
+        let durations = [
+            None,
+            Some(0),
+            Some(299),
+            Some(300),
+            Some(1000),
+            Some(u64::MAX),
+        ];
+
+        for end_date in dates {
+            for duration in durations {
+                // Act
+                let result =
+                    validate_and_generate_stream_end_date(end_date, duration, 100, 300, 1000);
+
+                // Assert
+                assert!(
+                    matches!(
+                        result,
+                        Ok(_)
+                            | Err(ContractError::EndDateAndDurationAreEmpty)
+                            | Err(ContractError::EndDateDurationConflict)
+                            | Err(ContractError::StreamEndDateInPast)
+                            | Err(ContractError::StreamDurationShouldBeGreater)
+                            | Err(ContractError::StreamDurationTooLong)
+                            | Err(ContractError::ArithmeticOverflow)
+                    ),
+                    "{:?} for end_date {:?} and duration {:?}",
+                    result,
+                    end_date,
+                    duration
+                );
+            }
+        }
+    }
+}